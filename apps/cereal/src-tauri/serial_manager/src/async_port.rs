@@ -0,0 +1,156 @@
+//! # Async Serial Port
+//!
+//! An open serial port whose reads and writes can be awaited from tokio tasks.
+//! Available with the `tokio` feature.
+//!
+//! The serialport crate only offers blocking I/O, so every operation runs on
+//! tokio's blocking pool. Reads use a short port timeout and are retried until
+//! data arrives, which keeps each blocking call brief. Reading and writing go
+//! through separate handles to the same device, so a pending read never holds
+//! up a write.
+//!
+//! ## Cancellation
+//!
+//! Dropping a read() future is safe: bytes that arrive after the future was
+//! dropped are kept and returned by the next call to read().
+
+use std::{
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use serialport::SerialPort;
+use crate::error::{Result, SerialManagerError};
+
+/// How long a single blocking read waits before giving the runtime a chance to cancel
+const READ_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reading half of the port, with bytes read on behalf of cancelled futures
+struct Reader {
+    port: Mutex<Box<dyn SerialPort>>,
+    pending: Mutex<Vec<u8>>,
+}
+
+impl Reader {
+    /// Moves up to `max` pending bytes into `buf`, returning how many were moved.
+    fn take_pending(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut pending = self.pending.lock().map_err(|_| lock_error())?;
+        let count = pending.len().min(buf.len());
+        buf[..count].copy_from_slice(&pending[..count]);
+        pending.drain(..count);
+        Ok(count)
+    }
+
+    /// Blocks for at most READ_POLL_INTERVAL, appending whatever arrives to pending.
+    fn fill(&self, max: usize) -> io::Result<()> {
+        let mut port = self.port.lock().map_err(|_| lock_error())?;
+        let mut chunk = vec![0; max];
+        match port.read(&mut chunk) {
+            Ok(count) => {
+                let mut pending = self.pending.lock().map_err(|_| lock_error())?;
+                pending.extend_from_slice(&chunk[..count]);
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A serial port opened for async reading and writing
+pub struct AsyncSerialPort {
+    port_name: String,
+    reader: Arc<Reader>,
+    writer: Arc<Mutex<Box<dyn SerialPort>>>,
+}
+
+impl AsyncSerialPort {
+    /// Opens a port with 8N1 framing and no flow control.
+    ///
+    /// Fails with SerialManagerError::PortBusy if another process has the port
+    /// open, and SerialManagerError::PermissionDenied if the user may not open it.
+    ///
+    /// # Arguments
+    ///
+    /// * `port_name` - Operating system name of the port (e.g. "/dev/ttyACM0", "COM3")
+    /// * `baud_rate` - Line speed in bits per second
+    ///
+    /// # Example
+    ///
+    /// ```rust ignore
+    /// let port = AsyncSerialPort::open("/dev/ttyACM0", 115_200).await?;
+    /// port.write_all(b"version\r\n").await?;
+    ///
+    /// let mut buf = [0; 256];
+    /// let count = port.read(&mut buf).await?;
+    /// ```
+    pub async fn open(port_name: &str, baud_rate: u32) -> Result<Self> {
+        let name = port_name.to_string();
+        let (reader, writer) = spawn_blocking(move || {
+            let reader = serialport::new(&name, baud_rate)
+                .timeout(READ_POLL_INTERVAL)
+                .open()?;
+            let writer = reader.try_clone()?;
+            Ok((reader, writer))
+        })
+        .await
+        .map_err(|e| SerialManagerError::from_port_io(port_name, e))?;
+
+        Ok(AsyncSerialPort {
+            port_name: port_name.to_string(),
+            reader: Arc::new(Reader {
+                port: Mutex::new(reader),
+                pending: Mutex::new(Vec::new()),
+            }),
+            writer: Arc::new(Mutex::new(writer)),
+        })
+    }
+
+    /// Returns the operating system name of the port.
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    /// Waits until at least one byte is available and reads as many as fit in `buf`.
+    ///
+    /// Returns Ok(0) only if `buf` is empty.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let count = self.reader.take_pending(buf)?;
+            if count > 0 {
+                return Ok(count);
+            }
+            let reader = self.reader.clone();
+            let max = buf.len();
+            spawn_blocking(move || reader.fill(max)).await?;
+        }
+    }
+
+    /// Writes the whole buffer to the port and waits until it has been handed to the driver.
+    pub async fn write_all(&self, data: &[u8]) -> io::Result<()> {
+        let writer = self.writer.clone();
+        let data = data.to_vec();
+        spawn_blocking(move || {
+            let mut port = writer.lock().map_err(|_| lock_error())?;
+            port.write_all(&data)?;
+            port.flush()
+        })
+        .await
+    }
+}
+
+/// Runs a blocking port operation on the blocking pool.
+async fn spawn_blocking<T: Send + 'static>(
+    operation: impl FnOnce() -> io::Result<T> + Send + 'static
+) -> io::Result<T> {
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(io::Error::other)?
+}
+
+fn lock_error() -> io::Error {
+    io::Error::other("serial port lock poisoned")
+}
@@ -0,0 +1,212 @@
+//! # Baud Rate Detection
+//!
+//! Listens to a port at each candidate baud rate in turn and ranks the rates by
+//! how much the data looks like what a device would send on purpose.
+//!
+//! At the wrong rate a UART samples bits in the wrong places: characters come
+//! out as random bytes, and characters whose stop bit is not where it should be
+//! are framing errors, which Linux and Windows deliver as NUL bytes by default.
+//! The serialport crate does not report line errors, so NULs stand in for them.
+//!
+//! ## Scoring
+//!
+//! Each sample is scored on three measures between 0 and 1:
+//!
+//! - **Printable ratio**: share of bytes that are printable ASCII, whitespace or valid UTF-8
+//! - **Error ratio**: share of NUL bytes and bytes that are not valid UTF-8
+//! - **Newline regularity**: share of bytes in lines of sensible length
+//!
+//! The confidence combines them and shrinks for samples too small to judge, so
+//! a silent device ranks every rate close to zero. The measures assume the
+//! device sends text, like boot logs or a shell prompt; binary protocols cannot
+//! be told apart this way.
+//!
+//! ```rust ignore
+//! let ranked = serial_manager::detect_baud("/dev/ttyUSB0", &[], Duration::from_millis(500))?;
+//! if let Some(best) = ranked.first() {
+//!     println!("{} baud ({:.0}% confident)", best.baud_rate, best.confidence * 100.0);
+//! }
+//! ```
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+use serde::{Deserialize, Serialize};
+use serialport::ClearBuffer;
+use crate::error::{Result, SerialManagerError};
+
+/// Rates tried when no candidates are given, most common first
+pub const COMMON_BAUD_RATES: &[u32] = &[
+    115_200, 9_600, 57_600, 38_400, 19_200, 230_400, 460_800, 921_600, 74_880, 4_800, 2_400, 1_200, 250_000,
+    1_000_000,
+];
+
+/// Samples with fewer bytes than this get proportionally less confidence
+const CONFIDENT_SAMPLE_SIZE: usize = 64;
+
+/// Longest line, in bytes, that still counts as regular
+const MAX_REGULAR_LINE: usize = 256;
+
+/// How long a single read waits while sampling
+const SAMPLE_READ_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// How a sample of received data scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BaudScore {
+    /// Number of bytes in the sample
+    pub bytes: usize,
+    /// Share of bytes that are printable text
+    pub printable_ratio: f64,
+    /// Share of bytes that look like framing errors or garbage
+    pub error_ratio: f64,
+    /// Share of bytes in newline-terminated lines of at most 256 bytes
+    pub newline_regularity: f64,
+}
+
+impl BaudScore {
+    /// Scores a sample of bytes received at one baud rate.
+    pub fn of(sample: &[u8]) -> Self {
+        if sample.is_empty() {
+            return BaudScore::default();
+        }
+
+        let mut printable = 0;
+        let mut errors = 0;
+        let mut rest = sample;
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    let (ok, bad) = count_text(text);
+                    printable += ok;
+                    errors += bad;
+                    break;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    let (ok, bad) = count_text(std::str::from_utf8(&rest[..valid]).unwrap_or_default());
+                    printable += ok;
+                    errors += bad;
+                    // A sequence cut off by the end of the sample is not an error
+                    let invalid = e.error_len().unwrap_or(rest.len() - valid);
+                    if e.error_len().is_some() {
+                        errors += invalid;
+                    }
+                    rest = &rest[valid + invalid..];
+                }
+            }
+        }
+
+        let regular: usize = sample
+            .split_inclusive(|&byte| byte == b'\n' || byte == b'\r')
+            .filter(|line| matches!(line.last(), Some(b'\n' | b'\r')) && line.len() <= MAX_REGULAR_LINE)
+            .map(<[u8]>::len)
+            .sum();
+
+        let total = sample.len() as f64;
+        BaudScore {
+            bytes: sample.len(),
+            printable_ratio: printable as f64 / total,
+            error_ratio: errors as f64 / total,
+            newline_regularity: regular as f64 / total,
+        }
+    }
+
+    /// Combines the measures into a confidence between 0 and 1.
+    pub fn confidence(&self) -> f64 {
+        let quality = 0.6 * self.printable_ratio + 0.2 * (1.0 - self.error_ratio) + 0.2 * self.newline_regularity;
+        let size = (self.bytes as f64 / CONFIDENT_SAMPLE_SIZE as f64).min(1.0);
+        // Garbage is rarely more than half printable, so only clean text scores high
+        (quality * quality * size).clamp(0.0, 1.0)
+    }
+}
+
+/// Counts the bytes of valid UTF-8 text that are printable and those that are NUL or control codes.
+fn count_text(text: &str) -> (usize, usize) {
+    let mut printable = 0;
+    let mut errors = 0;
+    for c in text.chars() {
+        match c {
+            '\0' => errors += 1,
+            '\t' | '\n' | '\r' => printable += 1,
+            c if c.is_control() => {}
+            c => printable += c.len_utf8(),
+        }
+    }
+    (printable, errors)
+}
+
+/// A baud rate and how likely it is to be the right one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BaudCandidate {
+    /// The rate the sample was received at
+    pub baud_rate: u32,
+    /// Between 0 and 1, higher is more likely
+    pub confidence: f64,
+    /// The measures the confidence was derived from
+    pub score: BaudScore,
+}
+
+/// Ranks samples received at different baud rates, most likely rate first.
+///
+/// This is the scoring half of detect_baud(), for samples recorded elsewhere.
+/// Equal confidences keep the order of the samples.
+pub fn rank_samples<'a>(samples: impl IntoIterator<Item = (u32, &'a [u8])>) -> Vec<BaudCandidate> {
+    let mut candidates: Vec<BaudCandidate> = samples
+        .into_iter()
+        .map(|(baud_rate, sample)| {
+            let score = BaudScore::of(sample);
+            BaudCandidate { baud_rate, confidence: score.confidence(), score }
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
+
+/// Listens to a port at each candidate rate and ranks the rates, most likely first.
+///
+/// The port is opened with 8N1 framing and no flow control, and listened to
+/// for `duration` at each rate, so this takes `duration` times the number of
+/// candidates. The device has to be sending while it runs.
+///
+/// # Arguments
+///
+/// * `port_name` - Operating system name of the port (e.g. "/dev/ttyUSB0", "COM3")
+/// * `candidates` - Rates to try, or an empty slice for COMMON_BAUD_RATES
+/// * `duration` - How long to listen at each rate
+///
+/// # Errors
+///
+/// Fails like SerialManager::open() if the port cannot be opened, including
+/// with SerialManagerError::PortBusy while a session has it open.
+pub fn detect_baud(port_name: &str, candidates: &[u32], duration: Duration) -> Result<Vec<BaudCandidate>> {
+    let candidates = if candidates.is_empty() { COMMON_BAUD_RATES } else { candidates };
+    let port_error = |e| SerialManagerError::from_serialport(port_name, e);
+
+    let mut port = serialport::new(port_name, candidates[0])
+        .timeout(SAMPLE_READ_TIMEOUT)
+        .open()
+        .map_err(port_error)?;
+
+    let mut samples = Vec::with_capacity(candidates.len());
+    for &baud_rate in candidates {
+        port.set_baud_rate(baud_rate).map_err(port_error)?;
+        // Bytes still queued were received at the previous rate
+        port.clear(ClearBuffer::Input).map_err(port_error)?;
+
+        let mut sample = Vec::new();
+        let mut chunk = [0; 1024];
+        let started = Instant::now();
+        while started.elapsed() < duration {
+            match port.read(&mut chunk) {
+                Ok(count) => sample.extend_from_slice(&chunk[..count]),
+                Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+                Err(e) => return Err(SerialManagerError::from_port_io(port_name, e)),
+            }
+        }
+        log::debug!("Sampled {} bytes from {} at {} baud", sample.len(), port_name, baud_rate);
+        samples.push((baud_rate, sample));
+    }
+
+    Ok(rank_samples(samples.iter().map(|(baud_rate, sample)| (*baud_rate, sample.as_slice()))))
+}
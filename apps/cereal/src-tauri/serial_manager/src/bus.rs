@@ -0,0 +1,359 @@
+//! # Serial Event Bus
+//!
+//! A broadcast channel for SerialEvents. Every subscriber gets its own bounded
+//! queue, so the logger, the auto-connect logic and the UI forwarder can each
+//! consume events at their own pace without stealing events from one another.
+//!
+//! ## Slow Subscribers
+//!
+//! Publishing never blocks. When a subscriber's queue is full, its oldest event
+//! is dropped to make room and a lag counter is incremented. The next call to
+//! recv() on that subscriber returns RecvError::Lagged with the number of events
+//! it missed, after which delivery continues with the events still queued.
+//!
+//! Received data is treated more carefully, because losing it silently would
+//! make a capture worthless. When a DataReceived event finds the queue full and
+//! the newest queued event is data from the same session, the new bytes are
+//! appended to it instead, up to MAX_COALESCED_BYTES. Only when that is not
+//! possible is the oldest event dropped. Either way the bus's overflow handler
+//! is told, which is how sessions keep count of their coalesced and dropped chunks.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, RwLock, Weak},
+    task::Waker,
+    time::{Duration, Instant},
+};
+use crate::manager::SerialEvent;
+
+/// Default number of events buffered per subscriber
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 256;
+
+/// Largest DataReceived event a full queue builds by appending chunks to one another
+pub const MAX_COALESCED_BYTES: usize = 64 * 1024;
+
+/// What a full subscriber queue did to make room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The event was dropped without being delivered
+    Dropped,
+    /// The event was appended to the newest queued event, which has the
+    /// timestamp of the first chunk it holds
+    Coalesced,
+}
+
+/// Called with the affected event whenever a subscriber queue overflows
+pub type OverflowHandler = Box<dyn Fn(&SerialEvent, Overflow) + Send + Sync>;
+
+/// Why an event could not be received
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The subscriber fell behind and this many events were dropped
+    Lagged(u64),
+    /// The bus was dropped and no more events will arrive
+    Closed,
+}
+
+/// Why an event could not be received without blocking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No event is currently queued
+    Empty,
+    /// The subscriber fell behind and this many events were dropped
+    Lagged(u64),
+    /// The bus was dropped and no more events will arrive
+    Closed,
+}
+
+impl std::fmt::Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvError::Lagged(missed) => write!(f, "subscriber lagged behind, {} events dropped", missed),
+            RecvError::Closed => write!(f, "event bus closed"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+struct QueueState {
+    events: VecDeque<SerialEvent>,
+    capacity: usize,
+    lagged: u64,
+    closed: bool,
+    /// Task to wake when an event arrives, if the receiver is polled as a stream
+    waker: Option<Waker>,
+}
+
+struct SubscriberQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl SubscriberQueue {
+    fn push(&self, event: SerialEvent, overflow: Option<&OverflowHandler>) {
+        let Ok(mut state) = self.state.lock() else { return };
+        if state.events.len() >= state.capacity && coalesce(&mut state.events, &event) {
+            if let Some(overflow) = overflow {
+                overflow(&event, Overflow::Coalesced);
+            }
+        } else {
+            while state.events.len() >= state.capacity {
+                let Some(dropped) = state.events.pop_front() else { break };
+                state.lagged += 1;
+                if let Some(overflow) = overflow {
+                    overflow(&dropped, Overflow::Dropped);
+                }
+            }
+            state.events.push_back(event);
+        }
+        self.ready.notify_one();
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+            self.ready.notify_all();
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Appends received data to the newest queued event if it is data from the
+/// same session with room to spare. Returns true if it did.
+fn coalesce(events: &mut VecDeque<SerialEvent>, event: &SerialEvent) -> bool {
+    let SerialEvent::DataReceived { session, bytes, .. } = event else { return false };
+    match events.back_mut() {
+        Some(SerialEvent::DataReceived { session: queued_session, bytes: queued_bytes, .. })
+            if queued_session == session && queued_bytes.len() + bytes.len() <= MAX_COALESCED_BYTES =>
+        {
+            queued_bytes.extend_from_slice(bytes);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Broadcasts SerialEvents to any number of independent subscribers
+pub struct EventBus {
+    subscribers: Mutex<Vec<Weak<SubscriberQueue>>>,
+    overflow: RwLock<Option<OverflowHandler>>,
+}
+
+impl EventBus {
+    /// Creates a bus with no subscribers.
+    pub fn new() -> Self {
+        EventBus {
+            subscribers: Mutex::new(Vec::new()),
+            overflow: RwLock::new(None),
+        }
+    }
+
+    /// Sets the function told about every event a full subscriber queue drops
+    /// or coalesces, replacing any previous one.
+    ///
+    /// The handler runs while the bus is publishing, so it must not publish itself.
+    pub fn set_overflow_handler(&self, handler: OverflowHandler) {
+        if let Ok(mut overflow) = self.overflow.write() {
+            *overflow = Some(handler);
+        }
+    }
+
+    /// Registers a new subscriber whose queue starts with the given events.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of queued events before the oldest are dropped
+    /// * `initial` - Events delivered before anything published after this call
+    pub fn subscribe(&self, capacity: usize, initial: Vec<SerialEvent>) -> EventReceiver {
+        // The snapshot is always delivered in full, even if it exceeds the requested capacity
+        let capacity = capacity.max(initial.len()).max(1);
+        let queue = Arc::new(SubscriberQueue {
+            state: Mutex::new(QueueState {
+                capacity,
+                events: initial.into(),
+                lagged: 0,
+                closed: false,
+                waker: None,
+            }),
+            ready: Condvar::new(),
+        });
+
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Arc::downgrade(&queue));
+        }
+
+        EventReceiver { queue, capacity }
+    }
+
+    /// Delivers an event to every live subscriber. Never blocks on slow subscribers.
+    pub fn publish(&self, event: SerialEvent) {
+        let Ok(mut subscribers) = self.subscribers.lock() else { return };
+        let overflow = self.overflow.read().ok();
+        let overflow = overflow.as_ref().and_then(|overflow| overflow.as_ref());
+        subscribers.retain(|subscriber| match subscriber.upgrade() {
+            Some(queue) => {
+                queue.push(event.clone(), overflow);
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Returns the number of live subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        match self.subscribers.lock() {
+            Ok(subscribers) => subscribers.iter().filter(|s| s.strong_count() > 0).count(),
+            Err(_) => 0,
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for EventBus {
+    fn drop(&mut self) {
+        if let Ok(subscribers) = self.subscribers.lock() {
+            for queue in subscribers.iter().filter_map(Weak::upgrade) {
+                queue.close();
+            }
+        }
+    }
+}
+
+/// A subscriber's end of the EventBus
+///
+/// Dropping the receiver unsubscribes it.
+pub struct EventReceiver {
+    queue: Arc<SubscriberQueue>,
+    capacity: usize,
+}
+
+impl EventReceiver {
+    /// Blocks until the next event arrives.
+    ///
+    /// Returns RecvError::Lagged once if events were dropped since the last call,
+    /// and RecvError::Closed once the bus is gone and the queue is drained.
+    pub fn recv(&self) -> Result<SerialEvent, RecvError> {
+        let Ok(mut state) = self.queue.state.lock() else {
+            return Err(RecvError::Closed);
+        };
+        loop {
+            if let Some(result) = Self::take(&mut state) {
+                return result;
+            }
+            state = match self.queue.ready.wait(state) {
+                Ok(state) => state,
+                Err(_) => return Err(RecvError::Closed),
+            };
+        }
+    }
+
+    /// Waits up to `timeout` for the next event.
+    ///
+    /// Returns Ok(None) if nothing arrived in time.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<SerialEvent>, RecvError> {
+        let deadline = Instant::now() + timeout;
+        let Ok(mut state) = self.queue.state.lock() else {
+            return Err(RecvError::Closed);
+        };
+        loop {
+            if let Some(result) = Self::take(&mut state) {
+                return result.map(Some);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            state = match self.queue.ready.wait_timeout(state, remaining) {
+                Ok((state, _)) => state,
+                Err(_) => return Err(RecvError::Closed),
+            };
+        }
+    }
+
+    /// Returns the next event if one is already queued.
+    pub fn try_recv(&self) -> Result<SerialEvent, TryRecvError> {
+        let Ok(mut state) = self.queue.state.lock() else {
+            return Err(TryRecvError::Closed);
+        };
+        match Self::take(&mut state) {
+            Some(Ok(event)) => Ok(event),
+            Some(Err(RecvError::Lagged(missed))) => Err(TryRecvError::Lagged(missed)),
+            Some(Err(RecvError::Closed)) => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns the maximum number of events this subscriber buffers.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Converts this receiver into a futures Stream of events.
+    ///
+    /// The stream ends when the bus is closed. Lag notifications cannot be
+    /// expressed as a SerialEvent, so they are logged and skipped.
+    #[cfg(feature = "tokio")]
+    pub fn into_stream(self) -> EventStream {
+        EventStream { receiver: self }
+    }
+
+    fn take(state: &mut QueueState) -> Option<Result<SerialEvent, RecvError>> {
+        if state.lagged > 0 {
+            let missed = std::mem::take(&mut state.lagged);
+            return Some(Err(RecvError::Lagged(missed)));
+        }
+        if let Some(event) = state.events.pop_front() {
+            return Some(Ok(event));
+        }
+        if state.closed {
+            return Some(Err(RecvError::Closed));
+        }
+        None
+    }
+}
+
+/// A futures Stream over a subscriber's events, see EventReceiver::into_stream()
+#[cfg(feature = "tokio")]
+pub struct EventStream {
+    receiver: EventReceiver,
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for EventStream {
+    type Item = SerialEvent;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<SerialEvent>> {
+        use std::task::Poll;
+
+        let Ok(mut state) = self.receiver.queue.state.lock() else {
+            return Poll::Ready(None);
+        };
+        loop {
+            match EventReceiver::take(&mut state) {
+                Some(Ok(event)) => return Poll::Ready(Some(event)),
+                Some(Err(RecvError::Lagged(missed))) => {
+                    log::warn!("Event stream lagged behind, {} events dropped", missed);
+                }
+                Some(Err(RecvError::Closed)) => return Poll::Ready(None),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! # Capture Files
+//!
+//! A capture is a raw recording of a session: every chunk it read from and
+//! wrote to the port, in the order it happened, with when it happened. Start
+//! one with SessionHandle::start_capture(), read it back with a CaptureReader,
+//! and replay it through a virtual port with SerialManager::replay_capture(),
+//! so that a problem seen in the field can be reproduced on a desk.
+//!
+//! ## Format
+//!
+//! A capture file is a header followed by any number of chunk records. All
+//! integers are little-endian.
+//!
+//! | Header field   | Size | Contents                                          |
+//! |----------------|------|---------------------------------------------------|
+//! | magic          | 4    | `SMCP`                                            |
+//! | version        | 2    | 1                                                 |
+//! | baud rate      | 4    | bits per second                                   |
+//! | data bits      | 1    | 5 to 8                                            |
+//! | parity         | 1    | 0 none, 1 odd, 2 even                             |
+//! | stop bits      | 1    | 1 or 2                                            |
+//! | flow control   | 1    | 0 none, 1 software, 2 hardware                    |
+//! | started at     | 8    | wall clock time, nanoseconds since the Unix epoch |
+//! | port name size | 2    | length of the port name in bytes                  |
+//! | port name      | n    | UTF-8                                             |
+//!
+//! | Record field   | Size | Contents                                          |
+//! |----------------|------|---------------------------------------------------|
+//! | direction      | 1    | 0 received, 1 sent                                |
+//! | elapsed        | 8    | monotonic nanoseconds since the capture started   |
+//! | size           | 4    | length of the data in bytes                       |
+//! | data           | n    | the bytes as read or written                      |
+//!
+//! Records are written in the order the session handled them. Reads and
+//! writes happen on different threads, so elapsed can step back slightly
+//! from a received record to a sent one and vice versa.
+//!
+//! A capture of a process that crashed may end in the middle of a record.
+//! Reading it yields every complete record and then an error of kind
+//! io::ErrorKind::UnexpectedEof.
+//!
+//! ```rust ignore
+//! let mut capture = CaptureReader::open("field-bug.smcp")?;
+//! println!("{} at {} baud", capture.header().port_name, capture.header().config.baud_rate);
+//! for record in &mut capture {
+//!     let record = record?;
+//!     println!("{:>10.3?} {:?} {:?}", record.elapsed, record.direction, record.bytes);
+//! }
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use serde::{Deserialize, Serialize};
+use crate::error::{Result, SerialManagerError};
+use crate::session::{DataBits, FlowControl, Parity, PortConfig, StopBits};
+use crate::timeline::Direction;
+
+/// First bytes of every capture file
+const MAGIC: &[u8; 4] = b"SMCP";
+
+/// Version of the format written by CaptureWriter
+const VERSION: u16 = 1;
+
+/// Describes the session a capture was taken from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureHeader {
+    /// Operating system name of the port when the capture started
+    pub port_name: String,
+    /// Line settings of the session. Only the baud rate, data bits, parity,
+    /// stop bits and flow control are captured, the rest are defaults when read.
+    pub config: PortConfig,
+    /// Wall clock time the capture started at
+    pub started_at: SystemTime,
+}
+
+/// A chunk of data read from or written to the port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    /// Whether the data was received or sent
+    pub direction: Direction,
+    /// Monotonic time since the capture started
+    pub elapsed: Duration,
+    /// The data as it was read or written
+    pub bytes: Vec<u8>,
+}
+
+/// How fast a capture is replayed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ReplayTiming {
+    /// With the delays between records as they were captured
+    Original,
+    /// With the delays divided by a factor, e.g. 2.0 replays at twice the speed
+    Scaled(f64),
+    /// Without any delays
+    AsFastAsPossible,
+}
+
+impl ReplayTiming {
+    /// Returns when a record captured at `elapsed` is due, counted from the start of the replay.
+    pub(crate) fn due(&self, elapsed: Duration) -> Duration {
+        match *self {
+            ReplayTiming::Original => elapsed,
+            ReplayTiming::Scaled(factor) if factor > 0.0 => elapsed.div_f64(factor),
+            ReplayTiming::Scaled(_) | ReplayTiming::AsFastAsPossible => Duration::ZERO,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> SerialManagerError {
+    io::Error::new(io::ErrorKind::InvalidData, message.into()).into()
+}
+
+/// Writes a capture in the format described in the module documentation
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Writes the header and returns a writer for the records.
+    pub fn new(mut writer: W, header: &CaptureHeader) -> Result<Self> {
+        let config = &header.config;
+        let started_at = header.started_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        let port_name = header.port_name.as_bytes();
+        let port_name_size = u16::try_from(port_name.len()).map_err(|_| invalid("port name is too long"))?;
+
+        let mut bytes = Vec::with_capacity(24 + port_name.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&config.baud_rate.to_le_bytes());
+        bytes.push(match config.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        });
+        bytes.push(match config.parity {
+            Parity::None => 0,
+            Parity::Odd => 1,
+            Parity::Even => 2,
+        });
+        bytes.push(match config.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        });
+        bytes.push(match config.flow_control {
+            FlowControl::None => 0,
+            FlowControl::Software => 1,
+            FlowControl::Hardware => 2,
+        });
+        bytes.extend_from_slice(&started_at.to_le_bytes());
+        bytes.extend_from_slice(&port_name_size.to_le_bytes());
+        bytes.extend_from_slice(port_name);
+        writer.write_all(&bytes)?;
+        Ok(CaptureWriter { writer })
+    }
+
+    /// Appends a record of data received or sent `elapsed` after the capture started.
+    pub fn write_record(&mut self, direction: Direction, elapsed: Duration, data: &[u8]) -> Result<()> {
+        let size = u32::try_from(data.len()).map_err(|_| invalid("record is too large"))?;
+        let mut prefix = [0; 13];
+        prefix[0] = match direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        };
+        prefix[1..9].copy_from_slice(&(elapsed.as_nanos() as u64).to_le_bytes());
+        prefix[9..13].copy_from_slice(&size.to_le_bytes());
+        self.writer.write_all(&prefix)?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// Flushes the capture and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a capture written by CaptureWriter
+///
+/// Iterating over a reader yields its records until the end of the capture
+/// or the first error.
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    header: CaptureHeader,
+    failed: bool,
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Opens a capture file and reads its header.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        CaptureReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Reads the header of a capture.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut fixed = [0; 24];
+        reader.read_exact(&mut fixed)?;
+        if &fixed[0..4] != MAGIC {
+            return Err(invalid("not a capture file"));
+        }
+        let version = u16::from_le_bytes([fixed[4], fixed[5]]);
+        if version != VERSION {
+            return Err(invalid(format!("unsupported capture version {}", version)));
+        }
+
+        let config = PortConfig {
+            baud_rate: u32::from_le_bytes(fixed[6..10].try_into().unwrap()),
+            data_bits: match fixed[10] {
+                5 => DataBits::Five,
+                6 => DataBits::Six,
+                7 => DataBits::Seven,
+                8 => DataBits::Eight,
+                other => return Err(invalid(format!("invalid data bits {}", other))),
+            },
+            parity: match fixed[11] {
+                0 => Parity::None,
+                1 => Parity::Odd,
+                2 => Parity::Even,
+                other => return Err(invalid(format!("invalid parity {}", other))),
+            },
+            stop_bits: match fixed[12] {
+                1 => StopBits::One,
+                2 => StopBits::Two,
+                other => return Err(invalid(format!("invalid stop bits {}", other))),
+            },
+            flow_control: match fixed[13] {
+                0 => FlowControl::None,
+                1 => FlowControl::Software,
+                2 => FlowControl::Hardware,
+                other => return Err(invalid(format!("invalid flow control {}", other))),
+            },
+            ..Default::default()
+        };
+        let started_at = UNIX_EPOCH + Duration::from_nanos(u64::from_le_bytes(fixed[14..22].try_into().unwrap()));
+        let mut port_name = vec![0; u16::from_le_bytes([fixed[22], fixed[23]]) as usize];
+        reader.read_exact(&mut port_name)?;
+        let port_name = String::from_utf8(port_name).map_err(|_| invalid("port name is not UTF-8"))?;
+
+        Ok(CaptureReader {
+            reader,
+            header: CaptureHeader { port_name, config, started_at },
+            failed: false,
+        })
+    }
+
+    /// Returns the header of the capture.
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Reads the next record, or returns None at the end of the capture.
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        let mut prefix = [0; 13];
+        // The capture may only end between records
+        loop {
+            match self.reader.read(&mut prefix[..1]) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.reader.read_exact(&mut prefix[1..])?;
+
+        let direction = match prefix[0] {
+            0 => Direction::Rx,
+            1 => Direction::Tx,
+            other => return Err(invalid(format!("invalid record direction {}", other))),
+        };
+        let elapsed = Duration::from_nanos(u64::from_le_bytes(prefix[1..9].try_into().unwrap()));
+        // Read through take() so that a corrupt size does not allocate gigabytes up front
+        let size = u32::from_le_bytes(prefix[9..13].try_into().unwrap()) as u64;
+        let mut bytes = Vec::new();
+        if (&mut self.reader).take(size).read_to_end(&mut bytes)? as u64 != size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Some(CaptureRecord { direction, elapsed, bytes }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let record = self.next_record().transpose();
+        self.failed = matches!(record, Some(Err(_)));
+        record
+    }
+}
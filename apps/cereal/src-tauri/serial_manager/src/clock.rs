@@ -0,0 +1,186 @@
+//! # Discovery Clock
+//!
+//! Discovery measures settle windows and flapping against a Clock, and the
+//! polling loop waits between scans by sleeping on it. Sleeps can be cut short
+//! with interrupt(), which is how pause, resume and rescan requests reach the
+//! discovery thread. The SystemClock is real time. The MockClock only moves
+//! when a test advances it, which makes polling discovery fully deterministic.
+//!
+//! ## Stepping Discovery
+//!
+//! MockClock::advance() runs the discovery thread through every sleep that ends
+//! within the advanced time, and returns once the thread is asleep again. Events
+//! caused by those scans and ticks have all been delivered by then.
+//!
+//! ```rust ignore
+//! let clock = MockClock::new();
+//! let discovery = DiscoveryService::spawn_with_enumerator(
+//!     known_ports, filter, config, callback, Box::new(enumerator), Arc::new(clock.clone()),
+//! );
+//! clock.advance(Duration::ZERO);           // initial scan done
+//! clock.advance(config.poll_interval);     // one more scan done
+//! ```
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long MockClock::advance() waits for a thread to go to sleep before
+/// assuming nobody is sleeping on the clock
+const SLEEPER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Source of time for discovery
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the calling thread for the given duration, or until interrupted.
+    fn sleep(&self, duration: Duration);
+
+    /// Wakes the thread blocked in sleep(). If no thread is sleeping, the
+    /// next call to sleep() returns immediately, so a wakeup is never lost.
+    fn interrupt(&self);
+}
+
+/// Real time
+#[derive(Default)]
+pub struct SystemClock {
+    interrupted: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl SystemClock {
+    /// Creates a real time clock.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let Ok(interrupted) = self.interrupted.lock() else { return };
+        if let Ok((mut interrupted, _)) = self.wake.wait_timeout_while(interrupted, duration, |i| !*i) {
+            *interrupted = false;
+        }
+    }
+
+    fn interrupt(&self) {
+        if let Ok(mut interrupted) = self.interrupted.lock() {
+            *interrupted = true;
+            self.wake.notify_all();
+        }
+    }
+}
+
+struct MockClockState {
+    now: Instant,
+    /// When the thread currently blocked in sleep() wants to wake up
+    sleeper: Option<Instant>,
+    /// Set by interrupt(), consumed by sleep()
+    interrupted: bool,
+}
+
+struct MockClockInner {
+    state: Mutex<MockClockState>,
+    changed: Condvar,
+}
+
+/// A clock that only moves when advanced
+///
+/// Supports a single sleeping thread, which is all one discovery service needs.
+/// Clones share the same time.
+#[derive(Clone)]
+pub struct MockClock {
+    inner: Arc<MockClockInner>,
+}
+
+impl MockClock {
+    /// Creates a clock stopped at the current instant.
+    pub fn new() -> Self {
+        MockClock {
+            inner: Arc::new(MockClockInner {
+                state: Mutex::new(MockClockState {
+                    now: Instant::now(),
+                    sleeper: None,
+                    interrupted: false,
+                }),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Moves time forward, waking the sleeping thread at each of its deadlines
+    /// on the way, and returns once it is asleep past the new time.
+    ///
+    /// Advancing by zero just waits for the sleeping thread to go to sleep.
+    /// If no thread goes to sleep within a second, time is moved regardless.
+    pub fn advance(&self, duration: Duration) {
+        let Ok(mut state) = self.inner.state.lock() else { return };
+        let target = state.now + duration;
+
+        loop {
+            state = match self.inner.changed.wait_timeout_while(state, SLEEPER_TIMEOUT, |s| s.sleeper.is_none()) {
+                Ok((state, _)) => state,
+                Err(_) => return,
+            };
+            let Some(deadline) = state.sleeper else { break };
+            if deadline > target {
+                break;
+            }
+
+            // Wake the sleeper and wait until it has done its work and sleeps again
+            state.now = state.now.max(deadline);
+            self.inner.changed.notify_all();
+            state = match self.inner.changed.wait_timeout_while(state, SLEEPER_TIMEOUT, |s| s.sleeper == Some(deadline)) {
+                Ok((state, _)) => state,
+                Err(_) => return,
+            };
+        }
+
+        state.now = target;
+        self.inner.changed.notify_all();
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        match self.inner.state.lock() {
+            Ok(state) => state.now,
+            Err(poisoned) => poisoned.into_inner().now,
+        }
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let Ok(mut state) = self.inner.state.lock() else { return };
+        if std::mem::take(&mut state.interrupted) || duration.is_zero() {
+            return;
+        }
+        let deadline = state.now + duration;
+        state.sleeper = Some(deadline);
+        self.inner.changed.notify_all();
+
+        let Ok(mut state) = self.inner.changed.wait_while(state, |s| s.now < deadline && !s.interrupted) else { return };
+        state.interrupted = false;
+        state.sleeper = None;
+        self.inner.changed.notify_all();
+    }
+
+    fn interrupt(&self) {
+        let Ok(mut state) = self.inner.state.lock() else { return };
+        state.interrupted = true;
+        // Clearing the sleeper right away makes advance() wait for the thread to sleep again
+        state.sleeper = None;
+        self.inner.changed.notify_all();
+    }
+}
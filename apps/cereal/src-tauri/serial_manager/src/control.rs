@@ -0,0 +1,132 @@
+//! # Modem Control Lines
+//!
+//! Besides data, a serial port carries control lines. The host drives DTR and
+//! RTS, and the device drives CTS, DSR, RI and CD. USB adapters on development
+//! boards often wire DTR and RTS to the microcontroller's reset and boot-mode
+//! pins instead, so toggling them in the right order resets the board or puts
+//! it into its bootloader. A ResetSequence describes such an order.
+//!
+//! Lines are named by their logical state: asserting DTR (`set_dtr(true)`)
+//! drives the pin low on an RS-232 level adapter and on most USB-UART chips.
+//!
+//! ## Built-in Sequences
+//!
+//! | Name             | Wiring                                  | Effect                             |
+//! |------------------|-----------------------------------------|------------------------------------|
+//! | esp32-bootloader | DTR to IO0 and RTS to EN, as on DevKits | Reboots into the ROM bootloader    |
+//! | esp32-reset      | RTS to EN                               | Reboots and runs the firmware      |
+//! | arduino-reset    | DTR or RTS to RESET through a capacitor | Reboots into the bootloader        |
+//! | stm32-bootloader | RTS to BOOT0 and DTR to NRST            | Reboots into the system bootloader |
+//!
+//! ```rust ignore
+//! let sequence = ResetSequence::find("esp32-bootloader").unwrap();
+//! session.run_reset_sequence(&sequence)?;
+//! ```
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// States of the control lines driven by the device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ControlLines {
+    /// Clear To Send
+    pub cts: bool,
+    /// Data Set Ready
+    pub dsr: bool,
+    /// Ring Indicator
+    pub ri: bool,
+    /// Carrier Detect
+    pub cd: bool,
+}
+
+/// One step of a ResetSequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ResetStep {
+    /// Asserts (true) or releases (false) Data Terminal Ready
+    Dtr(bool),
+    /// Asserts (true) or releases (false) Request To Send
+    Rts(bool),
+    /// Sends a break condition for the given time
+    Break(Duration),
+    /// Waits before the next step
+    Wait(Duration),
+}
+
+/// A named series of control line changes, e.g. to reset a board into its bootloader
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResetSequence {
+    /// Short name used to look the sequence up
+    pub name: String,
+    /// What the sequence does and which wiring it expects
+    pub description: String,
+    /// The steps, run in order
+    pub steps: Vec<ResetStep>,
+}
+
+impl ResetSequence {
+    /// Returns the built-in sequences.
+    pub fn builtin() -> Vec<ResetSequence> {
+        use ResetStep::*;
+
+        vec![
+            // Same as esptool's classic reset
+            ResetSequence::new(
+                "esp32-bootloader",
+                "Reset an ESP32/ESP8266 board into the ROM bootloader (DTR to IO0, RTS to EN)",
+                vec![
+                    Dtr(false),
+                    Rts(true),
+                    Wait(Duration::from_millis(100)),
+                    Dtr(true),
+                    Rts(false),
+                    Wait(Duration::from_millis(50)),
+                    Dtr(false),
+                ],
+            ),
+            ResetSequence::new(
+                "esp32-reset",
+                "Reset an ESP32/ESP8266 board and run its firmware (RTS to EN)",
+                vec![Rts(true), Wait(Duration::from_millis(100)), Rts(false)],
+            ),
+            // Same as avrdude's arduino programmer
+            ResetSequence::new(
+                "arduino-reset",
+                "Reset an Arduino through the auto-reset capacitor (DTR/RTS to RESET)",
+                vec![
+                    Dtr(false),
+                    Rts(false),
+                    Wait(Duration::from_millis(250)),
+                    Dtr(true),
+                    Rts(true),
+                    Wait(Duration::from_millis(50)),
+                ],
+            ),
+            ResetSequence::new(
+                "stm32-bootloader",
+                "Reset an STM32 into the system memory bootloader (RTS to BOOT0, DTR to NRST)",
+                vec![
+                    Rts(true),
+                    Dtr(true),
+                    Wait(Duration::from_millis(100)),
+                    Dtr(false),
+                    Wait(Duration::from_millis(100)),
+                    Rts(false),
+                ],
+            ),
+        ]
+    }
+
+    /// Returns the built-in sequence with the given name.
+    pub fn find(name: &str) -> Option<ResetSequence> {
+        Self::builtin().into_iter().find(|sequence| sequence.name == name)
+    }
+
+    fn new(name: &str, description: &str, steps: Vec<ResetStep>) -> Self {
+        ResetSequence {
+            name: name.to_string(),
+            description: description.to_string(),
+            steps,
+        }
+    }
+}
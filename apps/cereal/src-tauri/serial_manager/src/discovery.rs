@@ -7,46 +7,135 @@
 //! ## Key Features
 //! 
 //! - **Background Monitoring**: Runs in a dedicated thread to avoid blocking the main application
-//! - **Real-time Updates**: Checks for port changes every 2 seconds
-//! - **Thread-safe Communication**: Uses Arc<Mutex<HashSet<String>>> to share discovered ports
+//! - **Hotplug Events**: On Linux, reacts to kernel uevents for the tty subsystem as they happen
+//! - **Polling Fallback**: Checks for port changes every 2 seconds when hotplug events are unavailable
+//! - **Thread-safe Communication**: Uses Arc<Mutex<HashMap<String, PortInfo>>> to share discovered ports
 //! - **Event Notifications**: Sends structured events when ports are added or removed
-//! - **Graceful Shutdown**: Supports clean termination via atomic boolean flag
+//! - **Port Filtering**: Only ports accepted by a PortFilter are tracked and reported
+//! - **Stable Identity**: A device reappearing under a new port name is reported as a move
+//! - **Debouncing**: A device that drops out and comes back within a settle window is reported
+//!   once as re-enumerated, and devices that keep doing so are marked unstable
+//! - **Lifecycle Control**: Discovery can be paused, resumed and asked to rescan immediately
+//! - **Graceful Shutdown**: Supports clean termination, optionally waiting for the thread to exit
+//! - **Async Runtime**: With the `tokio` feature, discovery can run as a cancellable tokio task
 //! - **Change Detection**: Logs when ports are added or removed from the system
+//! - **Error Reporting**: Enumeration and hotplug failures are reported as PortEvent::Error
+//! - **Virtual Ports**: Ports created by the application, such as pseudo terminals, are
+//!   tracked alongside the system's and reported when they are added and removed
 //! 
 //! ## Usage
 //! 
-//! The service is typically spawned by CerialManager and shares a reference to the known_ports
-//! collection. As new serial devices are detected, they're automatically added to the shared
-//! collection and events are sent via the event channel.
+//! The service is typically spawned by SerialManager and shares a reference to the known_ports
+//! collection. As serial devices come and go, the shared collection is updated and the
+//! callback is invoked with a PortEvent for each change, on the discovery thread.
 //! 
 //! ```ignore
-//! let known_ports = Arc::new(Mutex::new(HashSet::new()));
-//! let discovery = DiscoveryService::spawn(known_ports.clone());
+//! let known_ports = Arc::new(Mutex::new(HashMap::new()));
+//! let discovery = DiscoveryService::spawn(
+//!     known_ports.clone(),
+//!     PortFilter::allow_all(),
+//!     DiscoveryConfig::default(),
+//!     Box::new(|event| match event {
+//!         PortEvent::Added(port) => println!("Device connected: {}", port.port_name),
+//!         PortEvent::Removed(port) => println!("Device disconnected: {}", port.port_name),
+//!         PortEvent::Moved { from, to, .. } => println!("Device moved: {} -> {}", from.port_name, to.port_name),
+//!         PortEvent::Reenumerated { port, .. } => println!("Device reset: {}", port.port_name),
+//!         PortEvent::Error(error) => eprintln!("Discovery problem: {}", error),
+//!     }),
+//! );
 //! 
-//! // Listen for events
-//! while let Ok(event) = discovery.events.recv() {
-//!     match event {
-//!         PortEvent::Added(port) => println!("Device connected: {}", port),
-//!         PortEvent::Removed(port) => println!("Device disconnected: {}", port),
-//!     }
-//! }
+//! // ... later
+//! discovery.shutdown_and_join(Duration::from_secs(1));
 //! ```
 
 use std::{
-    collections::HashSet, 
-    sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, 
-    time::Duration,
-    thread::{self, JoinHandle}
+    collections::{HashMap, HashSet, VecDeque}, 
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak}, 
+    time::{Duration, Instant},
+    thread::{self, JoinHandle, ThreadId}
 };
-use serialport;
+use crate::clock::{Clock, SystemClock};
+use crate::enumerator::{PortEnumerator, SystemEnumerator};
+use crate::error::SerialManagerError;
+use crate::filter::PortFilter;
+use crate::identity::{self, DeviceIdentity};
+use crate::port_info::PortInfo;
+use crate::uevent::{Uevent, UeventSource};
+use serde::{Deserialize, Serialize};
+
+/// How often settling devices are checked for expiry, and how long a single
+/// uevent read blocks
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a departed device is remembered, so that its reappearance under
+/// a different port name is reported as a move rather than a new device
+const MOVE_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long a hotplugged USB port without a serial number is held back while
+/// udev creates its /dev/serial/by-id link, which its identity depends on
+const LINK_WAIT: Duration = Duration::from_secs(1);
+
+/// Tuning for how discovery debounces devices that drop out and come back
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryConfig {
+    /// How long a departed device with a stable identity is held back before its
+    /// removal is reported. Zero reports removals immediately.
+    pub settle_window: Duration,
+    /// Number of re-enumerations within flap_window after which a device is unstable
+    pub flap_threshold: u32,
+    /// How long a device must stay put for its re-enumeration count to reset
+    pub flap_window: Duration,
+    /// How often the polling fallback rescans the system for ports
+    pub poll_interval: Duration,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            settle_window: Duration::from_secs(2),
+            flap_threshold: 3,
+            flap_window: Duration::from_secs(60),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// How often a device has re-enumerated recently
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceStability {
+    /// Re-enumerations seen since the device last stayed put for flap_window
+    pub reenumerations: u32,
+    /// True once reenumerations has reached the configured flap_threshold
+    pub unstable: bool,
+}
 
 /// Events emitted when serial port status changes
 #[derive(Debug, Clone, PartialEq)]
 pub enum PortEvent {
     /// A new serial port was detected and added to the system
-    Added(String),
+    Added(PortInfo),
     /// A serial port was removed from the system
-    Removed(String),
+    Removed(PortInfo),
+    /// A known device reappeared under a different port name
+    Moved {
+        /// The port the device was last seen on
+        from: PortInfo,
+        /// The port the device is now available on
+        to: PortInfo,
+        /// The stable identity that links the two ports
+        identity: DeviceIdentity,
+    },
+    /// A known device dropped out and came back on the same port within the settle window
+    Reenumerated {
+        /// The port as it looks after coming back
+        port: PortInfo,
+        /// The stable identity of the device
+        identity: DeviceIdentity,
+        /// How often the device has re-enumerated recently
+        stability: DeviceStability,
+    },
+    /// Discovery ran into a problem; it keeps running and retries where it can
+    Error(SerialManagerError),
 }
 
 /// Callback function type for port change notifications
@@ -62,22 +151,120 @@ pub type PortEventCallback = Box<dyn Fn(PortEvent) + Send + Sync>;
 /// added or removed, the changes are reflected in the shared known_ports collection
 /// and the callback function is invoked.
 pub struct DiscoveryService {
-    /// Atomic flag used to signal the background thread to shutdown gracefully.
-    shutdown: Arc<AtomicBool>,
+    /// Pause, rescan and shutdown requests for the background thread.
+    control: Arc<Control>,
+
+    /// Diff state shared with the background thread, used to apply filter changes.
+    tracker: Arc<PortTracker>,
     
-    /// Handle to the background discovery thread (prefixed with _ to indicate intentional non-use).
-    _handle: JoinHandle<()>,
+    /// The thread or task doing the discovery, until it is joined.
+    worker: Mutex<Option<Worker>>,
+}
+
+/// Where discovery runs
+enum Worker {
+    /// A dedicated background thread
+    Thread(JoinHandle<()>),
+    /// A tokio task
+    #[cfg(feature = "tokio")]
+    Task(tokio::task::JoinHandle<()>),
+}
+
+/// Requests from the owner of the service to the discovery thread or task
+struct Control {
+    state: Mutex<ControlState>,
+    /// Signalled when the worker exits
+    exited: Condvar,
+    /// The clock the discovery thread sleeps on, interrupted to deliver requests
+    clock: Arc<dyn Clock>,
+    /// Wakes the discovery task, when running on tokio
+    #[cfg(feature = "tokio")]
+    notify: tokio::sync::Notify,
+    /// Cancels the discovery task, when running on tokio
+    #[cfg(feature = "tokio")]
+    cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+#[derive(Default)]
+struct ControlState {
+    shutdown: bool,
+    paused: bool,
+    rescan: bool,
+    /// Set when the worker has exited: true if it returned, false if it panicked
+    exited: Option<bool>,
+}
+
+impl Control {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        Control {
+            state: Mutex::new(ControlState::default()),
+            exited: Condvar::new(),
+            clock,
+            #[cfg(feature = "tokio")]
+            notify: tokio::sync::Notify::new(),
+            #[cfg(feature = "tokio")]
+            cancel: None,
+        }
+    }
+
+    /// Applies a request and wakes the worker so it acts on it right away.
+    fn request(&self, change: impl FnOnce(&mut ControlState)) {
+        if let Ok(mut state) = self.state.lock() {
+            change(&mut state);
+        }
+        self.clock.interrupt();
+        #[cfg(feature = "tokio")]
+        self.notify.notify_one();
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.state.lock().map(|state| state.shutdown).unwrap_or(true)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().map(|state| state.paused).unwrap_or(false)
+    }
+
+    /// Consumes a pending rescan request.
+    fn take_rescan(&self) -> bool {
+        self.state.lock().map(|mut state| std::mem::take(&mut state.rescan)).unwrap_or(false)
+    }
+
+    /// Waits up to `timeout` for the worker to exit, returning how it exited.
+    fn wait_exited(&self, timeout: Duration) -> Option<bool> {
+        let state = self.state.lock().ok()?;
+        let (state, _) = self.exited
+            .wait_timeout_while(state, timeout, |state| state.exited.is_none())
+            .ok()?;
+        state.exited
+    }
+}
+
+/// Records that the worker exited when dropped, including by a panic.
+struct ExitGuard(Arc<Control>);
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.state.lock() {
+            state.exited = Some(!thread::panicking());
+            self.0.exited.notify_all();
+        }
+    }
 }
 
 impl DiscoveryService {
     /// Creates a new DiscoveryService and immediately starts background port discovery.
     /// 
     /// This spawns a background thread that will continuously monitor for serial port
-    /// changes and invoke the callback when events occur.
+    /// changes and invoke the callback when events occur. On Linux the thread listens
+    /// for kernel uevents on a netlink socket; if that socket cannot be opened, or fails
+    /// later on, it falls back to polling the system every poll_interval.
     /// 
     /// # Arguments
     /// 
-    /// * `known_ports` - Shared collection that will be updated with discovered port names.
+    /// * `known_ports` - Shared collection that will be updated with discovered ports, keyed by port name.
+    /// * `filter` - Rules selecting which ports are tracked and reported.
+    /// * `config` - Settle window and flapping thresholds.
     /// * `callback` - Function to call when ports are added or removed.
     /// 
     /// # Returns
@@ -86,91 +273,289 @@ impl DiscoveryService {
     /// 
     /// # Example
     /// 
-    /// ```rust
-    /// let known_ports = Arc::new(Mutex::new(HashSet::new()));
+    /// ```rust ignore
+    /// let known_ports = Arc::new(Mutex::new(HashMap::new()));
     /// let discovery = DiscoveryService::spawn(
     ///     known_ports.clone(),
+    ///     PortFilter::allow_all(),
+    ///     DiscoveryConfig::default(),
     ///     Box::new(|event| {
     ///         match event {
-    ///             PortEvent::Added(port) => println!("Device connected: {}", port),
-    ///             PortEvent::Removed(port) => println!("Device disconnected: {}", port),
+    ///             PortEvent::Added(port) => println!("Device connected: {}", port.port_name),
+    ///             PortEvent::Removed(port) => println!("Device disconnected: {}", port.port_name),
+    ///             _ => {}
     ///         }
     ///     })
     /// );
     /// ```
     pub fn spawn(
-        known_ports: Arc<Mutex<HashSet<String>>>,
+        known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+        filter: PortFilter,
+        config: DiscoveryConfig,
         callback: PortEventCallback
     ) -> Self {
-        let shutdown = Arc::new(AtomicBool::new(false));
-        let shutdown_clone = shutdown.clone();
-        let known_ports_clone = known_ports.clone();
+        let tracker = Arc::new(PortTracker::new(known_ports, filter, config, callback, Arc::new(SystemClock::new())));
+        let enumerator: Box<dyn PortEnumerator> = Box::new(SystemEnumerator);
+
+        #[cfg(target_os = "linux")]
+        match crate::uevent::NetlinkUeventSource::open() {
+            Ok(source) => return Self::start(tracker, Some(Box::new(source)), Some(enumerator)),
+            Err(e) => log::warn!("Netlink hotplug unavailable, falling back to polling: {}", e),
+        }
+
+        Self::start(tracker, None, Some(enumerator))
+    }
+
+    /// Creates a new DiscoveryService that polls the given enumerator, timed by the given clock.
+    /// 
+    /// Hotplug events are not used. Together with a MockEnumerator and a MockClock
+    /// this runs the complete polling discovery deterministically, without hardware.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `known_ports` - Shared collection that will be updated with discovered ports, keyed by port name.
+    /// * `filter` - Rules selecting which ports are tracked and reported.
+    /// * `config` - Settle window, flapping thresholds and poll interval.
+    /// * `callback` - Function to call when ports are added or removed.
+    /// * `enumerator` - Where the list of present ports comes from.
+    /// * `clock` - Time source for the settle window, flapping and the poll interval.
+    pub fn spawn_with_enumerator(
+        known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        callback: PortEventCallback,
+        enumerator: Box<dyn PortEnumerator>,
+        clock: Arc<dyn Clock>
+    ) -> Self {
+        let tracker = Arc::new(PortTracker::new(known_ports, filter, config, callback, clock));
+        Self::start(tracker, None, Some(enumerator))
+    }
+
+    /// Creates a new DiscoveryService driven only by the given uevent source.
+    /// 
+    /// Unlike spawn(), the system is never enumerated: known_ports is updated purely
+    /// from the messages the source delivers. This makes it possible to exercise the
+    /// hotplug backend with synthetic uevents, e.g. from an InjectedUeventSource.
+    /// The background thread exits when the source is closed.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `known_ports` - Shared collection that will be updated with discovered ports, keyed by port name.
+    /// * `filter` - Rules selecting which ports are tracked and reported.
+    /// * `config` - Settle window and flapping thresholds.
+    /// * `callback` - Function to call when ports are added or removed.
+    /// * `source` - Where raw uevent messages are read from.
+    pub fn spawn_with_uevent_source(
+        known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        callback: PortEventCallback,
+        source: Box<dyn UeventSource>
+    ) -> Self {
+        let tracker = Arc::new(PortTracker::new(known_ports, filter, config, callback, Arc::new(SystemClock::new())));
+        Self::start(tracker, Some(source), None)
+    }
+
+    /// Starts the discovery thread. Uevents are preferred when a source is given;
+    /// the enumerator, if any, provides the initial scan and the polling fallback.
+    fn start(
+        tracker: Arc<PortTracker>,
+        source: Option<Box<dyn UeventSource>>,
+        mut enumerator: Option<Box<dyn PortEnumerator>>
+    ) -> Self {
+        let control = Arc::new(Control::new(tracker.clock.clone()));
+        let control_clone = control.clone();
+        let tracker_clone = tracker.clone();
 
         let handle = thread::spawn(move || {
-            while !shutdown_clone.load(Ordering::Relaxed) {
-                match serialport::available_ports() {
-                    Ok(current_ports) => {
-                        let current_set: HashSet<String> = current_ports
-                            .iter()
-                            .map(|p| p.port_name.clone())
-                            .filter(|port_name| {
-                                // On macOS, prefer cu.* over tty.* for the same device
-                                if port_name.contains("/dev/tty.") {
-                                    let cu_equivalent = port_name.replace("/dev/tty.", "/dev/cu.");
-                                    !current_ports.iter().any(|p| p.port_name == cu_equivalent)
-                                } else {
-                                    true
-                                }
-                            })
-                            .collect();
-
-                        if let Ok(mut known) = known_ports_clone.lock() {
-                            // Notify callback for new ports
-                            for p in current_set.difference(&*known) {
-                                println!("New port: {}", p);
-                                callback(PortEvent::Added(p.clone()));
-                            }
-
-                            // Notify callback for removed ports  
-                            for p in known.difference(&current_set) {
-                                println!("Removed port: {}", p);
-                                callback(PortEvent::Removed(p.clone()));
-                            }
-
-                            *known = current_set;
-                        } else {
-                            eprintln!("Failed to lock known ports");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error listing ports: {}", e);
-                    }
+            let _exit = ExitGuard(control_clone.clone());
+
+            if let Some(mut source) = source {
+                // Pick up ports that were already present before we started listening
+                if let Some(enumerator) = enumerator.as_mut() {
+                    scan_ports(&tracker_clone, enumerator.as_mut());
                 }
 
-                // Sleep for 2 seconds, but check shutdown flag more frequently
-                for _ in 0..20 {
-                    if shutdown_clone.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    thread::sleep(Duration::from_millis(100));
+                if let Err(e) = listen_for_uevents(source.as_mut(), enumerator.as_mut(), &tracker_clone, &control_clone) {
+                    log::error!("Uevent source failed: {}", e);
+                    tracker_clone.report(e.into());
                 }
             }
+
+            if let Some(enumerator) = enumerator.as_mut() {
+                poll_ports(&tracker_clone, enumerator.as_mut(), &control_clone);
+            }
             
-            println!("DiscoveryService shutting down");
+            log::debug!("DiscoveryService shutting down");
         });
 
         DiscoveryService {
-            shutdown,
-            _handle: handle,
+            control,
+            tracker,
+            worker: Mutex::new(Some(Worker::Thread(handle))),
         }
     }
 
+    /// Creates a new DiscoveryService running as a task on the current tokio runtime.
+    /// 
+    /// Behaves like spawn(), but no thread is dedicated to discovery: on Linux the
+    /// netlink socket is registered with the runtime's reactor, and the polling
+    /// fallback sleeps on the runtime's timer. Scans, uevents and settle ticks run on
+    /// the blocking pool, as they read sysfs and /dev and invoke the callback.
+    /// 
+    /// The task stops when `cancel` is cancelled or shutdown() is called, whichever
+    /// comes first. Note that shutdown() cancels `cancel` itself, so pass a child
+    /// token if the parent is shared with other tasks.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if called outside the context of a tokio runtime.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let shutdown = CancellationToken::new();
+    /// let discovery = DiscoveryService::spawn_async(
+    ///     known_ports.clone(),
+    ///     PortFilter::allow_all(),
+    ///     DiscoveryConfig::default(),
+    ///     Box::new(|event| println!("{:?}", event)),
+    ///     shutdown.child_token(),
+    /// );
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn spawn_async(
+        known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        callback: PortEventCallback,
+        cancel: tokio_util::sync::CancellationToken
+    ) -> Self {
+        let tracker = Arc::new(PortTracker::new(known_ports, filter, config, callback, Arc::new(SystemClock::new())));
+        let mut control = Control::new(tracker.clock.clone());
+        control.cancel = Some(cancel.clone());
+        let control = Arc::new(control);
+        let control_clone = control.clone();
+        let tracker_clone = tracker.clone();
+
+        let handle = tokio::spawn(async move {
+            let _exit = ExitGuard(control_clone.clone());
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = tokio_discovery::run(tracker_clone, control_clone) => {}
+            }
+            log::debug!("DiscoveryService shutting down");
+        });
+
+        DiscoveryService {
+            control,
+            tracker,
+            worker: Mutex::new(Some(Worker::Task(handle))),
+        }
+    }
+
+    /// Returns a copy of the filter currently applied to discovered ports.
+    pub fn filter(&self) -> PortFilter {
+        self.tracker.filter()
+    }
+
+    /// Replaces the port filter at runtime.
+    /// 
+    /// Ports that are present but were previously rejected and are now accepted are
+    /// reported as Added, and tracked ports the new filter rejects are reported as
+    /// Removed, so listeners see the same delta as if the devices had been plugged
+    /// in or out. The events are delivered before this returns, on the calling
+    /// thread unless another thread is passing events to the callback at the time.
+    pub fn set_filter(&self, filter: PortFilter) {
+        self.tracker.set_filter(filter);
+    }
+
+    /// Reports a virtual port as present, as if its device had been plugged in.
+    /// 
+    /// Scanning the system does not find virtual ports, so they stay present
+    /// until remove_virtual_port() is called. Apart from that they are treated
+    /// like any other port: the filter applies, and an identity makes their
+    /// removal settle and their return under another name count as a move.
+    /// The events are delivered before this returns, on the calling thread unless
+    /// another thread is passing events to the callback at the time.
+    /// 
+    /// # Errors
+    /// 
+    /// Fails with SerialManagerError::PortBusy if a virtual port with the same
+    /// port name or identity is already present.
+    pub fn add_virtual_port(&self, port: PortInfo) -> Result<(), SerialManagerError> {
+        self.tracker.add_virtual_port(port)
+    }
+
+    /// Reports a virtual port as gone, as if its device had been unplugged.
+    pub fn remove_virtual_port(&self, port_name: &str) {
+        self.tracker.remove_virtual_port(port_name);
+    }
+
+    /// Returns a handle for removing virtual ports that does not keep discovery alive.
+    pub(crate) fn virtual_ports(&self) -> VirtualPorts {
+        VirtualPorts(Arc::downgrade(&self.tracker))
+    }
+
+    /// Returns how often the device with the given identity has re-enumerated
+    /// recently, or None if it has not re-enumerated within the flap window.
+    pub fn stability(&self, identity: &DeviceIdentity) -> Option<DeviceStability> {
+        self.tracker.stability(identity)
+    }
+
+    /// Stops scanning until resume() is called.
+    /// 
+    /// Ports already known stay in known_ports, and devices that were settling
+    /// are still reported as removed once their settle window expires. Hotplug
+    /// events arriving while paused are ignored; resume() rescans to catch up.
+    pub fn pause(&self) {
+        self.control.request(|state| state.paused = true);
+    }
+
+    /// Resumes scanning after pause(), starting with an immediate rescan.
+    pub fn resume(&self) {
+        self.control.request(|state| {
+            state.paused = false;
+            state.rescan = true;
+        });
+    }
+
+    /// Returns true while discovery is paused.
+    pub fn is_paused(&self) -> bool {
+        self.control.is_paused()
+    }
+
+    /// Enumerates the system right away instead of waiting for the next scan.
+    /// 
+    /// The polling thread is woken immediately. When listening for uevents, the
+    /// rescan happens within 100ms. Also works while paused. Returns without
+    /// waiting for the scan to finish; changes are reported through the callback.
+    /// Has no effect on a service created with spawn_with_uevent_source().
+    pub fn rescan_now(&self) {
+        self.control.request(|state| state.rescan = true);
+    }
+
+    /// Returns how often the system is rescanned when polling.
+    pub fn scan_interval(&self) -> Duration {
+        self.tracker.poll_interval()
+    }
+
+    /// Changes how often the system is rescanned when polling.
+    /// 
+    /// Takes effect immediately: if the last scan is already older than the
+    /// new interval, a scan happens right away.
+    pub fn set_scan_interval(&self, interval: Duration) {
+        self.tracker.set_poll_interval(interval);
+        self.control.request(|_| {});
+    }
+
     /// Signals the background discovery thread to shutdown gracefully.
     /// 
-    /// This method sets the atomic shutdown flag to true, which will cause the
-    /// background thread to exit its monitoring loop and terminate. The shutdown
+    /// The background thread is woken and exits its monitoring loop. The shutdown
     /// is asynchronous - this method returns immediately without waiting for the
-    /// thread to actually finish.
+    /// thread to actually finish; use shutdown_and_join() for that. A discovery
+    /// task started with spawn_async() is cancelled instead and stops at its next
+    /// await point.
     /// 
     /// # Thread Safety
     /// 
@@ -180,11 +565,44 @@ impl DiscoveryService {
     /// 
     /// # Timing
     /// 
-    /// The background thread checks the shutdown flag every 100ms, so it should
-    /// terminate no later than 100ms of calling this method.
+    /// A polling thread stops right away. A thread listening for uevents checks
+    /// for shutdown every 100ms, so it should terminate no later than 100ms after
+    /// calling this method.
     ///
     pub fn shutdown(&self) {
-        self.shutdown.store(true, Ordering::Relaxed);
+        self.control.request(|state| state.shutdown = true);
+        #[cfg(feature = "tokio")]
+        if let Some(cancel) = &self.control.cancel {
+            cancel.cancel();
+        }
+    }
+
+    /// Shuts discovery down and waits up to `timeout` for it to finish.
+    /// 
+    /// Returns true if the thread or task exited cleanly within the timeout, and
+    /// false if it is still running or panicked. Must not be called from the
+    /// discovery callback, which runs on the thread being joined.
+    pub fn shutdown_and_join(&self, timeout: Duration) -> bool {
+        self.shutdown();
+        let exited = self.control.wait_exited(timeout);
+
+        let Some(worker) = self.worker.lock().ok().and_then(|mut worker| worker.take()) else {
+            return exited.unwrap_or(false);
+        };
+        match worker {
+            Worker::Thread(handle) => match exited {
+                // The thread is past its last statement, so joining does not block
+                Some(clean) => handle.join().is_ok() && clean,
+                None => false,
+            },
+            #[cfg(feature = "tokio")]
+            Worker::Task(handle) => {
+                if exited.is_none() {
+                    handle.abort();
+                }
+                exited.unwrap_or(false)
+            }
+        }
     }
 }
 
@@ -193,3 +611,672 @@ impl Drop for DiscoveryService {
         self.shutdown();
     }
 }
+
+/// Removes virtual ports from discovery, if it still runs
+#[derive(Clone)]
+pub(crate) struct VirtualPorts(Weak<PortTracker>);
+
+impl VirtualPorts {
+    pub(crate) fn remove(&self, port_name: &str) {
+        if let Some(tracker) = self.0.upgrade() {
+            tracker.remove_virtual_port(port_name);
+        }
+    }
+}
+
+/// Tracks every port present on the system and reconciles the filtered
+/// view in known_ports with it, notifying the callback of each difference.
+struct PortTracker {
+    /// Ports accepted by the filter, shared with the owner of the service
+    known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+    /// Unfiltered view of the system and the active filter
+    state: Mutex<TrackerState>,
+    /// Where Added/Removed events are reported
+    callback: PortEventCallback,
+    /// Events waiting to be passed to the callback once the locks are released
+    queue: Mutex<EventQueue>,
+    /// Signalled when no thread is passing events to the callback any more
+    queue_idle: Condvar,
+    /// Time source for settle windows, flapping and polling
+    clock: Arc<dyn Clock>,
+}
+
+struct TrackerState {
+    /// Every port currently present on the system, before filtering
+    present: HashMap<String, PortInfo>,
+    /// Virtual ports added by the application, present until it removes them
+    virtual_ports: HashMap<String, PortInfo>,
+    /// Hotplugged ports waiting for their by-id link, with the time they arrived.
+    /// They become present once the link appears or LINK_WAIT has passed.
+    linking: HashMap<String, (PortInfo, Instant)>,
+    /// Rules deciding which present ports end up in known_ports
+    filter: PortFilter,
+    /// Debounce tuning
+    config: DiscoveryConfig,
+    /// Devices that disappeared less than settle_window ago. They stay in
+    /// known_ports and their removal is not reported until the window expires.
+    settling: HashMap<DeviceIdentity, (PortInfo, Instant)>,
+    /// Recently departed devices, by identity, with the time they left
+    departed: HashMap<DeviceIdentity, (PortInfo, Instant)>,
+    /// Re-enumeration counters, with the time of the last re-enumeration
+    flapping: HashMap<DeviceIdentity, (DeviceStability, Instant)>,
+    /// The last enumeration failure, so that a persistent failure is reported once
+    scan_error: Option<SerialManagerError>,
+}
+
+#[derive(Default)]
+struct EventQueue {
+    /// Events in the order they happened
+    events: VecDeque<PortEvent>,
+    /// The thread passing events to the callback, if any
+    dispatcher: Option<ThreadId>,
+}
+
+/// Lets another thread pass events on if the callback panics
+struct DispatchGuard<'a>(&'a PortTracker);
+
+impl Drop for DispatchGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.queue.lock().unwrap_or_else(PoisonError::into_inner).dispatcher = None;
+            self.0.queue_idle.notify_all();
+        }
+    }
+}
+
+impl TrackerState {
+    fn is_settling(&self, port_name: &str) -> bool {
+        self.settling.values().any(|(p, _)| p.port_name == port_name)
+    }
+
+    /// Counts a re-enumeration of the device and returns its updated stability.
+    fn record_reenumeration(&mut self, identity: &DeviceIdentity, now: Instant) -> DeviceStability {
+        let flap_window = self.config.flap_window;
+        let (stability, last) = self.flapping
+            .entry(identity.clone())
+            .or_insert((DeviceStability::default(), now));
+        if now.duration_since(*last) >= flap_window {
+            *stability = DeviceStability::default();
+        }
+        stability.reenumerations += 1;
+        stability.unstable = stability.reenumerations >= self.config.flap_threshold;
+        *last = now;
+        *stability
+    }
+}
+
+impl PortTracker {
+    fn new(
+        known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        callback: PortEventCallback,
+        clock: Arc<dyn Clock>
+    ) -> Self {
+        PortTracker {
+            known_ports,
+            state: Mutex::new(TrackerState {
+                present: HashMap::new(),
+                virtual_ports: HashMap::new(),
+                linking: HashMap::new(),
+                filter,
+                config,
+                settling: HashMap::new(),
+                departed: HashMap::new(),
+                flapping: HashMap::new(),
+                scan_error: None,
+            }),
+            callback,
+            queue: Mutex::new(EventQueue::default()),
+            queue_idle: Condvar::new(),
+            clock,
+        }
+    }
+
+    /// Reports a problem to the callback.
+    fn report(&self, error: SerialManagerError) {
+        self.push_event(PortEvent::Error(error));
+        self.dispatch();
+    }
+
+    /// Queues an event for the callback. Safe to call with locks held.
+    fn push_event(&self, event: PortEvent) {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner).events.push_back(event);
+    }
+
+    /// Passes queued events to the callback and returns once they are delivered.
+    /// Must be called without holding any of the tracker's locks, so the callback
+    /// may call back into the service.
+    ///
+    /// One thread dispatches at a time, so events arrive in the order they
+    /// happened. A thread finding another one dispatching waits for it to pass
+    /// on everything queued; events queued by the callback itself are passed on
+    /// by the dispatch it was called from.
+    fn dispatch(&self) {
+        let lock_queue = || self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = thread::current().id();
+        let mut queue = lock_queue();
+        while let Some(dispatcher) = queue.dispatcher {
+            if dispatcher == current {
+                return;
+            }
+            queue = self.queue_idle.wait(queue).unwrap_or_else(PoisonError::into_inner);
+        }
+        if queue.events.is_empty() {
+            return;
+        }
+        queue.dispatcher = Some(current);
+        drop(queue);
+
+        let _guard = DispatchGuard(self);
+        loop {
+            let event = {
+                let mut queue = lock_queue();
+                match queue.events.pop_front() {
+                    Some(event) => event,
+                    None => {
+                        queue.dispatcher = None;
+                        self.queue_idle.notify_all();
+                        return;
+                    }
+                }
+            };
+            (self.callback)(event);
+        }
+    }
+
+    /// Locks a mutex, recovering the data if a panicking thread poisoned it.
+    /// The poisoning is reported once, then cleared. The report is only queued,
+    /// as other locks may be held; it goes out with the next dispatch.
+    fn lock<'a, T>(&self, mutex: &'a Mutex<T>, what: &'static str) -> MutexGuard<'a, T> {
+        mutex.lock().unwrap_or_else(|poisoned| {
+            log::error!("The {} lock was poisoned, recovering", what);
+            mutex.clear_poison();
+            self.push_event(PortEvent::Error(SerialManagerError::LockPoisoned(what)));
+            poisoned.into_inner()
+        })
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, TrackerState> {
+        self.lock(&self.state, "discovery state")
+    }
+
+    fn filter(&self) -> PortFilter {
+        self.lock_state().filter.clone()
+    }
+
+    fn set_filter(&self, filter: PortFilter) {
+        self.update(|state| state.filter = filter);
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.lock_state().config.poll_interval
+    }
+
+    fn set_poll_interval(&self, interval: Duration) {
+        self.lock_state().config.poll_interval = interval;
+    }
+
+    fn add_virtual_port(&self, port: PortInfo) -> Result<(), SerialManagerError> {
+        let mut state = self.lock_state();
+        let identity = port.identity();
+        let taken = state.virtual_ports
+            .values()
+            .any(|p| p.port_name == port.port_name || (identity.is_some() && p.identity() == identity));
+        if taken {
+            return Err(SerialManagerError::PortBusy { port: port.port_name });
+        }
+        state.virtual_ports.insert(port.port_name.clone(), port);
+        self.reconcile(&mut state);
+        drop(state);
+        self.dispatch();
+        Ok(())
+    }
+
+    fn remove_virtual_port(&self, port_name: &str) {
+        self.update(|state| {
+            state.virtual_ports.remove(port_name);
+        });
+    }
+
+    /// Returns true while any departed device is waiting out its settle window.
+    fn has_settling(&self) -> bool {
+        !self.lock_state().settling.is_empty()
+    }
+
+    fn stability(&self, identity: &DeviceIdentity) -> Option<DeviceStability> {
+        let state = self.lock_state();
+        let now = self.clock.now();
+        state.flapping
+            .get(identity)
+            .filter(|(_, last)| now.duration_since(*last) < state.config.flap_window)
+            .map(|(stability, _)| *stability)
+    }
+
+    /// Applies a change to the tracker state, then reports the resulting delta.
+    fn update(&self, change: impl FnOnce(&mut TrackerState)) {
+        let mut state = self.lock_state();
+        change(&mut state);
+        self.reconcile(&mut state);
+        drop(state);
+        self.dispatch();
+    }
+
+    /// Makes ports present whose by-id link has appeared or who waited long
+    /// enough, then reports removals whose settle window has expired.
+    fn tick(&self) {
+        self.resolve_links();
+        let mut state = self.lock_state();
+        if !state.settling.is_empty() {
+            self.reconcile(&mut state);
+        }
+        drop(state);
+        self.dispatch();
+    }
+
+    /// Looks again for the by-id links of the ports waiting for them.
+    fn resolve_links(&self) {
+        let mut waiting: Vec<PortInfo> = self.lock_state().linking.values().map(|(p, _)| p.clone()).collect();
+        if waiting.is_empty() {
+            return;
+        }
+        identity::resolve_paths(waiting.iter_mut());
+
+        let now = self.clock.now();
+        self.update(|state| {
+            for port in waiting {
+                // The port may have been removed while its link was looked up
+                let Some((_, since)) = state.linking.get(&port.port_name) else { continue };
+                if port.by_id.is_some() || now.duration_since(*since) >= LINK_WAIT {
+                    state.linking.remove(&port.port_name);
+                    state.present.insert(port.port_name.clone(), port);
+                }
+            }
+        });
+    }
+
+    /// Brings known_ports in line with the filtered set of present ports.
+    /// 
+    /// Departures of devices with a stable identity are held back for the settle
+    /// window. If the device comes back on the same port in that time, a single
+    /// Reenumerated event is reported instead of a Removed/Added pair. A departure
+    /// and an arrival sharing an identity on different ports are reported as a
+    /// single Moved event, whether they are seen together, within the settle window,
+    /// or the arrival follows within MOVE_WINDOW of the reported removal.
+    ///
+    /// Events are only queued; the caller dispatches them after releasing the state.
+    fn reconcile(&self, state: &mut TrackerState) {
+        let mut known = self.lock(&self.known_ports, "known ports");
+        let now = self.clock.now();
+
+        let accepted: HashMap<String, PortInfo> = state.present
+            .values()
+            .chain(state.virtual_ports.values())
+            .filter(|p| state.filter.accepts(p))
+            .map(|p| (p.port_name.clone(), p.clone()))
+            .collect();
+
+        // Keep what is known about ports that stay up to date, so that a by-id
+        // link resolved after the port arrived counts towards its identity
+        for (name, port) in known.iter_mut() {
+            if let Some(current) = accepted.get(name) {
+                port.clone_from(current);
+            }
+        }
+
+        // Settling devices that are back on their old port were only re-enumerated
+        let returned: Vec<DeviceIdentity> = state.settling
+            .iter()
+            .filter(|(_, (p, _))| accepted.contains_key(&p.port_name))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for identity in returned {
+            let Some((before, _)) = state.settling.remove(&identity) else { continue };
+            let port = accepted[&before.port_name].clone();
+            known.insert(port.port_name.clone(), port.clone());
+
+            if port.identity().as_ref() == Some(&identity) {
+                let stability = state.record_reenumeration(&identity, now);
+                log::info!("Re-enumerated port: {} ({} times)", port.port_name, stability.reenumerations);
+                self.push_event(PortEvent::Reenumerated { port, identity, stability });
+            } else {
+                // A different device took over the port
+                log::info!("Removed port: {}", before.port_name);
+                self.push_event(PortEvent::Removed(before));
+                log::info!("New port: {}", port.port_name);
+                self.push_event(PortEvent::Added(port));
+            }
+        }
+
+        // Hold back departures of identifiable devices, report the rest right away
+        let departed_names: Vec<String> = known
+            .keys()
+            .filter(|name| !accepted.contains_key(*name) && !state.is_settling(name))
+            .cloned()
+            .collect();
+        let mut departures: Vec<PortInfo> = Vec::new();
+        for name in departed_names {
+            // Only devices that physically went away settle; filtered out ports leave at once
+            let gone = !state.present.contains_key(&name) && !state.virtual_ports.contains_key(&name);
+            match known[&name].identity() {
+                Some(identity) if gone && !state.config.settle_window.is_zero() => {
+                    state.settling.insert(identity, (known[&name].clone(), now));
+                }
+                _ => departures.extend(known.remove(&name)),
+            }
+        }
+
+        let arrivals: Vec<PortInfo> = accepted
+            .into_values()
+            .filter(|p| !known.contains_key(&p.port_name))
+            .collect();
+
+        state.departed.retain(|_, (_, at)| now.duration_since(*at) < MOVE_WINDOW);
+        let flap_window = state.config.flap_window;
+        state.flapping.retain(|_, (_, last)| now.duration_since(*last) < flap_window);
+
+        // Notify callback for new ports, pairing them with departures of the same device
+        for to in arrivals {
+            known.insert(to.port_name.clone(), to.clone());
+
+            let identity = to.identity();
+            let from = identity.as_ref().and_then(|id| {
+                if let Some(index) = departures.iter().position(|d| d.identity().as_ref() == Some(id)) {
+                    return Some(departures.remove(index));
+                }
+                if let Some((from, _)) = state.settling.remove(id) {
+                    known.remove(&from.port_name);
+                    state.record_reenumeration(id, now);
+                    return Some(from);
+                }
+                state.departed.remove(id).map(|(from, _)| from)
+            });
+
+            match (from, identity) {
+                (Some(from), Some(identity)) if from.port_name != to.port_name => {
+                    log::info!("Moved port: {} -> {}", from.port_name, to.port_name);
+                    self.push_event(PortEvent::Moved { from, to, identity });
+                }
+                _ => {
+                    log::info!("New port: {}", to.port_name);
+                    self.push_event(PortEvent::Added(to));
+                }
+            }
+        }
+
+        // Devices that did not come back within the settle window are gone
+        let settle_window = state.config.settle_window;
+        let expired: Vec<DeviceIdentity> = state.settling
+            .iter()
+            .filter(|(_, (_, at))| now.duration_since(*at) >= settle_window)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for identity in expired {
+            if let Some((info, _)) = state.settling.remove(&identity) {
+                known.remove(&info.port_name);
+                departures.push(info);
+            }
+        }
+
+        // Notify callback for removed ports
+        for info in departures {
+            log::info!("Removed port: {}", info.port_name);
+            if let Some(identity) = info.identity() {
+                state.departed.insert(identity, (info.clone(), now));
+            }
+            self.push_event(PortEvent::Removed(info));
+        }
+    }
+}
+
+/// Enumerates the ports once and reports the difference to known_ports.
+fn scan_ports(tracker: &PortTracker, enumerator: &mut dyn PortEnumerator) {
+    match enumerator.available_ports() {
+        Ok(current_ports) => {
+            let current: HashMap<String, PortInfo> = prefer_callout_ports(current_ports)
+                .into_iter()
+                .map(|p| (p.port_name.clone(), p))
+                .collect();
+
+            tracker.update(|state| {
+                state.linking.retain(|name, _| !current.contains_key(name));
+                state.present = current;
+                state.scan_error = None;
+            });
+        }
+        Err(e) => {
+            log::error!("Error listing ports: {}", e);
+            let error = SerialManagerError::Enumeration(e.to_string());
+            let mut state = tracker.lock_state();
+            if state.scan_error.as_ref() != Some(&error) {
+                state.scan_error = Some(error.clone());
+                drop(state);
+                tracker.report(error);
+            }
+        }
+    }
+}
+
+/// On macOS every device has both a /dev/tty.* and a /dev/cu.* node.
+/// Keeps only the cu.* one when both are present.
+fn prefer_callout_ports(ports: Vec<PortInfo>) -> Vec<PortInfo> {
+    let port_names: HashSet<String> = ports
+        .iter()
+        .map(|p| p.port_name.clone())
+        .collect();
+
+    ports
+        .into_iter()
+        .filter(|p| {
+            if p.port_name.contains("/dev/tty.") {
+                let cu_equivalent = p.port_name.replace("/dev/tty.", "/dev/cu.");
+                !port_names.contains(&cu_equivalent)
+            } else {
+                true
+            }
+        })
+        .collect()
+}
+
+/// Returns true if a scan should happen now: one was requested, or
+/// discovery is running and the last scan is older than poll_interval.
+fn scan_due(tracker: &PortTracker, control: &Control, last_scan: Option<Instant>) -> bool {
+    let requested = control.take_rescan();
+    let elapsed = |at: Instant| tracker.clock.now().duration_since(at) >= tracker.poll_interval();
+    requested || (!control.is_paused() && last_scan.is_none_or(elapsed))
+}
+
+/// Returns how long to sleep after a scan: until the next one is due, but no
+/// longer than TICK_INTERVAL while devices are settling.
+fn idle_time(tracker: &PortTracker, control: &Control, last_scan: Instant) -> Duration {
+    let interval = tracker.poll_interval();
+    let wait = if control.is_paused() {
+        interval
+    } else {
+        (last_scan + interval).saturating_duration_since(tracker.clock.now())
+    };
+    if tracker.has_settling() { wait.min(TICK_INTERVAL) } else { wait }
+}
+
+/// Rescans every poll_interval until shutdown is requested.
+/// 
+/// Sleeps on the tracker's clock, which Control interrupts to deliver
+/// pause, resume, rescan and shutdown requests immediately.
+fn poll_ports(tracker: &PortTracker, enumerator: &mut dyn PortEnumerator, control: &Control) {
+    let mut last_scan = None;
+    while !control.is_shutdown() {
+        if scan_due(tracker, control, last_scan) {
+            scan_ports(tracker, enumerator);
+            last_scan = Some(tracker.clock.now());
+        }
+
+        let wait = idle_time(tracker, control, last_scan.unwrap_or_else(|| tracker.clock.now()));
+        tracker.clock.sleep(wait);
+        tracker.tick();
+    }
+}
+
+/// Applies tty add/remove uevents from the source until shutdown is requested
+/// or the source fails. Rescan requests are served by the enumerator, if any.
+fn listen_for_uevents(
+    source: &mut dyn UeventSource,
+    mut enumerator: Option<&mut Box<dyn PortEnumerator>>,
+    tracker: &PortTracker,
+    control: &Control
+) -> std::io::Result<()> {
+    while !control.is_shutdown() {
+        if control.take_rescan() {
+            if let Some(enumerator) = enumerator.as_mut() {
+                scan_ports(tracker, enumerator.as_mut());
+            }
+        }
+
+        let Some(message) = source.recv(TICK_INTERVAL)? else {
+            tracker.tick();
+            continue;
+        };
+        // Hotplug events missed while paused are caught up by the rescan on resume
+        if !control.is_paused() {
+            apply_uevent(tracker, &message);
+        }
+    }
+    Ok(())
+}
+
+/// Applies a single raw uevent message, ignoring anything but tty add/remove.
+fn apply_uevent(tracker: &PortTracker, message: &[u8]) {
+    let Some(uevent) = Uevent::parse(message) else {
+        return;
+    };
+
+    match uevent.port_event() {
+        Some(PortEvent::Added(port)) => {
+            // The device is still present in sysfs, so its USB descriptors can be read
+            let mut info = uevent.port_info();
+            identity::resolve_paths([&mut info]);
+            // udev may not have created the by-id link yet. Without a serial number
+            // the link decides the identity, so wait for it rather than report the
+            // device under a different identity than a scan would.
+            let needs_link = info.by_id.is_none() && info.serial_number.is_none() && info.location.is_some();
+            let now = tracker.clock.now();
+            tracker.update(|state| {
+                if needs_link {
+                    state.linking.insert(port.port_name, (info, now));
+                } else {
+                    state.present.insert(port.port_name, info);
+                }
+            });
+        }
+        Some(PortEvent::Removed(port)) => {
+            // The device is already gone; reconcile reports what we knew about it
+            tracker.update(|state| {
+                state.linking.remove(&port.port_name);
+                state.present.remove(&port.port_name);
+            });
+        }
+        _ => {}
+    }
+}
+
+/// The discovery loops of spawn_async(), written against the tokio runtime
+#[cfg(feature = "tokio")]
+mod tokio_discovery {
+    use std::sync::Arc;
+    use crate::enumerator::SystemEnumerator;
+    use super::{idle_time, scan_due, Control, PortTracker};
+
+    /// Runs discovery until the task is cancelled.
+    pub async fn run(tracker: Arc<PortTracker>, control: Arc<Control>) {
+        #[cfg(target_os = "linux")]
+        match crate::uevent::NetlinkUeventSource::open().and_then(tokio::io::unix::AsyncFd::new) {
+            Ok(mut source) => {
+                // Pick up ports that were already present before we started listening
+                scan_ports(&tracker).await;
+
+                if let Err(e) = listen_for_uevents(&mut source, &tracker, &control).await {
+                    log::error!("Uevent source failed: {}", e);
+                    tracker.report(e.into());
+                }
+            }
+            Err(e) => log::warn!("Netlink hotplug unavailable, falling back to polling: {}", e),
+        }
+
+        poll_ports(&tracker, &control).await;
+    }
+
+    /// Runs work on the tracker on the blocking pool. Scans, uevents and ticks
+    /// read sysfs and /dev, and pass events to the callback, which may block.
+    async fn on_blocking_pool(tracker: &Arc<PortTracker>, what: &str, work: impl FnOnce(&PortTracker) + Send + 'static) {
+        let worker = tracker.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || work(&worker)).await {
+            log::error!("{} task failed: {}", what, e);
+            tracker.report(std::io::Error::other(e).into());
+        }
+    }
+
+    /// Enumerates the system ports.
+    async fn scan_ports(tracker: &Arc<PortTracker>) {
+        on_blocking_pool(tracker, "Port scan", |tracker| super::scan_ports(tracker, &mut SystemEnumerator)).await;
+    }
+
+    /// Looks for pending by-id links and reports expired settle windows.
+    async fn tick(tracker: &Arc<PortTracker>) {
+        on_blocking_pool(tracker, "Discovery tick", PortTracker::tick).await;
+    }
+
+    /// Rescans the system every poll_interval, ticking settling devices in between.
+    async fn poll_ports(tracker: &Arc<PortTracker>, control: &Control) {
+        let mut last_scan = None;
+        loop {
+            if scan_due(tracker, control, last_scan) {
+                scan_ports(tracker).await;
+                last_scan = Some(tracker.clock.now());
+            }
+
+            let wait = idle_time(tracker, control, last_scan.unwrap_or_else(|| tracker.clock.now()));
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = control.notify.notified() => {}
+            }
+            tick(tracker).await;
+        }
+    }
+
+    /// Applies uevents as the reactor reports the netlink socket readable.
+    #[cfg(target_os = "linux")]
+    async fn listen_for_uevents(
+        source: &mut tokio::io::unix::AsyncFd<crate::uevent::NetlinkUeventSource>,
+        tracker: &Arc<PortTracker>,
+        control: &Control
+    ) -> std::io::Result<()> {
+        use std::{io, time::Duration};
+        use crate::uevent::UeventSource;
+
+        let mut ticks = tokio::time::interval(super::TICK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => tick(tracker).await,
+                _ = control.notify.notified() => {
+                    if control.take_rescan() {
+                        scan_ports(tracker).await;
+                    }
+                }
+                guard = source.readable_mut() => {
+                    let mut guard = guard?;
+                    // A zero timeout never blocks; nothing to read means readiness was stale
+                    let received = guard.try_io(|source| {
+                        source.get_mut().recv(Duration::ZERO)?
+                            .ok_or_else(|| io::Error::from(io::ErrorKind::WouldBlock))
+                    });
+                    // Hotplug events missed while paused are caught up by the rescan on resume
+                    if let Ok(message) = received {
+                        let message = message?;
+                        if !control.is_paused() {
+                            on_blocking_pool(tracker, "Uevent", move |tracker| super::apply_uevent(tracker, &message)).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
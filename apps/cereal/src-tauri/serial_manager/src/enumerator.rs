@@ -0,0 +1,143 @@
+//! # Port Enumeration Backends
+//!
+//! Polling discovery asks a PortEnumerator for the full list of ports present on
+//! the system and diffs it against what it saw last time. The SystemEnumerator
+//! asks the operating system; the MockEnumerator replays a script, so that the
+//! diff and notification logic can be tested without hardware.
+//!
+//! ## Scripting
+//!
+//! Every call to available_ports() on a MockEnumerator is one tick, the first
+//! call being tick 0. Changes scheduled for a tick take effect from that call on.
+//!
+//! ```rust ignore
+//! let enumerator = MockEnumerator::new();
+//! enumerator
+//!     .add_at(0, PortInfo::new("/dev/ttyS0"))
+//!     .add_at(3, PortInfo::new("/dev/ttyACM0"))
+//!     .remove_at(5, "/dev/ttyACM0");
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{Arc, Mutex},
+};
+use crate::identity;
+use crate::port_info::PortInfo;
+
+/// Lists the serial ports currently present on the system
+pub trait PortEnumerator: Send {
+    /// Returns every port present right now, before filtering.
+    fn available_ports(&mut self) -> io::Result<Vec<PortInfo>>;
+}
+
+/// Enumerates the operating system's serial ports via the serialport crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnumerator;
+
+impl PortEnumerator for SystemEnumerator {
+    fn available_ports(&mut self) -> io::Result<Vec<PortInfo>> {
+        let mut ports: Vec<PortInfo> = serialport::available_ports()?
+            .into_iter()
+            .map(PortInfo::from)
+            .collect();
+        identity::resolve_paths(ports.iter_mut());
+        Ok(ports)
+    }
+}
+
+/// A change scheduled for a given tick
+#[derive(Debug, Clone)]
+enum Change {
+    Add(PortInfo),
+    Remove(String),
+    Fail,
+}
+
+#[derive(Default)]
+struct Script {
+    /// Number of enumerations performed so far
+    ticks: u64,
+    /// Ports present as of the last enumeration, in arrival order
+    present: Vec<PortInfo>,
+    /// Pending changes, keyed by the tick they take effect on
+    changes: BTreeMap<u64, Vec<Change>>,
+}
+
+/// A PortEnumerator that replays scripted arrivals and departures
+///
+/// Clones share the same script, so a test can keep a handle to extend the
+/// script or check the tick count after handing a clone to discovery.
+#[derive(Clone, Default)]
+pub struct MockEnumerator {
+    script: Arc<Mutex<Script>>,
+}
+
+impl MockEnumerator {
+    /// Creates an enumerator that reports no ports until told otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a port appear from the given tick on.
+    ///
+    /// Adding a port name that is already present replaces its metadata.
+    pub fn add_at(&self, tick: u64, port: PortInfo) -> &Self {
+        self.schedule(tick, Change::Add(port))
+    }
+
+    /// Makes a port disappear from the given tick on.
+    pub fn remove_at(&self, tick: u64, port_name: &str) -> &Self {
+        self.schedule(tick, Change::Remove(port_name.to_string()))
+    }
+
+    /// Makes enumeration fail at the given tick. Other changes for the
+    /// same tick still apply and are visible from the next tick.
+    pub fn fail_at(&self, tick: u64) -> &Self {
+        self.schedule(tick, Change::Fail)
+    }
+
+    /// Returns the number of enumerations performed so far.
+    pub fn ticks(&self) -> u64 {
+        self.script.lock().map(|script| script.ticks).unwrap_or_default()
+    }
+
+    fn schedule(&self, tick: u64, change: Change) -> &Self {
+        if let Ok(mut script) = self.script.lock() {
+            script.changes.entry(tick).or_default().push(change);
+        }
+        self
+    }
+}
+
+impl PortEnumerator for MockEnumerator {
+    fn available_ports(&mut self) -> io::Result<Vec<PortInfo>> {
+        let mut script = self.script.lock()
+            .map_err(|_| io::Error::other("mock enumerator script poisoned"))?;
+        let tick = script.ticks;
+        script.ticks += 1;
+
+        let due: Vec<u64> = script.changes.range(..=tick).map(|(tick, _)| *tick).collect();
+        let changes: Vec<Change> = due
+            .into_iter()
+            .flat_map(|t| script.changes.remove(&t).unwrap_or_default())
+            .collect();
+        let mut failed = false;
+        for change in changes {
+            match change {
+                Change::Add(port) => {
+                    script.present.retain(|p| p.port_name != port.port_name);
+                    script.present.push(port);
+                }
+                Change::Remove(port_name) => script.present.retain(|p| p.port_name != port_name),
+                Change::Fail => failed = true,
+            }
+        }
+
+        if failed {
+            return Err(io::Error::other("scripted enumeration failure"));
+        }
+        Ok(script.present.clone())
+    }
+}
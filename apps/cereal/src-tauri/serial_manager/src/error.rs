@@ -0,0 +1,89 @@
+//! # Errors
+//!
+//! Everything that can go wrong in serial_manager is described by a
+//! SerialManagerError. Public APIs return it in a Result, and failures that
+//! happen in the background are published as SerialEvent::DiscoveryError so
+//! that the application can show them instead of them only reaching the log.
+
+use std::{fmt, io};
+
+/// Errors reported by serial_manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialManagerError {
+    /// Listing the serial ports present on the system failed
+    Enumeration(String),
+    /// The operating system refused access to a port
+    PermissionDenied {
+        /// The port that could not be accessed
+        port: String,
+    },
+    /// The port is already open, usually by another process
+    PortBusy {
+        /// The port that is in use
+        port: String,
+    },
+    /// The session was closed, explicitly or because the port went away
+    SessionClosed {
+        /// The port the session was open on
+        port: String,
+    },
+    /// The session lost its device and is waiting for it to come back
+    SessionDisconnected {
+        /// The port the device was last seen on
+        port: String,
+    },
+    /// A thread panicked while holding a lock. The data it protected was
+    /// recovered, but may be inconsistent.
+    LockPoisoned(&'static str),
+    /// Any other I/O failure
+    Io {
+        /// The category of the underlying io::Error
+        kind: io::ErrorKind,
+        /// The underlying error's description
+        message: String,
+    },
+}
+
+/// Result type for serial_manager operations
+pub type Result<T> = std::result::Result<T, SerialManagerError>;
+
+impl SerialManagerError {
+    /// Classifies an I/O error that occurred while accessing the given port.
+    pub fn from_port_io(port: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => SerialManagerError::PermissionDenied { port: port.to_string() },
+            io::ErrorKind::ResourceBusy => SerialManagerError::PortBusy { port: port.to_string() },
+            _ => error.into(),
+        }
+    }
+
+    /// Classifies a serialport error that occurred while accessing the given port.
+    pub fn from_serialport(port: &str, error: serialport::Error) -> Self {
+        Self::from_port_io(port, error.into())
+    }
+}
+
+impl fmt::Display for SerialManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialManagerError::Enumeration(message) => write!(f, "failed to list serial ports: {}", message),
+            SerialManagerError::PermissionDenied { port } => write!(f, "permission denied opening {}", port),
+            SerialManagerError::PortBusy { port } => write!(f, "{} is already in use", port),
+            SerialManagerError::SessionClosed { port } => write!(f, "session on {} is closed", port),
+            SerialManagerError::SessionDisconnected { port } => write!(f, "session on {} is waiting for its device to reconnect", port),
+            SerialManagerError::LockPoisoned(what) => write!(f, "{} lock poisoned by a panicking thread", what),
+            SerialManagerError::Io { message, .. } => write!(f, "I/O error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SerialManagerError {}
+
+impl From<io::Error> for SerialManagerError {
+    fn from(error: io::Error) -> Self {
+        SerialManagerError::Io {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! # Port Filter Rules
+//!
+//! A PortFilter decides which discovered serial ports an application is interested in.
+//! Ports rejected by the filter are never tracked in known_ports and never reported
+//! through discovery events, so a bench full of unrelated USB-serial adapters does
+//! not clutter the port list.
+//!
+//! ## Matching Rules
+//!
+//! A filter holds two lists of PortMatchers:
+//!
+//! - **include**: if non-empty, a port must match at least one of these
+//! - **exclude**: a port matching any of these is rejected, even if included
+//!
+//! Every criterion set on a PortMatcher must hold for it to match. Serial numbers,
+//! by-id links and paths are matched with glob patterns (`*`, `?`, `[...]`),
+//! manufacturer and product strings with a case-insensitive substring search.
+//!
+//! ```rust ignore
+//! let filter = PortFilter {
+//!     include: vec![PortMatcher { vid: Some(0x0483), pid: Some(0x5740), ..Default::default() }],
+//!     exclude: vec![PortMatcher { path: Some("/dev/ttyS*".into()), ..Default::default() }],
+//! };
+//! ```
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use crate::port_info::PortInfo;
+
+/// A single rule matching serial ports by their metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortMatcher {
+    /// USB vendor ID the port must have
+    pub vid: Option<u16>,
+    /// USB product ID the port must have
+    pub pid: Option<u16>,
+    /// Glob pattern the USB serial number must match
+    pub serial_number: Option<String>,
+    /// Substring the USB manufacturer string must contain (case-insensitive)
+    pub manufacturer: Option<String>,
+    /// Substring the USB product string must contain (case-insensitive)
+    pub product: Option<String>,
+    /// Glob pattern the port name must match (e.g. "/dev/ttyACM*")
+    pub path: Option<String>,
+    /// Glob pattern the /dev/serial/by-id link must match
+    pub by_id: Option<String>,
+}
+
+impl PortMatcher {
+    /// Returns true if every criterion set on this matcher holds for the port.
+    ///
+    /// A matcher with no criteria matches every port. Criteria on USB fields
+    /// and the by-id link never match ports that don't report them.
+    pub fn matches(&self, port: &PortInfo) -> bool {
+        if self.vid.is_some() && self.vid != port.vid {
+            return false;
+        }
+        if self.pid.is_some() && self.pid != port.pid {
+            return false;
+        }
+        if let Some(pattern) = &self.serial_number {
+            if !port.serial_number.as_deref().is_some_and(|s| glob_matches(pattern, s)) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.manufacturer {
+            if !port.manufacturer.as_deref().is_some_and(|s| contains_ignore_case(s, needle)) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.product {
+            if !port.product.as_deref().is_some_and(|s| contains_ignore_case(s, needle)) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.path {
+            if !glob_matches(pattern, &port.port_name) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.by_id {
+            if !port.by_id.as_deref().is_some_and(|s| glob_matches(pattern, s)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Include/exclude rules selecting which serial ports are discovered
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortFilter {
+    /// If non-empty, only ports matching at least one of these are accepted
+    pub include: Vec<PortMatcher>,
+    /// Ports matching any of these are rejected
+    pub exclude: Vec<PortMatcher>,
+}
+
+impl PortFilter {
+    /// Creates a filter that accepts every port.
+    pub fn allow_all() -> Self {
+        PortFilter::default()
+    }
+
+    /// Returns true if the port passes the include and exclude rules.
+    pub fn accepts(&self, port: &PortInfo) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|m| m.matches(port));
+        included && !self.exclude.iter().any(|m| m.matches(port))
+    }
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    // An invalid pattern can never match, rather than silently matching everything
+    Pattern::new(pattern).is_ok_and(|p| p.matches(text))
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
@@ -0,0 +1,718 @@
+//! # Framing
+//!
+//! The port hands over data in whatever chunks the driver happened to have
+//! ready, which split lines and packets at arbitrary places. A Framer turns
+//! that byte stream back into the frames the device sent, and a session with a
+//! framer publishes SerialEvent::FrameReceived for each one instead of
+//! DataReceived for each chunk.
+//!
+//! ## Built-in Framers
+//!
+//! | Framer               | Frame boundary                                  | Metadata       |
+//! |----------------------|-------------------------------------------------|----------------|
+//! | LineFramer           | LF, CR, CRLF or any of them, stripped           |                |
+//! | FixedFramer          | Every `length` bytes                            |                |
+//! | LengthPrefixedFramer | 1, 2 or 4 byte length header, stripped          |                |
+//! | SlipFramer           | RFC 1055 END bytes, unescaped                   | bad escapes    |
+//! | CobsFramer           | Zero bytes, COBS decoded                        | bad encoding   |
+//! | HdlcFramer           | 0x7E flags, unescaped, FCS-16 stripped          | CRC ok/bad     |
+//!
+//! ## Limits
+//!
+//! Every built-in framer has a maximum frame size, so that a device that never
+//! sends a delimiter cannot make it buffer without bound. Bytes beyond the
+//! limit are discarded up to the next frame boundary and the frame is marked
+//! FrameStatus::Truncated. Lines are the exception: an over-long line is cut
+//! into several frames, since nothing needs to be decoded and no byte is lost.
+//!
+//! A framer can also have an idle timeout. When no bytes arrive for that long,
+//! the session flushes the frame in progress as FrameStatus::Flushed. This shows
+//! prompts that are not followed by a newline, and lets the fixed-length and
+//! length-prefixed framers find the start of a frame again after a glitch.
+//! Sessions check the timeout between reads, so it is only as precise as
+//! PortConfig::read_timeout.
+//!
+//! ```rust ignore
+//! use serial_manager::{FramingConfig, FramingKind, PortConfig};
+//!
+//! let config = PortConfig {
+//!     framing: Some(FramingConfig { kind: FramingKind::Hdlc, ..Default::default() }),
+//!     ..Default::default()
+//! };
+//! let session = manager.open("/dev/ttyUSB0", config)?;
+//!
+//! while let Ok(event) = events.recv() {
+//!     if let SerialEvent::FrameReceived { frame, .. } = event {
+//!         println!("{} bytes, CRC {:?}", frame.bytes.len(), frame.crc_ok);
+//!     }
+//! }
+//! ```
+
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::session::Stamp;
+
+/// Largest frame the built-in framers assemble unless configured otherwise
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4096;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+const HDLC_FLAG: u8 = 0x7E;
+const HDLC_ESCAPE: u8 = 0x7D;
+const HDLC_ESCAPE_XOR: u8 = 0x20;
+const HDLC_FCS_LEN: usize = 2;
+
+/// How a frame ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameStatus {
+    /// The frame ended at a boundary and was decoded successfully
+    Complete,
+    /// The frame was cut short by the idle timeout or the session ending. It
+    /// holds the bytes received so far, without decoding or CRC check where
+    /// those need the whole frame.
+    Flushed,
+    /// The frame was longer than the maximum frame size and holds only its start
+    Truncated,
+    /// The frame could not be decoded and holds the bytes as received
+    Malformed,
+}
+
+/// A frame recovered from a session's byte stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    /// The frame's payload, without delimiters, headers or checksums
+    pub bytes: Vec<u8>,
+    /// How the frame ended
+    pub status: FrameStatus,
+    /// Whether the frame's checksum matched, for framers that check one
+    pub crc_ok: Option<bool>,
+}
+
+impl Frame {
+    /// Creates a frame with the given status and no checksum.
+    pub fn new(bytes: Vec<u8>, status: FrameStatus) -> Self {
+        Frame { bytes, status, crc_ok: None }
+    }
+}
+
+/// Turns a byte stream into frames
+///
+/// A session feeds every chunk it reads to its framer, in order, and flushes
+/// the framer when it stops reading or the idle timeout passes. Framers are
+/// used from the session's reader thread only.
+pub trait Framer: Send {
+    /// Consumes bytes read from the port, appending every frame they complete to `frames`.
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>);
+
+    /// Ends the frame in progress, returning it as FrameStatus::Flushed if it
+    /// holds any bytes. The framer then waits for the start of a new frame.
+    fn flush(&mut self) -> Option<Frame>;
+
+    /// Returns the number of bytes held for the frame in progress.
+    fn buffered(&self) -> usize;
+
+    /// How long the frame in progress may wait for more bytes before it is
+    /// flushed. None waits forever.
+    fn idle_timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Which bytes end a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// "\n"
+    Lf,
+    /// "\r"
+    Cr,
+    /// "\r\n"; a lone "\r" or "\n" is part of the line
+    CrLf,
+    /// Any of "\n", "\r" and "\r\n"
+    #[default]
+    Any,
+}
+
+/// Size and byte order of a length header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthPrefix {
+    U8,
+    U16Be,
+    U16Le,
+    U32Be,
+    U32Le,
+}
+
+impl LengthPrefix {
+    /// Returns the size of the header in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            LengthPrefix::U8 => 1,
+            LengthPrefix::U16Be | LengthPrefix::U16Le => 2,
+            LengthPrefix::U32Be | LengthPrefix::U32Le => 4,
+        }
+    }
+
+    fn decode(&self, header: &[u8]) -> usize {
+        match self {
+            LengthPrefix::U8 => header[0] as usize,
+            LengthPrefix::U16Be => u16::from_be_bytes([header[0], header[1]]) as usize,
+            LengthPrefix::U16Le => u16::from_le_bytes([header[0], header[1]]) as usize,
+            LengthPrefix::U32Be => u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize,
+            LengthPrefix::U32Le => u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize,
+        }
+    }
+}
+
+/// Which built-in framer to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FramingKind {
+    /// Text lines, see LineFramer
+    Lines { ending: LineEnding },
+    /// Frames of a fixed size, see FixedFramer
+    Fixed { length: usize },
+    /// Frames with a length header, see LengthPrefixedFramer
+    LengthPrefixed { prefix: LengthPrefix },
+    /// SLIP, see SlipFramer
+    Slip,
+    /// COBS, see CobsFramer
+    Cobs,
+    /// HDLC-like framing with FCS-16, see HdlcFramer
+    Hdlc,
+}
+
+/// Settings for one of the built-in framers
+///
+/// Fields missing when deserializing take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FramingConfig {
+    /// Which framer to use
+    pub kind: FramingKind,
+    /// Largest frame payload to assemble. Ignored by FixedFramer.
+    pub max_frame_size: usize,
+    /// How long a partial frame may wait for more bytes before it is flushed
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for FramingConfig {
+    /// Lines ending in any of CR, LF and CRLF, flushed after 200ms without data
+    fn default() -> Self {
+        FramingConfig {
+            kind: FramingKind::Lines { ending: LineEnding::Any },
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            idle_timeout: Some(Duration::from_millis(200)),
+        }
+    }
+}
+
+impl FramingConfig {
+    /// Creates the framer these settings describe.
+    pub fn build(&self) -> Box<dyn Framer> {
+        let max = self.max_frame_size.max(1);
+        let idle = self.idle_timeout;
+        match self.kind {
+            FramingKind::Lines { ending } => Box::new(LineFramer::new(ending, max, idle)),
+            FramingKind::Fixed { length } => Box::new(FixedFramer::new(length, idle)),
+            FramingKind::LengthPrefixed { prefix } => Box::new(LengthPrefixedFramer::new(prefix, max, idle)),
+            FramingKind::Slip => Box::new(SlipFramer::new(max, idle)),
+            FramingKind::Cobs => Box::new(CobsFramer::new(max, idle)),
+            FramingKind::Hdlc => Box::new(HdlcFramer::new(max, idle)),
+        }
+    }
+}
+
+/// Bytes of the frame in progress, capped at the maximum frame size
+struct FrameBuffer {
+    bytes: Vec<u8>,
+    max_frame_size: usize,
+    truncated: bool,
+}
+
+impl FrameBuffer {
+    fn new(max_frame_size: usize) -> Self {
+        FrameBuffer { bytes: Vec::new(), max_frame_size, truncated: false }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.bytes.len() < self.max_frame_size {
+            self.bytes.push(byte);
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty() && !self.truncated
+    }
+
+    fn is_full(&self) -> bool {
+        self.bytes.len() >= self.max_frame_size
+    }
+
+    /// Takes the bytes as a frame with the given status, or Truncated if bytes were discarded.
+    fn take(&mut self, status: FrameStatus) -> Frame {
+        let status = if std::mem::take(&mut self.truncated) && status == FrameStatus::Complete {
+            FrameStatus::Truncated
+        } else {
+            status
+        };
+        Frame::new(std::mem::take(&mut self.bytes), status)
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        if self.is_empty() {
+            return None;
+        }
+        self.truncated = false;
+        Some(self.take(FrameStatus::Flushed))
+    }
+}
+
+/// Splits text into lines, without their line endings
+///
+/// Empty lines are delivered as empty frames. A line longer than the maximum
+/// frame size is delivered in pieces marked FrameStatus::Truncated, the last
+/// of which is Complete.
+pub struct LineFramer {
+    ending: LineEnding,
+    line: FrameBuffer,
+    /// The previous byte was a CR that ended a line, so an LF right after it
+    /// belongs to the same line ending
+    after_cr: bool,
+    idle_timeout: Option<Duration>,
+}
+
+impl LineFramer {
+    /// Creates a framer for lines ending in `ending`.
+    pub fn new(ending: LineEnding, max_frame_size: usize, idle_timeout: Option<Duration>) -> Self {
+        LineFramer {
+            ending,
+            line: FrameBuffer::new(max_frame_size.max(1)),
+            after_cr: false,
+            idle_timeout,
+        }
+    }
+}
+
+impl Framer for LineFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            let ends_line = match (self.ending, byte) {
+                (LineEnding::Lf, b'\n') | (LineEnding::Cr, b'\r') => true,
+                (LineEnding::CrLf, b'\n') if self.line.bytes.last() == Some(&b'\r') => {
+                    self.line.bytes.pop();
+                    true
+                }
+                (LineEnding::Any, b'\r') => {
+                    self.after_cr = true;
+                    true
+                }
+                (LineEnding::Any, b'\n') if after_cr => continue,
+                (LineEnding::Any, b'\n') => true,
+                _ => false,
+            };
+            if ends_line {
+                frames.push(self.line.take(FrameStatus::Complete));
+                continue;
+            }
+            // Keep a CR in reserve for a CRLF ending, even in a full line
+            if self.line.is_full() && !(self.ending == LineEnding::CrLf && self.line.bytes.last() == Some(&b'\r')) {
+                frames.push(self.line.take(FrameStatus::Truncated));
+            }
+            self.line.bytes.push(byte);
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        self.after_cr = false;
+        self.line.flush()
+    }
+
+    fn buffered(&self) -> usize {
+        self.line.bytes.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Cuts the stream into frames of a fixed size
+pub struct FixedFramer {
+    length: usize,
+    frame: Vec<u8>,
+    idle_timeout: Option<Duration>,
+}
+
+impl FixedFramer {
+    /// Creates a framer for frames of `length` bytes, at least one.
+    pub fn new(length: usize, idle_timeout: Option<Duration>) -> Self {
+        FixedFramer { length: length.max(1), frame: Vec::new(), idle_timeout }
+    }
+}
+
+impl Framer for FixedFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            self.frame.push(byte);
+            if self.frame.len() == self.length {
+                frames.push(Frame::new(std::mem::take(&mut self.frame), FrameStatus::Complete));
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        (!self.frame.is_empty()).then(|| Frame::new(std::mem::take(&mut self.frame), FrameStatus::Flushed))
+    }
+
+    fn buffered(&self) -> usize {
+        self.frame.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Reads frames that start with a header giving the payload length
+///
+/// The header is not part of the frame. A frame announcing more than the
+/// maximum frame size is read in full, but only its start is kept.
+pub struct LengthPrefixedFramer {
+    prefix: LengthPrefix,
+    header: Vec<u8>,
+    /// Payload bytes still expected, once the header is complete
+    remaining: Option<usize>,
+    payload: FrameBuffer,
+    idle_timeout: Option<Duration>,
+}
+
+impl LengthPrefixedFramer {
+    /// Creates a framer for frames with the given length header.
+    pub fn new(prefix: LengthPrefix, max_frame_size: usize, idle_timeout: Option<Duration>) -> Self {
+        LengthPrefixedFramer {
+            prefix,
+            header: Vec::with_capacity(prefix.size()),
+            remaining: None,
+            payload: FrameBuffer::new(max_frame_size),
+            idle_timeout,
+        }
+    }
+}
+
+impl Framer for LengthPrefixedFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            match self.remaining {
+                None => {
+                    self.header.push(byte);
+                    if self.header.len() == self.prefix.size() {
+                        self.remaining = Some(self.prefix.decode(&self.header));
+                        self.header.clear();
+                    }
+                }
+                Some(remaining) => {
+                    self.payload.push(byte);
+                    self.remaining = Some(remaining - 1);
+                }
+            }
+            if self.remaining == Some(0) {
+                self.remaining = None;
+                frames.push(self.payload.take(FrameStatus::Complete));
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        self.header.clear();
+        self.remaining = None;
+        self.payload.flush()
+    }
+
+    fn buffered(&self) -> usize {
+        self.header.len() + self.payload.bytes.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Decodes SLIP (RFC 1055) frames
+///
+/// Frames end with an END byte; END bytes with nothing between them are
+/// ignored, so devices may also start frames with one. A frame containing an
+/// invalid escape sequence is marked FrameStatus::Malformed, with the escaped
+/// byte kept as received.
+pub struct SlipFramer {
+    frame: FrameBuffer,
+    escaped: bool,
+    malformed: bool,
+    idle_timeout: Option<Duration>,
+}
+
+impl SlipFramer {
+    /// Creates a SLIP decoder.
+    pub fn new(max_frame_size: usize, idle_timeout: Option<Duration>) -> Self {
+        SlipFramer { frame: FrameBuffer::new(max_frame_size), escaped: false, malformed: false, idle_timeout }
+    }
+}
+
+impl Framer for SlipFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            if byte == SLIP_END {
+                if !self.frame.is_empty() || self.escaped || self.malformed {
+                    let status = if self.escaped || std::mem::take(&mut self.malformed) {
+                        FrameStatus::Malformed
+                    } else {
+                        FrameStatus::Complete
+                    };
+                    self.escaped = false;
+                    frames.push(self.frame.take(status));
+                }
+                continue;
+            }
+            if std::mem::take(&mut self.escaped) {
+                match byte {
+                    SLIP_ESC_END => self.frame.push(SLIP_END),
+                    SLIP_ESC_ESC => self.frame.push(SLIP_ESC),
+                    _ => {
+                        self.malformed = true;
+                        self.frame.push(byte);
+                    }
+                }
+            } else if byte == SLIP_ESC {
+                self.escaped = true;
+            } else {
+                self.frame.push(byte);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        self.escaped = false;
+        self.malformed = false;
+        self.frame.flush()
+    }
+
+    fn buffered(&self) -> usize {
+        self.frame.bytes.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Decodes COBS frames, each ended by a zero byte
+///
+/// Empty frames are ignored. A frame that is not valid COBS is marked
+/// FrameStatus::Malformed and holds the encoded bytes.
+pub struct CobsFramer {
+    encoded: FrameBuffer,
+    idle_timeout: Option<Duration>,
+}
+
+impl CobsFramer {
+    /// Creates a COBS decoder.
+    pub fn new(max_frame_size: usize, idle_timeout: Option<Duration>) -> Self {
+        // Encoding adds a byte per 254 bytes of payload, plus one
+        let max_encoded_size = max_frame_size + max_frame_size / 254 + 1;
+        CobsFramer { encoded: FrameBuffer::new(max_encoded_size), idle_timeout }
+    }
+}
+
+impl Framer for CobsFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            if byte != 0 {
+                self.encoded.push(byte);
+                continue;
+            }
+            if self.encoded.is_empty() {
+                continue;
+            }
+            let frame = self.encoded.take(FrameStatus::Complete);
+            if frame.status == FrameStatus::Truncated {
+                frames.push(frame);
+                continue;
+            }
+            frames.push(match cobs_decode(&frame.bytes) {
+                Some(decoded) => Frame::new(decoded, FrameStatus::Complete),
+                None => Frame::new(frame.bytes, FrameStatus::Malformed),
+            });
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        self.encoded.flush()
+    }
+
+    fn buffered(&self) -> usize {
+        self.encoded.bytes.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Decodes a COBS encoded frame without its trailing zero.
+fn cobs_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let code = encoded[index] as usize;
+        let end = index + code;
+        if code == 0 || end > encoded.len() {
+            return None;
+        }
+        decoded.extend_from_slice(&encoded[index + 1..end]);
+        index = end;
+        // A full block of 254 bytes is not followed by an implicit zero
+        if code < 0xFF && index < encoded.len() {
+            decoded.push(0);
+        }
+    }
+    Some(decoded)
+}
+
+/// Decodes HDLC-like frames as used by PPP (RFC 1662)
+///
+/// Frames are delimited by 0x7E flags and byte-stuffed with 0x7D. The last two
+/// bytes of each frame are a CRC-16/X.25 frame check sequence, least
+/// significant byte first; it is checked, reported in Frame::crc_ok and removed.
+/// Frames too short to hold a check sequence are marked FrameStatus::Malformed.
+pub struct HdlcFramer {
+    frame: FrameBuffer,
+    escaped: bool,
+    idle_timeout: Option<Duration>,
+}
+
+impl HdlcFramer {
+    /// Creates an HDLC decoder.
+    pub fn new(max_frame_size: usize, idle_timeout: Option<Duration>) -> Self {
+        HdlcFramer { frame: FrameBuffer::new(max_frame_size + HDLC_FCS_LEN), escaped: false, idle_timeout }
+    }
+}
+
+impl Framer for HdlcFramer {
+    fn push(&mut self, bytes: &[u8], frames: &mut Vec<Frame>) {
+        for &byte in bytes {
+            match byte {
+                HDLC_FLAG => {
+                    // An escape right before a flag aborts the frame
+                    if std::mem::take(&mut self.escaped) {
+                        frames.push(self.frame.take(FrameStatus::Malformed));
+                        continue;
+                    }
+                    if self.frame.is_empty() {
+                        continue;
+                    }
+                    let mut frame = self.frame.take(FrameStatus::Complete);
+                    if frame.status == FrameStatus::Complete {
+                        if frame.bytes.len() < HDLC_FCS_LEN {
+                            frame.status = FrameStatus::Malformed;
+                        } else {
+                            let split = frame.bytes.len() - HDLC_FCS_LEN;
+                            let fcs = u16::from_le_bytes([frame.bytes[split], frame.bytes[split + 1]]);
+                            frame.bytes.truncate(split);
+                            frame.crc_ok = Some(crc16_x25(&frame.bytes) == fcs);
+                        }
+                    }
+                    frames.push(frame);
+                }
+                HDLC_ESCAPE if !self.escaped => self.escaped = true,
+                _ if std::mem::take(&mut self.escaped) => self.frame.push(byte ^ HDLC_ESCAPE_XOR),
+                _ => self.frame.push(byte),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Option<Frame> {
+        self.escaped = false;
+        self.frame.flush()
+    }
+
+    fn buffered(&self) -> usize {
+        self.frame.bytes.len()
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// CRC-16/X.25, the frame check sequence of HDLC and PPP
+pub fn crc16_x25(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Runs a session's data through its framer and works out when each frame started
+pub(crate) struct Deframer {
+    framer: Box<dyn Framer>,
+    /// When the chunk holding the first byte of the frame in progress was read
+    started: Option<Stamp>,
+    last_data: Instant,
+}
+
+impl Deframer {
+    pub(crate) fn new(framer: Box<dyn Framer>) -> Self {
+        Deframer { framer, started: None, last_data: Instant::now() }
+    }
+
+    /// Feeds a chunk read at `read_at`, returning the frames it completed
+    /// with the time their first byte was read.
+    pub(crate) fn push(&mut self, bytes: &[u8], read_at: Stamp) -> Vec<(Frame, Stamp)> {
+        let first_started = match self.started {
+            Some(started) if self.framer.buffered() > 0 => started,
+            _ => read_at,
+        };
+        let mut frames = Vec::new();
+        self.framer.push(bytes, &mut frames);
+        self.last_data = Instant::now();
+
+        let in_progress = self.framer.buffered() > 0;
+        self.started = match (frames.is_empty(), in_progress) {
+            (_, false) => None,
+            (true, true) => Some(first_started),
+            (false, true) => Some(read_at),
+        };
+        // Only the first frame can have started in an earlier chunk
+        frames.into_iter()
+            .enumerate()
+            .map(|(index, frame)| (frame, if index == 0 { first_started } else { read_at }))
+            .collect()
+    }
+
+    /// Flushes the frame in progress if no data arrived within the framer's idle timeout.
+    pub(crate) fn poll_idle(&mut self, now: Stamp) -> Option<(Frame, Stamp)> {
+        let idle_timeout = self.framer.idle_timeout()?;
+        if self.framer.buffered() == 0 || self.last_data.elapsed() < idle_timeout {
+            return None;
+        }
+        self.flush(now)
+    }
+
+    /// Flushes the frame in progress. Frames whose start is unknown are dated `now`.
+    pub(crate) fn flush(&mut self, now: Stamp) -> Option<(Frame, Stamp)> {
+        let started = self.started.take().unwrap_or(now);
+        self.framer.flush().map(|frame| (frame, started))
+    }
+}
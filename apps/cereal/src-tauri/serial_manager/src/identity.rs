@@ -0,0 +1,107 @@
+//! # Stable Device Identity
+//!
+//! Kernel port names are not stable: when a board resets while its port is still
+//! open, Linux hands out the next free node and /dev/ttyACM0 becomes /dev/ttyACM1.
+//! A DeviceIdentity names the physical device instead, so discovery can recognise
+//! it under its new name and sessions or profiles can follow it.
+//!
+//! ## Identity Sources
+//!
+//! In order of preference:
+//!
+//! 1. **USB serial number**: together with VID/PID, unique per physical device
+//! 2. **/dev/serial/by-id link**: persistent name created by udev
+//! 3. **Physical location**: the USB bus and port chain the device is plugged into
+
+use serde::{Deserialize, Serialize};
+use crate::port_info::PortInfo;
+
+/// A name for a physical serial device that survives re-enumeration
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceIdentity {
+    /// USB device reporting a serial number
+    UsbSerial {
+        vid: u16,
+        pid: u16,
+        serial_number: String,
+    },
+    /// Persistent /dev/serial/by-id symlink pointing at the port
+    ById(String),
+    /// Physical connection path, e.g. the USB bus-port chain "1-2.3"
+    Location(String),
+}
+
+impl DeviceIdentity {
+    /// Derives the most specific identity available for a port.
+    ///
+    /// Returns None for ports that only have a kernel name, such as
+    /// on-board UARTs, since nothing distinguishes them across resets.
+    pub fn of(port: &PortInfo) -> Option<Self> {
+        if let (Some(vid), Some(pid), Some(serial_number)) = (port.vid, port.pid, &port.serial_number) {
+            return Some(DeviceIdentity::UsbSerial { vid, pid, serial_number: serial_number.clone() });
+        }
+        if let Some(by_id) = &port.by_id {
+            return Some(DeviceIdentity::ById(by_id.clone()));
+        }
+        port.location.clone().map(DeviceIdentity::Location)
+    }
+}
+
+/// Fills in the by-id link and physical location of each port.
+///
+/// The /dev/serial/by-id directory is read once for the whole batch.
+/// Only implemented on Linux; elsewhere the ports are left unchanged.
+pub(crate) fn resolve_paths<'a>(ports: impl IntoIterator<Item = &'a mut PortInfo>) {
+    #[cfg(target_os = "linux")]
+    {
+        let by_id = linux::by_id_links();
+        for port in ports {
+            if port.by_id.is_none() {
+                port.by_id = by_id.get(&port.port_name).cloned();
+            }
+            if port.location.is_none() {
+                port.location = linux::usb_location(&port.port_name);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = ports;
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{collections::HashMap, fs, path::Path};
+
+    const BY_ID_DIR: &str = "/dev/serial/by-id";
+
+    /// Maps port names to the /dev/serial/by-id links that point at them.
+    pub fn by_id_links() -> HashMap<String, String> {
+        let mut links = HashMap::new();
+        let Ok(entries) = fs::read_dir(BY_ID_DIR) else {
+            return links;
+        };
+        for entry in entries.flatten() {
+            let link = entry.path();
+            if let Ok(target) = fs::canonicalize(&link) {
+                links.insert(
+                    target.to_string_lossy().into_owned(),
+                    link.to_string_lossy().into_owned(),
+                );
+            }
+        }
+        links
+    }
+
+    /// Returns the sysfs name of the USB device behind a tty, which encodes
+    /// the bus number and the chain of hub ports it is plugged into.
+    pub fn usb_location(port_name: &str) -> Option<String> {
+        let tty = Path::new(port_name).file_name()?;
+        let device = fs::canonicalize(Path::new("/sys/class/tty").join(tty).join("device")).ok()?;
+        device
+            .ancestors()
+            .find(|dir| dir.join("idVendor").is_file())
+            .and_then(|usb| usb.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+}
@@ -8,26 +8,150 @@
 //! ## Quick Start
 //! 
 //! ```rust ignore
-//! use serial_manager::{SerialManager, PortEvent};
+//! use serial_manager::{SerialManager, SerialEvent, PortFilter};
 //! 
-//! let manager = SerialManager::new();
-//! let ports = manager.get_ports();
-//! println!("Available ports: {:?}", ports);
+//! let manager = SerialManager::new(PortFilter::allow_all());
+//! for port in manager.get_ports()? {
+//!     println!("{} ({})", port.display_name(), port.port_name);
+//! }
 //! 
-//! // Poll for events
-//! while let Ok(event) = manager.poll_event() {
+//! // Each subscriber receives every event independently
+//! let events = manager.subscribe()?;
+//! while let Ok(event) = events.recv() {
 //!     match event {
-//!         PortEvent::Added(port) => println!("Device connected: {}", port),
-//!         PortEvent::Removed(port) => println!("Device disconnected: {}", port),
+//!         SerialEvent::DeviceArrived { port, .. } => println!("Device connected: {}", port.port_name),
+//!         SerialEvent::DeviceDeparted { port, .. } => println!("Device disconnected: {}", port.port_name),
+//!         SerialEvent::DeviceMoved { from, to, .. } => println!("Device moved: {} -> {}", from.port_name, to.port_name),
+//!         SerialEvent::DeviceReenumerated { port, .. } => println!("Device reset: {}", port.port_name),
+//!         SerialEvent::DiscoveryError { error, .. } => eprintln!("Discovery problem: {}", error),
+//!         SerialEvent::DataReceived { session, bytes, .. } => println!("{}: {} bytes", session, bytes.len()),
+//!         SerialEvent::DataSent { session, bytes, .. } => println!("{}: sent {} bytes", session, bytes.len()),
+//!         SerialEvent::FrameReceived { session, frame, .. } => println!("{}: {:?} frame", session, frame.status),
+//!         SerialEvent::ControlLinesChanged { session, lines, .. } => println!("{}: CTS {}", session, lines.cts),
+//!         SerialEvent::TransmitProgress { sent, total, .. } => println!("Sent {} of {} bytes", sent, total),
+//!         SerialEvent::TransmitFinished { transmit, outcome, .. } => println!("{}: {:?}", transmit, outcome),
+//!         SerialEvent::SessionDisconnected { port_name, .. } => println!("Lost {}, waiting for it", port_name),
+//!         SerialEvent::SessionReconnected { port_name, .. } => println!("Reconnected on {}", port_name),
+//!         SerialEvent::DataGap { since, until, .. } => println!("No data from {:?} to {:?}", since, until),
+//!         SerialEvent::SessionClosed { port_name, reason, .. } => println!("Closed {}: {:?}", port_name, reason),
 //!     }
 //! }
 //! ```
+//! 
+//! ## Sessions
+//! 
+//! Ports are opened as sessions, which publish what they read as DataReceived
+//! events and can be written from any thread.
+//! 
+//! ```rust ignore
+//! use serial_manager::{Parity, PortConfig};
+//! 
+//! let session = manager.open("/dev/ttyUSB0", PortConfig { baud_rate: 9600, parity: Parity::Even, ..Default::default() })?;
+//! session.write(b"status\r\n")?;
+//! // ... DataReceived events arrive on every subscriber ...
+//! session.close();
+//! println!("dropped {} chunks", session.stats().chunks_dropped);
+//! ```
+//! 
+//! Give the PortConfig a FramingConfig to receive FrameReceived events with
+//! whole lines or packets instead, or pass your own Framer to open_with_framer().
+//! 
+//! On Unix, create_virtual_port() adds a pseudo terminal that is discovered and
+//! opened like a real port, with the device end in the application's hands,
+//! which makes it possible to test without hardware.
+//! 
+//! SendMode turns what a user typed into bytes to write: text with a line
+//! ending, text with C-style escapes, or hex.
+//! 
+//! Data can also be queued with transmit() or transmit_file(), to be sent
+//! with delays between characters and lines or line by line after the
+//! device's prompt, for devices that cannot keep up.
+//! 
+//! A session can record what it reads and writes to a capture file with
+//! start_capture(). On Unix, replay_capture() plays a capture back through a
+//! virtual port, with its original timing or faster.
+//! 
+//! Several sessions can be open at once. SerialManager::timeline() merges what
+//! they receive and send into one stream ordered on a shared monotonic clock.
+//! 
+//! ## Async
+//! 
+//! With the `tokio` feature enabled, discovery can run as a tokio task and events
+//! can be consumed as a Stream, so no thread has to block waiting for them.
+//! 
+//! ```rust ignore
+//! use futures::StreamExt;
+//! use serial_manager::{CancellationToken, DiscoveryConfig, PortFilter, SerialManager};
+//! 
+//! let manager = SerialManager::spawn_async(PortFilter::allow_all(), DiscoveryConfig::default(), CancellationToken::new());
+//! let mut events = manager.events()?;
+//! while let Some(event) = events.next().await {
+//!     println!("Event: {:?}", event);
+//! }
+//! ```
 
 mod manager;
+mod baud;
+mod bus;
+mod capture;
+mod clock;
+mod control;
 mod discovery;
+mod enumerator;
+mod error;
+mod filter;
+mod framing;
+mod identity;
+mod payload;
+mod port_info;
+#[cfg(unix)]
+mod replay;
+mod session;
+mod timeline;
+mod transmit;
+mod uevent;
+#[cfg(unix)]
+mod virtual_port;
+#[cfg(feature = "tokio")]
+mod async_port;
 
 // Re-export the public API
 pub use manager::{SerialManager, SerialEvent};  // Added SerialEvent
+pub use baud::{detect_baud, rank_samples, BaudCandidate, BaudScore, COMMON_BAUD_RATES};
+pub use bus::{
+    EventBus, EventReceiver, Overflow, OverflowHandler, RecvError, TryRecvError, DEFAULT_SUBSCRIBER_CAPACITY,
+    MAX_COALESCED_BYTES,
+};
+pub use capture::{CaptureHeader, CaptureReader, CaptureRecord, CaptureWriter, ReplayTiming};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use control::{ControlLines, ResetSequence, ResetStep};
+pub use discovery::{DiscoveryConfig, DeviceStability, DiscoveryService, PortEvent, PortEventCallback};
+pub use enumerator::{MockEnumerator, PortEnumerator, SystemEnumerator};
+pub use error::{Result, SerialManagerError};
+pub use filter::{PortFilter, PortMatcher};
+pub use framing::{
+    crc16_x25, CobsFramer, FixedFramer, Frame, FrameStatus, Framer, FramingConfig, FramingKind, HdlcFramer, LengthPrefix,
+    LengthPrefixedFramer, LineEnding, LineFramer, SlipFramer, DEFAULT_MAX_FRAME_SIZE,
+};
+pub use identity::DeviceIdentity;
+pub use payload::{SendLineEnding, SendMode};
+pub use port_info::{PortInfo, PortType};
+pub use session::{CloseReason, DataBits, FlowControl, Parity, PortConfig, SessionHandle, SessionId, SessionStats, StopBits};
+pub use timeline::{Direction, Timeline, TimelineEntry, DEFAULT_REORDER_WINDOW};
+pub use transmit::{TransmitId, TransmitMode, TransmitOptions, TransmitOutcome};
+pub use uevent::{Uevent, UeventAction, UeventSource, InjectedUeventSource};
+#[cfg(target_os = "linux")]
+pub use uevent::NetlinkUeventSource;
+#[cfg(unix)]
+pub use replay::Replay;
+#[cfg(unix)]
+pub use virtual_port::VirtualDevice;
+#[cfg(feature = "tokio")]
+pub use async_port::AsyncSerialPort;
+#[cfg(feature = "tokio")]
+pub use bus::EventStream;
+#[cfg(feature = "tokio")]
+pub use tokio_util::sync::CancellationToken;
 
 // Re-export common types that users might need
 pub use std::time::{Duration, SystemTime};
\ No newline at end of file
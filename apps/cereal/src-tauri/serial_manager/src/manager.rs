@@ -1,102 +1,720 @@
-use std::{collections::HashSet, sync::Arc, sync::Mutex, sync::mpsc::Sender};
-use crate::discovery::{DiscoveryService, PortEvent, PortEventCallback};
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex, Weak}, time::Duration};
+use crate::bus::{EventBus, EventReceiver, DEFAULT_SUBSCRIBER_CAPACITY};
+use crate::clock::Clock;
+use crate::control::ControlLines;
+use crate::discovery::{DiscoveryConfig, DeviceStability, DiscoveryService, PortEvent, PortEventCallback};
+use crate::enumerator::PortEnumerator;
+use crate::error::{Result, SerialManagerError};
+use crate::filter::PortFilter;
+use crate::framing::{Frame, Framer, FramingConfig};
+use crate::identity::DeviceIdentity;
+use crate::port_info::PortInfo;
+use crate::session::{
+    CloseReason, PortConfig, SessionEvent, SessionEventCallback, SessionHandle, SessionId, SessionRegistry, SessionStats,
+};
+use crate::timeline::{Timeline, TIMELINE_CAPACITY};
+use crate::transmit::{TransmitId, TransmitOutcome};
+#[cfg(unix)]
+use crate::capture::{CaptureReader, ReplayTiming};
+#[cfg(unix)]
+use crate::replay::Replay;
+#[cfg(unix)]
+use crate::virtual_port::VirtualDevice;
 
 /// High-level events emitted by the SerialManager
 #[derive(Debug, Clone, PartialEq)]
 pub enum SerialEvent {
     /// A new serial device was connected and is now available
     DeviceArrived {
-        port_name: String,
+        /// Metadata of the port that appeared
+        port: PortInfo,
         /// Timestamp when the device was detected
         timestamp: std::time::SystemTime,
     },
     /// A serial device was disconnected and is no longer available
     DeviceDeparted {
-        port_name: String,
+        /// Metadata of the port that disappeared, as last seen
+        port: PortInfo,
         /// Timestamp when the device was removed
         timestamp: std::time::SystemTime,
     },
+    /// A known device re-enumerated under a different port name
+    DeviceMoved {
+        /// The port the device was last seen on
+        from: PortInfo,
+        /// The port the device is now available on
+        to: PortInfo,
+        /// The stable identity that links the two ports
+        identity: DeviceIdentity,
+        /// Timestamp when the move was detected
+        timestamp: std::time::SystemTime,
+    },
+    /// A known device dropped out and came back on the same port within the settle window
+    DeviceReenumerated {
+        /// The port as it looks after coming back
+        port: PortInfo,
+        /// The stable identity of the device
+        identity: DeviceIdentity,
+        /// How often the device has re-enumerated recently, and whether it is unstable
+        stability: DeviceStability,
+        /// Timestamp when the device came back
+        timestamp: std::time::SystemTime,
+    },
+    /// Discovery failed to do its job, e.g. the system ports could not be listed.
+    /// Discovery keeps running; a persistent failure is only reported once.
+    DiscoveryError {
+        /// What went wrong
+        error: SerialManagerError,
+        /// Timestamp when the problem was detected
+        timestamp: std::time::SystemTime,
+    },
+    /// Bytes were read from a session's port
+    DataReceived {
+        /// The session the bytes were read on
+        session: SessionId,
+        /// The bytes, in the order they arrived. If the subscriber fell behind,
+        /// several reads may have been coalesced into one event.
+        bytes: Vec<u8>,
+        /// Time taken right after the (first) read returned
+        host_timestamp: std::time::SystemTime,
+        /// Same moment as host_timestamp on the manager's monotonic clock,
+        /// which all sessions share. Use it to order data across sessions.
+        elapsed: Duration,
+    },
+    /// Bytes were written to a session's port with SessionHandle::write()
+    DataSent {
+        /// The session the bytes were written to
+        session: SessionId,
+        /// The bytes written
+        bytes: Vec<u8>,
+        /// Time taken right after the write completed
+        host_timestamp: std::time::SystemTime,
+        /// Same moment as host_timestamp on the manager's monotonic clock
+        elapsed: Duration,
+    },
+    /// A session's framer assembled a frame from the bytes read. Sessions with
+    /// a framer publish these instead of DataReceived.
+    FrameReceived {
+        /// The session the frame was read on
+        session: SessionId,
+        /// The frame, with the framer's metadata
+        frame: Frame,
+        /// Time taken right after the read holding the frame's first byte returned
+        host_timestamp: std::time::SystemTime,
+        /// Same moment as host_timestamp on the manager's monotonic clock
+        elapsed: Duration,
+    },
+    /// The control lines driven by a session's device changed. Only published
+    /// while the session watches them, see SessionHandle::watch_control_lines().
+    ControlLinesChanged {
+        /// The session whose lines changed
+        session: SessionId,
+        /// The new state of the lines
+        lines: ControlLines,
+        /// Timestamp when the change was seen
+        timestamp: std::time::SystemTime,
+    },
+    /// A transmission queued with SessionHandle::transmit() sent another line or block
+    TransmitProgress {
+        /// The session sending it
+        session: SessionId,
+        /// The transmission
+        transmit: TransmitId,
+        /// Bytes sent so far
+        sent: u64,
+        /// Bytes to send in all, after line endings were replaced
+        total: u64,
+        /// Timestamp when the line or block was written
+        timestamp: std::time::SystemTime,
+    },
+    /// A transmission queued with SessionHandle::transmit() ended
+    TransmitFinished {
+        /// The session that sent it
+        session: SessionId,
+        /// The transmission
+        transmit: TransmitId,
+        /// Whether everything was sent, and if not why
+        outcome: TransmitOutcome,
+        /// Timestamp when the transmission ended
+        timestamp: std::time::SystemTime,
+    },
+    /// A session with auto-reconnect lost its device and is waiting for it to come back
+    SessionDisconnected {
+        /// The session that lost its device
+        session: SessionId,
+        /// The port the device was last seen on
+        port_name: String,
+        /// How the device was lost
+        reason: CloseReason,
+        /// Timestamp when the port was released
+        timestamp: std::time::SystemTime,
+    },
+    /// A disconnected session reopened its device, possibly on a different port
+    SessionReconnected {
+        /// The session that reconnected
+        session: SessionId,
+        /// The port the device is now open on
+        port_name: String,
+        /// Timestamp when the port was reopened
+        timestamp: std::time::SystemTime,
+    },
+    /// Marks the place in a session's data where nothing could be received.
+    /// Published right after SessionReconnected, before any newly received data.
+    DataGap {
+        /// The session whose data has a gap
+        session: SessionId,
+        /// When the device was lost
+        since: std::time::SystemTime,
+        /// When the device was reopened
+        until: std::time::SystemTime,
+    },
+    /// A session ended and its port was released
+    SessionClosed {
+        /// The session that ended
+        session: SessionId,
+        /// The port the session was open on
+        port_name: String,
+        /// Why the session ended
+        reason: CloseReason,
+        /// Timestamp when the port was released
+        timestamp: std::time::SystemTime,
+    },
 }
 
 /// High-level interface for serial port management with background discovery.
 /// 
 /// The SerialManager provides a simple API for accessing discovered serial ports
 /// while running a background service to monitor for device changes. Events are
-/// sent through the provided sender channel.
+/// broadcast to every receiver obtained from subscribe().
+/// 
+/// Ports are opened as sessions with open(). Sessions are closed when their
+/// device departs and when the manager is dropped.
 pub struct SerialManager {
-    known_ports: Arc<Mutex<HashSet<String>>>,
+    known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+    bus: Arc<EventBus>,
+    sessions: Arc<SessionRegistry>,
     discovery: DiscoveryService,
 }
 
 impl SerialManager {
-    /// Creates a new SerialManager with the provided event sender.
+    /// Creates a new SerialManager and starts background discovery.
     /// 
-    /// This allows the caller to control where events are sent and how they are handled.
+    /// Events are published on an internal bus; call subscribe() to listen.
     /// 
     /// # Arguments
     /// 
-    /// * `event_sender` - Channel sender for broadcasting SerialEvents to listeners
+    /// * `filter` - Rules selecting which ports are tracked and reported
     /// 
     /// # Returns
     /// 
-    /// A new SerialManager instance that will send events through the provided sender.
+    /// A new SerialManager instance with an active discovery service.
     /// 
     /// # Example
     /// 
-    /// ```rust
-    /// use std::sync::mpsc;
-    /// let (sender, receiver) = mpsc::channel();
-    /// let manager = SerialManager::new(sender);
+    /// ```rust ignore
+    /// let manager = SerialManager::new(PortFilter::allow_all());
+    /// let receiver = manager.subscribe()?;
     /// 
     /// // Listen for events on the receiver
     /// while let Ok(event) = receiver.recv() {
     ///     println!("Event: {:?}", event);
     /// }
     /// ```
-    pub fn new(event_sender: Sender<SerialEvent>) -> Self {
-        let known_ports = Arc::new(Mutex::new(HashSet::new()));
-
-        // Create callback that converts PortEvents to SerialEvents
-        let callback: PortEventCallback = Box::new(move |port_event| {
-            let serial_event = match port_event {
-                PortEvent::Added(port_name) => SerialEvent::DeviceArrived {
-                    port_name,
-                    timestamp: std::time::SystemTime::now(),
-                },
-                PortEvent::Removed(port_name) => SerialEvent::DeviceDeparted {
-                    port_name,
-                    timestamp: std::time::SystemTime::now(),
-                },
-            };
-            
-            // Send to event sender (ignore if channel is closed or full)
-            let _ = event_sender.send(serial_event);
-        });
+    pub fn new(filter: PortFilter) -> Self {
+        Self::with_config(filter, DiscoveryConfig::default())
+    }
+
+    /// Creates a new SerialManager with custom discovery tuning.
+    /// 
+    /// Same as new(), but allows the settle window and flapping thresholds
+    /// used to debounce re-enumerating devices to be chosen.
+    pub fn with_config(filter: PortFilter, config: DiscoveryConfig) -> Self {
+        let known_ports = Arc::new(Mutex::new(HashMap::new()));
+        let (bus, sessions) = session_bus();
 
         // Start discovery with callback
-        let discovery = DiscoveryService::spawn(known_ports.clone(), callback);
+        let discovery = DiscoveryService::spawn(
+            known_ports.clone(),
+            filter,
+            config,
+            publisher(bus.clone(), sessions.clone())
+        );
+
+        SerialManager {
+            known_ports,
+            bus,
+            sessions,
+            discovery,
+        }
+    }
+
+    /// Creates a new SerialManager whose discovery polls the given enumerator
+    /// and measures time with the given clock.
+    /// 
+    /// Intended for tests: with a MockEnumerator and a MockClock, the ports
+    /// the manager sees and when it sees them are fully scripted.
+    pub fn with_enumerator(
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        enumerator: Box<dyn PortEnumerator>,
+        clock: Arc<dyn Clock>
+    ) -> Self {
+        let known_ports = Arc::new(Mutex::new(HashMap::new()));
+        let (bus, sessions) = session_bus();
+        let discovery = DiscoveryService::spawn_with_enumerator(
+            known_ports.clone(),
+            filter,
+            config,
+            publisher(bus.clone(), sessions.clone()),
+            enumerator,
+            clock
+        );
+
+        SerialManager {
+            known_ports,
+            bus,
+            sessions,
+            discovery,
+        }
+    }
+
+    /// Creates a new SerialManager whose discovery runs as a tokio task.
+    /// 
+    /// Same as with_config(), but no thread is dedicated to discovery. The task
+    /// stops when `cancel` is cancelled or the manager is dropped.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if called outside the context of a tokio runtime.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let manager = SerialManager::spawn_async(
+    ///     PortFilter::allow_all(),
+    ///     DiscoveryConfig::default(),
+    ///     CancellationToken::new(),
+    /// );
+    /// let mut events = manager.events()?;
+    /// while let Some(event) = events.next().await {
+    ///     println!("Event: {:?}", event);
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn spawn_async(
+        filter: PortFilter,
+        config: DiscoveryConfig,
+        cancel: tokio_util::sync::CancellationToken
+    ) -> Self {
+        let known_ports = Arc::new(Mutex::new(HashMap::new()));
+        let (bus, sessions) = session_bus();
+        let discovery = DiscoveryService::spawn_async(
+            known_ports.clone(),
+            filter,
+            config,
+            publisher(bus.clone(), sessions.clone()),
+            cancel
+        );
 
         SerialManager {
             known_ports,
+            bus,
+            sessions,
             discovery,
         }
     }
 
-    /// Returns a sorted list of currently available serial port names.
+    /// Subscribes to SerialEvents with the default queue capacity.
+    /// 
+    /// The receiver starts with a DeviceArrived event for every port currently
+    /// present, followed by every event published after this call, so a late
+    /// subscriber sees the same state as one that was there from the start.
+    pub fn subscribe(&self) -> Result<EventReceiver> {
+        self.subscribe_with_capacity(DEFAULT_SUBSCRIBER_CAPACITY)
+    }
+
+    /// Subscribes to SerialEvents, buffering up to `capacity` events.
+    /// 
+    /// If the subscriber falls further behind than that, the oldest events are
+    /// dropped and its next recv() reports how many were missed.
+    pub fn subscribe_with_capacity(&self, capacity: usize) -> Result<EventReceiver> {
+        // Holding the known_ports lock keeps discovery from publishing between
+        // taking the snapshot and registering the subscriber
+        let known = self.known_ports.lock()
+            .map_err(|_| SerialManagerError::LockPoisoned("known ports"))?;
+        let mut ports: Vec<&PortInfo> = known.values().collect();
+        ports.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+
+        let timestamp = std::time::SystemTime::now();
+        let snapshot = ports
+            .into_iter()
+            .map(|port| SerialEvent::DeviceArrived { port: port.clone(), timestamp })
+            .collect();
+
+        Ok(self.bus.subscribe(capacity, snapshot))
+    }
+
+    /// Subscribes to SerialEvents as a futures Stream.
+    /// 
+    /// Like subscribe(), the stream starts with a DeviceArrived event for every
+    /// port currently present. It ends when the manager is dropped. If the
+    /// consumer falls behind, the oldest events are dropped.
+    #[cfg(feature = "tokio")]
+    pub fn events(&self) -> Result<impl futures_core::Stream<Item = SerialEvent> + Send + Unpin> {
+        Ok(self.subscribe()?.into_stream())
+    }
+
+    /// Returns the currently available serial ports, sorted by port name.
     /// 
     /// This list is maintained by the background discovery service and 
     /// reflects the current state of connected serial devices.
-    pub fn get_ports(&self) -> Vec<String> {
-        let mut ports: Vec<String> = self.known_ports.lock().unwrap()
-            .iter().cloned().collect();
-        ports.sort();
-        ports
+    pub fn get_ports(&self) -> Result<Vec<PortInfo>> {
+        let mut ports: Vec<PortInfo> = self.known_ports.lock()
+            .map_err(|_| SerialManagerError::LockPoisoned("known ports"))?
+            .values().cloned().collect();
+        ports.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+        Ok(ports)
+    }
+
+    /// Opens a port as a session.
+    /// 
+    /// The port does not have to be one that discovery knows about. The session
+    /// reads from the port in the background until it is closed with
+    /// SessionHandle::close(), its device departs, or reading fails; in each
+    /// case SerialEvent::SessionClosed is published once the port is released.
+    /// Call SessionHandle::set_auto_reconnect() to have the session survive
+    /// its device resetting instead.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `port_name` - Operating system name of the port (e.g. "/dev/ttyACM0", "COM3")
+    /// * `config` - Line settings and timeouts
+    /// 
+    /// # Errors
+    /// 
+    /// Fails with SerialManagerError::PortBusy if the port already has an open
+    /// session or is in use by another process, and with
+    /// SerialManagerError::PermissionDenied if the user may not open it.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let session = manager.open("/dev/ttyACM0", PortConfig { baud_rate: 9600, ..Default::default() })?;
+    /// session.write(b"AT\r\n")?;
+    /// ```
+    pub fn open(&self, port_name: &str, config: PortConfig) -> Result<SessionHandle> {
+        let framer = config.framing.as_ref().map(FramingConfig::build);
+        self.sessions.open(port_name, self.known_identity(port_name)?, config, framer)
     }
 
+    /// Opens a port as a session whose data is split into frames by a custom framer.
+    /// 
+    /// Works like open(), except that `framer` is used instead of the one
+    /// described by `config.framing`, which is ignored.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let session = manager.open_with_framer("/dev/ttyACM0", PortConfig::default(), Box::new(MyProtocolFramer::new()))?;
+    /// ```
+    pub fn open_with_framer(&self, port_name: &str, config: PortConfig, framer: Box<dyn Framer>) -> Result<SessionHandle> {
+        self.sessions.open(port_name, self.known_identity(port_name)?, config, Some(framer))
+    }
+
+    /// Creates a virtual port: a pseudo terminal standing in for a serial device.
+    /// 
+    /// The port is reported as DeviceArrived before this returns and can be
+    /// opened like any other. The returned VirtualDevice is the device end;
+    /// dropping it removes the port again (see the virtual_port module).
+    /// 
+    /// # Errors
+    /// 
+    /// Fails with SerialManagerError::PortBusy if a virtual port with the same
+    /// name exists, and with SerialManagerError::Io if no pseudo terminal
+    /// could be created.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let mut device = manager.create_virtual_port("gps")?;
+    /// let session = manager.open(device.port_name(), PortConfig::default())?;
+    /// device.write_all(b"$GPGGA,...\r\n")?;
+    /// ```
+    #[cfg(unix)]
+    pub fn create_virtual_port(&self, name: &str) -> Result<VirtualDevice> {
+        VirtualDevice::create(name, &self.discovery)
+    }
+
+    /// Creates a virtual port that replays the data received in a capture.
+    /// 
+    /// The replay waits for Replay::start(), so that a session can open the
+    /// port first, and sends the records with the given timing. Dropping the
+    /// Replay removes the port (see the replay module).
+    /// 
+    /// # Errors
+    /// 
+    /// Fails like create_virtual_port().
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let capture = CaptureReader::open("field-bug.smcp")?;
+    /// let replay = manager.replay_capture("field-bug", capture, ReplayTiming::Scaled(10.0))?;
+    /// let session = manager.open(replay.port_name(), replay.header().config.clone())?;
+    /// replay.start();
+    /// ```
+    #[cfg(unix)]
+    pub fn replay_capture<R: std::io::Read + Send + 'static>(
+        &self,
+        name: &str,
+        capture: CaptureReader<R>,
+        timing: ReplayTiming
+    ) -> Result<Replay> {
+        Ok(Replay::new(self.create_virtual_port(name)?, capture, timing))
+    }
+
+    /// Returns who discovery saw behind the port, so a session opened on it
+    /// can find the device again if it moves.
+    fn known_identity(&self, port_name: &str) -> Result<Option<DeviceIdentity>> {
+        Ok(self.known_ports.lock()
+            .map_err(|_| SerialManagerError::LockPoisoned("known ports"))?
+            .get(port_name)
+            .and_then(PortInfo::identity))
+    }
+
+    /// Returns the open session with the given id, if there is one.
+    pub fn session(&self, id: SessionId) -> Result<Option<SessionHandle>> {
+        self.sessions.get(id)
+    }
+
+    /// Returns every open session, oldest first.
+    pub fn sessions(&self) -> Result<Vec<SessionHandle>> {
+        self.sessions.list()
+    }
+
+    /// Merges the data of several sessions into one time-ordered stream.
+    /// 
+    /// The timeline carries what the given sessions receive and what is
+    /// written to them from now on, tagged with session, port and direction
+    /// and ordered on a monotonic clock all sessions share (see the timeline
+    /// module). Pass an empty slice to include every session, including ones
+    /// opened later.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust ignore
+    /// let mut timeline = manager.timeline(&[])?;
+    /// for entry in timeline.recv_timeout(Duration::from_millis(100))? {
+    ///     println!("{} {:?} {} bytes", entry.port_name, entry.direction, entry.bytes.len());
+    /// }
+    /// ```
+    pub fn timeline(&self, sessions: &[SessionId]) -> Result<Timeline> {
+        let events = self.subscribe_with_capacity(TIMELINE_CAPACITY)?;
+        let sessions = (!sessions.is_empty()).then(|| sessions.iter().copied().collect::<HashSet<_>>());
+        Ok(Timeline::new(events, sessions, Arc::downgrade(&self.sessions), self.sessions.epoch()))
+    }
+
+    /// Returns the delivery counters of the open session with the given id.
+    /// 
+    /// Shorthand for looking the session up and calling SessionHandle::stats().
+    pub fn session_stats(&self, id: SessionId) -> Result<Option<SessionStats>> {
+        Ok(self.sessions.get(id)?.map(|session| session.stats()))
+    }
+
+    /// Returns a copy of the filter currently applied to discovered ports.
+    pub fn filter(&self) -> PortFilter {
+        self.discovery.filter()
+    }
+
+    /// Replaces the port filter at runtime.
+    /// 
+    /// Newly accepted ports are reported as DeviceArrived and newly rejected
+    /// ports as DeviceDeparted, so listeners stay consistent with get_ports().
+    pub fn set_filter(&self, filter: PortFilter) {
+        self.discovery.set_filter(filter);
+    }
+
+    /// Returns how often the device with the given identity has re-enumerated
+    /// recently, or None if it has been stable for the whole flap window.
+    pub fn device_stability(&self, identity: &DeviceIdentity) -> Option<DeviceStability> {
+        self.discovery.stability(identity)
+    }
+
+    /// Stops discovery until resume() is called. Known ports are kept.
+    pub fn pause(&self) {
+        self.discovery.pause();
+    }
+
+    /// Resumes discovery after pause(), starting with an immediate rescan.
+    pub fn resume(&self) {
+        self.discovery.resume();
+    }
+
+    /// Returns true while discovery is paused.
+    pub fn is_paused(&self) -> bool {
+        self.discovery.is_paused()
+    }
+
+    /// Rescans for ports right away, e.g. when the user asks for a refresh.
+    /// 
+    /// Returns immediately; any changes are published as events.
+    pub fn rescan_now(&self) {
+        self.discovery.rescan_now();
+    }
+
+    /// Returns how often the system is rescanned when polling.
+    pub fn scan_interval(&self) -> Duration {
+        self.discovery.scan_interval()
+    }
+
+    /// Changes how often the system is rescanned when polling.
+    pub fn set_scan_interval(&self, interval: Duration) {
+        self.discovery.set_scan_interval(interval);
+    }
+
+    /// Stops discovery and waits up to `timeout` for it to finish.
+    /// 
+    /// Returns true if discovery exited cleanly within the timeout. Subscribers
+    /// keep their receivers; they are closed when the manager is dropped.
+    pub fn shutdown_and_join(&self, timeout: Duration) -> bool {
+        self.discovery.shutdown_and_join(timeout)
+    }
 }
 
 impl Drop for SerialManager {
     fn drop(&mut self) {
         self.discovery.shutdown();
+        self.sessions.shutdown_all();
     }
-}
\ No newline at end of file
+}
+
+/// Builds the discovery callback that converts PortEvents to SerialEvents and publishes them.
+/// 
+/// Sessions open on a port that went away are closed as well.
+fn publisher(bus: Arc<EventBus>, sessions: Arc<SessionRegistry>) -> PortEventCallback {
+    Box::new(move |port_event| {
+        let serial_event = match port_event {
+            PortEvent::Added(port) => {
+                sessions.device_present(&port, false);
+                SerialEvent::DeviceArrived {
+                    port,
+                    timestamp: std::time::SystemTime::now(),
+                }
+            }
+            PortEvent::Removed(port) => {
+                sessions.shutdown_port(&port.port_name, CloseReason::DeviceDeparted);
+                SerialEvent::DeviceDeparted {
+                    port,
+                    timestamp: std::time::SystemTime::now(),
+                }
+            }
+            PortEvent::Moved { from, to, identity } => {
+                sessions.shutdown_port(&from.port_name, CloseReason::DeviceDeparted);
+                sessions.device_present(&to, true);
+                SerialEvent::DeviceMoved {
+                    from,
+                    to,
+                    identity,
+                    timestamp: std::time::SystemTime::now(),
+                }
+            }
+            PortEvent::Reenumerated { port, identity, stability } => {
+                // Whatever the sessions had open on the port went away in between
+                sessions.device_present(&port, true);
+                SerialEvent::DeviceReenumerated {
+                    port,
+                    identity,
+                    stability,
+                    timestamp: std::time::SystemTime::now(),
+                }
+            }
+            PortEvent::Error(error) => SerialEvent::DiscoveryError {
+                error,
+                timestamp: std::time::SystemTime::now(),
+            },
+        };
+
+        // Broadcast to subscribers (slow subscribers lag rather than block discovery)
+        bus.publish(serial_event);
+    })
+}
+
+/// Creates the event bus and the session registry that publishes on it.
+/// 
+/// Data that overflows a subscriber queue is counted against its session. The
+/// bus holds only a weak reference to the registry, since the registry already
+/// keeps the bus alive.
+fn session_bus() -> (Arc<EventBus>, Arc<SessionRegistry>) {
+    let bus = Arc::new(EventBus::new());
+    let sessions = Arc::new(SessionRegistry::new(session_publisher(bus.clone())));
+
+    let registry: Weak<SessionRegistry> = Arc::downgrade(&sessions);
+    bus.set_overflow_handler(Box::new(move |event, overflow| {
+        if let Some(sessions) = registry.upgrade() {
+            sessions.record_overflow(event, overflow);
+        }
+    }));
+    (bus, sessions)
+}
+
+/// Builds the session callback that converts SessionEvents to SerialEvents and publishes them.
+fn session_publisher(bus: Arc<EventBus>) -> SessionEventCallback {
+    Arc::new(move |session_event| {
+        let serial_event = match session_event {
+            SessionEvent::Data { session, bytes, host_timestamp, elapsed } => SerialEvent::DataReceived {
+                session,
+                bytes,
+                host_timestamp,
+                elapsed,
+            },
+            SessionEvent::Sent { session, bytes, host_timestamp, elapsed } => SerialEvent::DataSent {
+                session,
+                bytes,
+                host_timestamp,
+                elapsed,
+            },
+            SessionEvent::ControlLines { session, lines } => SerialEvent::ControlLinesChanged {
+                session,
+                lines,
+                timestamp: std::time::SystemTime::now(),
+            },
+            SessionEvent::Frame { session, frame, host_timestamp, elapsed } => SerialEvent::FrameReceived {
+                session,
+                frame,
+                host_timestamp,
+                elapsed,
+            },
+            SessionEvent::TransmitProgress { session, transmit, sent, total } => SerialEvent::TransmitProgress {
+                session,
+                transmit,
+                sent,
+                total,
+                timestamp: std::time::SystemTime::now(),
+            },
+            SessionEvent::TransmitFinished { session, transmit, outcome } => SerialEvent::TransmitFinished {
+                session,
+                transmit,
+                outcome,
+                timestamp: std::time::SystemTime::now(),
+            },
+            SessionEvent::Disconnected { session, port_name, reason } => SerialEvent::SessionDisconnected {
+                session,
+                port_name,
+                reason,
+                timestamp: std::time::SystemTime::now(),
+            },
+            SessionEvent::Reconnected { session, port_name, disconnected_at } => {
+                let timestamp = std::time::SystemTime::now();
+                bus.publish(SerialEvent::SessionReconnected { session, port_name, timestamp });
+                SerialEvent::DataGap { session, since: disconnected_at, until: timestamp }
+            }
+            SessionEvent::Closed { session, port_name, reason } => SerialEvent::SessionClosed {
+                session,
+                port_name,
+                reason,
+                timestamp: std::time::SystemTime::now(),
+            },
+        };
+        bus.publish(serial_event);
+    })
+}
@@ -0,0 +1,148 @@
+//! # Send Payloads
+//!
+//! What a user types to send is not always what should go down the wire: a
+//! command needs the line ending the device expects, a modem wants an ESC
+//! typed as `\x1b`, a bootloader wants raw bytes typed as hex. A SendMode
+//! says how to turn the typed payload into bytes.
+//!
+//! - **Text**: the payload as UTF-8, followed by a line ending
+//! - **Escaped**: like Text, with C-style escapes such as `\r`, `\x1b` and
+//!   `\033` turned into the bytes they stand for
+//! - **Hex**: pairs of hex digits, e.g. "DE AD BE EF", "de:ad:be:ef",
+//!   "0xDE 0xAD" or "DEADBEEF"
+//!
+//! The line ending is a SendLineEnding rather than the framers' LineEnding, as
+//! a payload can be sent without one.
+//!
+//! ```rust ignore
+//! let mode = SendMode::Escaped { line_ending: SendLineEnding::CrLf };
+//! session.write(&mode.encode(r"AT+CMGS=1\x1a")?)?;
+//! ```
+
+use std::io;
+use serde::{Deserialize, Serialize};
+use crate::error::{Result, SerialManagerError};
+
+/// Bytes sent after a Text or Escaped payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SendLineEnding {
+    /// Nothing
+    None,
+    /// "\n"
+    Lf,
+    /// "\r"
+    Cr,
+    /// "\r\n"
+    CrLf,
+}
+
+/// How a typed payload is turned into bytes to send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SendMode {
+    /// The payload as it is, followed by `line_ending`
+    Text { line_ending: SendLineEnding },
+    /// The payload with C-style escapes replaced, followed by `line_ending`
+    Escaped { line_ending: SendLineEnding },
+    /// Hex digit pairs, optionally separated by spaces, commas, colons or dashes
+    Hex,
+}
+
+impl Default for SendMode {
+    /// Text followed by "\r\n"
+    fn default() -> Self {
+        SendMode::Text { line_ending: SendLineEnding::CrLf }
+    }
+}
+
+impl SendMode {
+    /// Returns the bytes to send for `payload`.
+    ///
+    /// Fails with an InvalidInput error naming the problem if the payload has
+    /// an unknown or incomplete escape, or is not valid hex.
+    pub fn encode(&self, payload: &str) -> Result<Vec<u8>> {
+        let (mut bytes, line_ending) = match *self {
+            SendMode::Text { line_ending } => (payload.as_bytes().to_vec(), line_ending),
+            SendMode::Escaped { line_ending } => (unescape(payload)?, line_ending),
+            SendMode::Hex => return parse_hex(payload),
+        };
+        bytes.extend_from_slice(match line_ending {
+            SendLineEnding::None => b"",
+            SendLineEnding::Lf => b"\n",
+            SendLineEnding::Cr => b"\r",
+            SendLineEnding::CrLf => b"\r\n",
+        });
+        Ok(bytes)
+    }
+}
+
+fn invalid(message: String) -> SerialManagerError {
+    io::Error::new(io::ErrorKind::InvalidInput, message).into()
+}
+
+/// Replaces C-style escapes with the bytes they stand for
+fn unescape(payload: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(payload.len());
+    let mut chars = payload.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if c != '\\' {
+            let mut encoded = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut encoded).as_bytes());
+            continue;
+        }
+        let Some((at, escape)) = chars.next() else {
+            return Err(invalid("payload ends in the middle of an escape".to_string()));
+        };
+        let byte = match escape {
+            '\\' => b'\\',
+            '"' => b'"',
+            '\'' => b'\'',
+            '?' => b'?',
+            'a' => 0x07,
+            'b' => 0x08,
+            'e' => 0x1b,
+            'f' => 0x0c,
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            'v' => 0x0b,
+            'x' => {
+                let digits: String = (0..2).filter_map(|_| chars.next_if(|(_, c)| c.is_ascii_hexdigit())).map(|(_, c)| c).collect();
+                if digits.len() != 2 {
+                    return Err(invalid(format!("\\x at {} needs two hex digits", at - 1)));
+                }
+                u8::from_str_radix(&digits, 16).unwrap()
+            }
+            '0'..='7' => {
+                let mut value = escape.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.next_if(|(_, c)| c.is_digit(8)) {
+                        Some((_, digit)) => value = value * 8 + digit.to_digit(8).unwrap(),
+                        None => break,
+                    }
+                }
+                u8::try_from(value).map_err(|_| invalid(format!("octal escape at {} is more than a byte", at - 1)))?
+            }
+            other => return Err(invalid(format!("unknown escape \\{} at {}", other, at - 1))),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+/// Parses pairs of hex digits, each group optionally prefixed with "0x"
+fn parse_hex(payload: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for group in payload.split(|c: char| c.is_whitespace() || matches!(c, ',' | ':' | '-')).filter(|group| !group.is_empty()) {
+        let digits = group.strip_prefix("0x").or_else(|| group.strip_prefix("0X")).unwrap_or(group);
+        if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid(format!("{:?} is not a whole number of hex bytes", group)));
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            // Checked to be ASCII hex digits above
+            bytes.push(u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap());
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,100 @@
+//! # Serial Port Metadata
+//!
+//! Structured information about a discovered serial port. This carries the USB
+//! descriptor strings and IDs reported by the operating system so that applications
+//! can present "STM32 Virtual ComPort" rather than "/dev/ttyACM3".
+
+use serde::{Deserialize, Serialize};
+use crate::identity::DeviceIdentity;
+
+/// The bus a serial port is attached through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PortType {
+    /// USB serial adapter or CDC-ACM device
+    Usb,
+    /// PCI/PCIe serial card or on-board UART
+    Pci,
+    /// Bluetooth serial (RFCOMM) link
+    Bluetooth,
+    /// Pseudo terminal standing in for a device, see SerialManager::create_virtual_port()
+    Virtual,
+    /// Anything the operating system could not classify
+    Unknown,
+}
+
+/// Metadata describing a single serial port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortInfo {
+    /// Operating system name of the port (e.g. "/dev/ttyACM0", "COM3")
+    pub port_name: String,
+    /// Bus the port is attached through
+    pub port_type: PortType,
+    /// USB vendor ID
+    pub vid: Option<u16>,
+    /// USB product ID
+    pub pid: Option<u16>,
+    /// USB serial number string
+    pub serial_number: Option<String>,
+    /// USB manufacturer string
+    pub manufacturer: Option<String>,
+    /// USB product string
+    pub product: Option<String>,
+    /// Persistent /dev/serial/by-id link pointing at this port, if any
+    pub by_id: Option<String>,
+    /// Physical connection path (USB bus and port chain), if known
+    pub location: Option<String>,
+}
+
+impl PortInfo {
+    /// Creates a PortInfo for a port about which nothing but the name is known.
+    pub fn new(port_name: impl Into<String>) -> Self {
+        PortInfo {
+            port_name: port_name.into(),
+            port_type: PortType::Unknown,
+            vid: None,
+            pid: None,
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+            by_id: None,
+            location: None,
+        }
+    }
+
+    /// Returns the stable identity of the device behind this port, if it has one.
+    pub fn identity(&self) -> Option<DeviceIdentity> {
+        DeviceIdentity::of(self)
+    }
+
+    /// Returns a human readable name for the device behind this port.
+    ///
+    /// Prefers the USB product string, then the manufacturer, and falls back
+    /// to the port name when neither is available.
+    pub fn display_name(&self) -> String {
+        match (&self.manufacturer, &self.product) {
+            (_, Some(product)) => product.clone(),
+            (Some(manufacturer), None) => manufacturer.clone(),
+            (None, None) => self.port_name.clone(),
+        }
+    }
+}
+
+impl From<serialport::SerialPortInfo> for PortInfo {
+    fn from(info: serialport::SerialPortInfo) -> Self {
+        let mut port = PortInfo::new(info.port_name);
+        match info.port_type {
+            serialport::SerialPortType::UsbPort(usb) => {
+                port.port_type = PortType::Usb;
+                port.vid = Some(usb.vid);
+                port.pid = Some(usb.pid);
+                port.serial_number = usb.serial_number;
+                port.manufacturer = usb.manufacturer;
+                port.product = usb.product;
+            }
+            serialport::SerialPortType::PciPort => port.port_type = PortType::Pci,
+            serialport::SerialPortType::BluetoothPort => port.port_type = PortType::Bluetooth,
+            serialport::SerialPortType::Unknown => port.port_type = PortType::Unknown,
+        }
+        port
+    }
+}
@@ -0,0 +1,221 @@
+//! # Replay
+//!
+//! A Replay plays the received side of a capture back through a virtual port,
+//! so that the application and its parsers see the device's data exactly as it
+//! arrived when the capture was taken: same chunks, same gaps between them, or
+//! scaled or squeezed together as ReplayTiming asks.
+//!
+//! What was sent to the device is not replayed, since the application under
+//! test does its own sending. The replay reads and discards it so the port
+//! never fills up.
+//!
+//! Opening a port discards whatever is waiting in it, so a replay only starts
+//! when asked to, once the application has opened the port. The virtual port
+//! stays until the Replay is dropped, so the last of the data can be read.
+//!
+//! ```rust ignore
+//! let capture = CaptureReader::open("field-bug.smcp")?;
+//! let config = capture.header().config.clone();
+//! let replay = manager.replay_capture("field-bug", capture, ReplayTiming::Original)?;
+//! let session = manager.open(replay.port_name(), config)?;
+//! replay.start();
+//! println!("replayed {} bytes", replay.wait()?);
+//! ```
+
+use std::{
+    io::{self, Read, Write},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use crate::capture::{CaptureHeader, CaptureReader, ReplayTiming};
+use crate::error::Result;
+use crate::port_info::PortInfo;
+use crate::timeline::Direction;
+use crate::virtual_port::VirtualDevice;
+
+/// Longest the replay thread waits for data from the application before
+/// checking whether it was stopped
+const DRAIN_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long writing a record may block while the application does not read
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct ReplayState {
+    started: bool,
+    stopping: bool,
+    /// Set once every record was replayed or replaying failed
+    outcome: Option<Result<u64>>,
+}
+
+#[derive(Default)]
+struct ReplayShared {
+    state: Mutex<ReplayState>,
+    changed: Condvar,
+}
+
+impl ReplayShared {
+    fn lock_state(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn stopping(&self) -> bool {
+        self.lock_state().stopping
+    }
+}
+
+/// A capture being replayed through a virtual port
+///
+/// Dropping it stops the replay and removes the port.
+pub struct Replay {
+    port: PortInfo,
+    header: CaptureHeader,
+    shared: Arc<ReplayShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Replay {
+    /// Hands the device and the capture to a thread that replays it once started.
+    pub(crate) fn new<R: Read + Send + 'static>(
+        device: VirtualDevice,
+        capture: CaptureReader<R>,
+        timing: ReplayTiming
+    ) -> Self {
+        let port = device.port().clone();
+        let header = capture.header().clone();
+        let shared = Arc::new(ReplayShared::default());
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || run_replay(device, capture, timing, thread_shared));
+        Replay { port, header, shared, thread: Some(thread) }
+    }
+
+    /// Returns the operating system name of the virtual port, for opening a session on it.
+    pub fn port_name(&self) -> &str {
+        &self.port.port_name
+    }
+
+    /// Returns the virtual port as discovery reports it.
+    pub fn port(&self) -> &PortInfo {
+        &self.port
+    }
+
+    /// Returns the header of the capture being replayed.
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Starts replaying. Timing is counted from here, so the first record is
+    /// sent as long after this as it was received after the capture started.
+    ///
+    /// Does nothing if the replay already started.
+    pub fn start(&self) {
+        self.shared.lock_state().started = true;
+        self.shared.changed.notify_all();
+    }
+
+    /// Returns whether every record was replayed, or replaying failed.
+    pub fn is_finished(&self) -> bool {
+        self.shared.lock_state().outcome.is_some()
+    }
+
+    /// Starts the replay if it has not started yet and waits until it finishes.
+    ///
+    /// Returns the number of bytes replayed, or the error that stopped the
+    /// replay: reading the capture failed, or writing to the port timed out
+    /// because nothing read from it.
+    pub fn wait(&self) -> Result<u64> {
+        self.start();
+        let state = self.shared.lock_state();
+        let state = self.shared.changed
+            .wait_while(state, |state| state.outcome.is_none())
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.outcome.clone().unwrap_or(Ok(0))
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        self.shared.lock_state().stopping = true;
+        self.shared.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("Replay thread for {} panicked", self.port.port_name);
+            }
+        }
+    }
+}
+
+/// Waits to be started, replays the received records of the capture and then
+/// keeps the device until the replay is dropped.
+fn run_replay<R: Read>(
+    mut device: VirtualDevice,
+    mut capture: CaptureReader<R>,
+    timing: ReplayTiming,
+    shared: Arc<ReplayShared>
+) {
+    {
+        let state = shared.lock_state();
+        let state = shared.changed
+            .wait_while(state, |state| !state.started && !state.stopping)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.stopping {
+            return;
+        }
+    }
+    log::info!("Replaying {} on {} ({:?})", capture.header().port_name, device.port_name(), timing);
+
+    let started = Instant::now();
+    let outcome: Result<u64> = (|| {
+        let mut replayed = 0;
+        while let Some(record) = capture.next_record()? {
+            if record.direction != Direction::Rx {
+                continue;
+            }
+            if !drain(&mut device, &shared, Some(started + timing.due(record.elapsed))) {
+                break;
+            }
+            device.set_timeout(WRITE_TIMEOUT)?;
+            device.write_all(&record.bytes)?;
+            replayed += record.bytes.len() as u64;
+        }
+        Ok(replayed)
+    })();
+
+    match &outcome {
+        Ok(replayed) => log::info!("Replayed {} bytes on {}", replayed, device.port_name()),
+        Err(e) => log::warn!("Replay on {} failed: {}", device.port_name(), e),
+    }
+    shared.lock_state().outcome = Some(outcome);
+    shared.changed.notify_all();
+
+    drain(&mut device, &shared, None);
+}
+
+/// Reads and discards what the application writes to the port until
+/// `deadline`, or forever if there is none. Returns false if the replay
+/// was stopped first.
+fn drain(device: &mut VirtualDevice, shared: &ReplayShared, deadline: Option<Instant>) -> bool {
+    let mut discarded = [0; 1024];
+    loop {
+        if shared.stopping() {
+            return false;
+        }
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(wait) if !wait.is_zero() => wait.min(DRAIN_INTERVAL),
+                _ => return true,
+            },
+            None => DRAIN_INTERVAL,
+        };
+        if device.set_timeout(wait).is_err() {
+            thread::sleep(wait);
+            continue;
+        }
+        match device.read(&mut discarded) {
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+            Err(_) => thread::sleep(wait),
+        }
+    }
+}
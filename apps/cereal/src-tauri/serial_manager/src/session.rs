@@ -0,0 +1,1214 @@
+//! # Serial Sessions
+//!
+//! A session is a serial port opened through SerialManager::open(). Each session
+//! owns a background thread that reads from the port and publishes every chunk
+//! as a SerialEvent::DataReceived the moment it arrives, and a writer that any
+//! thread can use, which publishes what it wrote as a SerialEvent::DataSent.
+//! Both are stamped on a monotonic clock shared by all sessions of a manager,
+//! so data can be ordered across sessions (see the timeline module). A session
+//! with a framer publishes a SerialEvent::FrameReceived for each frame instead
+//! (see the framing module).
+//!
+//! ## Control Lines
+//!
+//! SessionHandle sets DTR and RTS, sends breaks and runs reset sequences (see
+//! the control module). The lines driven by the device can be read at any
+//! time, or watched: the reader thread then polls them between reads and
+//! reports every change as SerialEvent::ControlLinesChanged.
+//!
+//! ## Transmitting
+//!
+//! SessionHandle::transmit() and transmit_file() queue data to be sent at a
+//! pace the device can follow: with delays between characters and lines, or
+//! line by line, waiting for the device's prompt (see the transmit module).
+//!
+//! ## Capture
+//!
+//! SessionHandle::start_capture() records every chunk the session reads and
+//! writes, with when it happened, to a capture file that can be read back and
+//! replayed later (see the capture module). Capturing happens on the session's
+//! own threads, so unlike a subscriber it cannot fall behind and lose data.
+//!
+//! ## Accounting
+//!
+//! Every session counts the chunks and bytes it received. Subscribers that fall
+//! behind have chunks coalesced or, failing that, dropped from their queues (see
+//! the bus module); those are counted against the session too, so a capture can
+//! always tell whether it is complete.
+//!
+//! ## Lifecycle
+//!
+//! A session stays open until SessionHandle::close() is called, the device it
+//! is open on departs, or reading from the port fails. Either way the port is
+//! released and a SessionEvent::Closed is reported with the reason, exactly once.
+//!
+//! A session with auto-reconnect turned on survives losing its device: it is
+//! reported as disconnected, and when discovery sees the same device again it
+//! reopens it with the same settings and is reported as reconnected.
+//! Dropping a SessionHandle does not close the session; handles are cheap
+//! clones of each other and the session can be looked up again by its id.
+//!
+//! ```rust ignore
+//! let events = manager.subscribe()?;
+//! let session = manager.open("/dev/ttyACM0", PortConfig { baud_rate: 9600, ..Default::default() })?;
+//! session.write(b"version\r\n")?;
+//!
+//! while let Ok(event) = events.recv() {
+//!     if let SerialEvent::DataReceived { session: id, bytes, .. } = event {
+//!         if id == session.id() {
+//!             print!("{}", String::from_utf8_lossy(&bytes));
+//!         }
+//!     }
+//! }
+//! session.close();
+//! println!("{:?}", session.stats());
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use crate::bus::Overflow;
+use crate::capture::{CaptureHeader, CaptureWriter};
+use crate::control::{ControlLines, ResetSequence, ResetStep};
+use crate::error::{Result, SerialManagerError};
+use crate::framing::{Deframer, Frame, Framer, FramingConfig};
+use crate::identity::DeviceIdentity;
+use crate::manager::SerialEvent;
+use crate::port_info::PortInfo;
+use crate::timeline::Direction;
+use crate::transmit::{TransmitId, TransmitOptions, TransmitOutcome, TransmitQueue};
+
+/// Largest number of bytes taken from the port in a single read
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// How often a reconnecting session tries to reopen a port that reappeared
+const REOPEN_ATTEMPTS: u32 = 10;
+
+/// How long a reconnecting session waits between attempts to reopen a port
+const REOPEN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a session watching its control lines polls them
+const CONTROL_LINE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Identifies a session for as long as its manager lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionId(u64);
+
+impl SessionId {
+    /// Returns the numeric value of the id.
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for SessionId {
+    fn from(value: u64) -> Self {
+        SessionId(value)
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "session {}", self.0)
+    }
+}
+
+/// Number of data bits per character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DataBits {
+    #[serde(rename = "5")]
+    Five,
+    #[serde(rename = "6")]
+    Six,
+    #[serde(rename = "7")]
+    Seven,
+    #[serde(rename = "8")]
+    Eight,
+}
+
+/// Parity checking mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+/// Number of stop bits per character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StopBits {
+    #[serde(rename = "1")]
+    One,
+    #[serde(rename = "2")]
+    Two,
+}
+
+/// Flow control mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowControl {
+    /// No flow control
+    None,
+    /// XON/XOFF characters in the data stream
+    Software,
+    /// RTS/CTS lines
+    Hardware,
+}
+
+/// Line settings and timeouts used to open a session
+///
+/// Fields missing when deserializing take their default values, so a frontend
+/// only needs to send the settings the user changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortConfig {
+    /// Line speed in bits per second
+    pub baud_rate: u32,
+    /// Number of data bits per character
+    pub data_bits: DataBits,
+    /// Parity checking mode
+    pub parity: Parity,
+    /// Number of stop bits per character
+    pub stop_bits: StopBits,
+    /// Flow control mode
+    pub flow_control: FlowControl,
+    /// How long a single blocking read on the port waits for data. This bounds
+    /// how quickly the reader thread notices that the session was closed.
+    pub read_timeout: Duration,
+    /// How long a write may block, e.g. while flow control holds it back,
+    /// before it fails. On Windows the read timeout applies to writes as well.
+    pub write_timeout: Duration,
+    /// Framer to split received data into frames with, or None to publish
+    /// data in the chunks it was read in
+    pub framing: Option<FramingConfig>,
+}
+
+impl Default for PortConfig {
+    /// 115200 baud, 8N1, no flow control, no framing
+    fn default() -> Self {
+        PortConfig {
+            baud_rate: 115_200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            read_timeout: Duration::from_millis(50),
+            write_timeout: Duration::from_secs(1),
+            framing: None,
+        }
+    }
+}
+
+impl From<DataBits> for serialport::DataBits {
+    fn from(bits: DataBits) -> Self {
+        match bits {
+            DataBits::Five => serialport::DataBits::Five,
+            DataBits::Six => serialport::DataBits::Six,
+            DataBits::Seven => serialport::DataBits::Seven,
+            DataBits::Eight => serialport::DataBits::Eight,
+        }
+    }
+}
+
+impl From<Parity> for serialport::Parity {
+    fn from(parity: Parity) -> Self {
+        match parity {
+            Parity::None => serialport::Parity::None,
+            Parity::Odd => serialport::Parity::Odd,
+            Parity::Even => serialport::Parity::Even,
+        }
+    }
+}
+
+impl From<StopBits> for serialport::StopBits {
+    fn from(bits: StopBits) -> Self {
+        match bits {
+            StopBits::One => serialport::StopBits::One,
+            StopBits::Two => serialport::StopBits::Two,
+        }
+    }
+}
+
+impl From<FlowControl> for serialport::FlowControl {
+    fn from(flow_control: FlowControl) -> Self {
+        match flow_control {
+            FlowControl::None => serialport::FlowControl::None,
+            FlowControl::Software => serialport::FlowControl::Software,
+            FlowControl::Hardware => serialport::FlowControl::Hardware,
+        }
+    }
+}
+
+/// Why a session ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The application closed the session, or the manager was dropped
+    Closed,
+    /// Discovery reported that the device behind the port went away
+    DeviceDeparted,
+    /// Reading from the port failed
+    Error(SerialManagerError),
+}
+
+/// Delivery counters of a session
+///
+/// Coalesced and dropped chunks and frames are counted once for every subscriber whose
+/// queue overflowed, so with a single subscriber they are exact.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// Reads from the port that returned data
+    pub chunks_received: u64,
+    /// Bytes read from the port
+    pub bytes_received: u64,
+    /// Chunks appended to an earlier chunk because a subscriber fell behind.
+    /// No bytes were lost, but they carry the earlier chunk's timestamp.
+    pub chunks_coalesced: u64,
+    /// Chunks a subscriber fell too far behind to receive
+    pub chunks_dropped: u64,
+    /// Bytes in the dropped chunks
+    pub bytes_dropped: u64,
+    /// Frames the session's framer assembled
+    pub frames_received: u64,
+    /// Frames a subscriber fell too far behind to receive
+    pub frames_dropped: u64,
+}
+
+/// Events reported by sessions to their manager
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SessionEvent {
+    /// Bytes were read from the port
+    Data {
+        session: SessionId,
+        bytes: Vec<u8>,
+        host_timestamp: SystemTime,
+        elapsed: Duration,
+    },
+    /// Bytes were written to the port
+    Sent {
+        session: SessionId,
+        bytes: Vec<u8>,
+        host_timestamp: SystemTime,
+        elapsed: Duration,
+    },
+    /// The lines driven by the device changed, or started being watched
+    ControlLines {
+        session: SessionId,
+        lines: ControlLines,
+    },
+    /// The session's framer completed a frame
+    Frame {
+        session: SessionId,
+        frame: Frame,
+        host_timestamp: SystemTime,
+        elapsed: Duration,
+    },
+    /// A transmission sent another line or block
+    TransmitProgress {
+        session: SessionId,
+        transmit: TransmitId,
+        sent: u64,
+        total: u64,
+    },
+    /// A transmission ended
+    TransmitFinished {
+        session: SessionId,
+        transmit: TransmitId,
+        outcome: TransmitOutcome,
+    },
+    /// The session lost its device and waits for it to come back
+    Disconnected {
+        session: SessionId,
+        port_name: String,
+        reason: CloseReason,
+    },
+    /// The session reopened its device after being disconnected
+    Reconnected {
+        session: SessionId,
+        port_name: String,
+        disconnected_at: SystemTime,
+    },
+    /// The session ended and its port was released
+    Closed {
+        session: SessionId,
+        port_name: String,
+        reason: CloseReason,
+    },
+}
+
+/// Callback through which sessions report SessionEvents
+pub(crate) type SessionEventCallback = Arc<dyn Fn(SessionEvent) + Send + Sync>;
+
+/// When data was read or written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Stamp {
+    /// Wall clock time
+    pub(crate) host_timestamp: SystemTime,
+    /// Monotonic time since the registry's epoch, shared by all its sessions
+    pub(crate) elapsed: Duration,
+}
+
+impl Stamp {
+    pub(crate) fn now(epoch: Instant) -> Self {
+        Stamp { host_timestamp: SystemTime::now(), elapsed: epoch.elapsed() }
+    }
+}
+
+struct SessionState {
+    /// Operating system name of the port, which changes if the device
+    /// reconnects under a different name
+    port_name: String,
+    /// Stable identity of the device behind the port, if known
+    identity: Option<DeviceIdentity>,
+    /// Whether the session waits for its device to come back after losing it
+    auto_reconnect: bool,
+    /// Why the session is closing or disconnecting, set by shutdown requests
+    closing: Option<CloseReason>,
+    /// Port the device reappeared on, for a disconnected session to reopen
+    reconnect_to: Option<String>,
+    /// When the session lost its device, while it is disconnected
+    disconnected_at: Option<SystemTime>,
+    /// Why the session closed, set once the reader thread has released the port
+    closed: Option<CloseReason>,
+}
+
+impl SessionState {
+    /// Whether losing the device disconnects the session rather than closing it
+    fn reconnects(&self) -> bool {
+        self.auto_reconnect && self.identity.is_some()
+    }
+
+    fn close_requested(&self) -> bool {
+        self.closing == Some(CloseReason::Closed)
+    }
+
+    /// Whether a disconnected session should give up waiting for its device
+    fn stop_waiting(&self) -> bool {
+        self.close_requested() || !self.auto_reconnect
+    }
+}
+
+/// A capture in progress, see SessionHandle::start_capture()
+struct SessionCapture {
+    writer: CaptureWriter<BufWriter<File>>,
+    /// Time on the session's clock the capture started at
+    started: Duration,
+}
+
+struct Session {
+    id: SessionId,
+    config: PortConfig,
+    /// Start of the monotonic clock the session's data is stamped with
+    epoch: Instant,
+    callback: SessionEventCallback,
+    state: Mutex<SessionState>,
+    /// Signalled when the session closes, and when a disconnected session is
+    /// asked to reconnect or close
+    changed: Condvar,
+    stats: Mutex<SessionStats>,
+    /// Whether the reader thread polls the control lines
+    watch_lines: AtomicBool,
+    /// Writing half of the port, taken while the port is not open
+    writer: Mutex<Option<Box<dyn SerialPort>>>,
+    reader: Mutex<Option<JoinHandle<()>>>,
+    capture: Mutex<Option<SessionCapture>>,
+    transmit: Arc<TransmitQueue>,
+}
+
+impl Session {
+    fn lock_state(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Asks the reader thread to stop. An explicit close overrides any other
+    /// reason; otherwise the first reason given wins.
+    fn request_close(&self, reason: CloseReason) {
+        let mut state = self.lock_state();
+        if state.closing.is_none() || reason == CloseReason::Closed {
+            state.closing = Some(reason);
+        }
+        self.changed.notify_all();
+    }
+
+    fn close_requested(&self) -> Option<CloseReason> {
+        self.lock_state().closing.clone()
+    }
+
+    fn port_name(&self) -> String {
+        self.lock_state().port_name.clone()
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut SessionStats)) {
+        update(&mut self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    }
+
+    fn stamp(&self) -> Stamp {
+        Stamp::now(self.epoch)
+    }
+
+    fn report(&self, event: SessionEvent) {
+        (self.callback)(event);
+    }
+
+    /// Adds data read or written at the given time to the capture, if one is
+    /// in progress. A capture that cannot be written to is abandoned.
+    fn record(&self, direction: Direction, at: Stamp, bytes: &[u8]) {
+        let mut capture = self.capture.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(active) = capture.as_mut() else {
+            return;
+        };
+        if let Err(e) = active.writer.write_record(direction, at.elapsed.saturating_sub(active.started), bytes) {
+            log::warn!("Stopped capturing {}: {}", self.id, e);
+            *capture = None;
+        }
+    }
+
+    /// Ends the capture in progress, if any, and flushes it to its file.
+    fn finish_capture(&self) -> Result<()> {
+        let capture = self.capture.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+        match capture {
+            Some(capture) => capture.writer.finish().map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Opens a port with the given settings, returning its reading and writing halves.
+fn open_port(port_name: &str, config: &PortConfig) -> Result<(Box<dyn SerialPort>, Box<dyn SerialPort>)> {
+    let reader = serialport::new(port_name, config.baud_rate)
+        .data_bits(config.data_bits.into())
+        .parity(config.parity.into())
+        .stop_bits(config.stop_bits.into())
+        .flow_control(config.flow_control.into())
+        .timeout(config.read_timeout)
+        .open()
+        .map_err(|e| SerialManagerError::from_serialport(port_name, e))?;
+    let mut writer = reader.try_clone()
+        .map_err(|e| SerialManagerError::from_serialport(port_name, e))?;
+    writer.set_timeout(config.write_timeout)
+        .map_err(|e| SerialManagerError::from_serialport(port_name, e))?;
+    Ok((reader, writer))
+}
+
+/// A handle to an open session
+///
+/// Handles are cheap to clone and can be used from any thread. All clones
+/// refer to the same session.
+#[derive(Clone)]
+pub struct SessionHandle {
+    session: Arc<Session>,
+}
+
+impl SessionHandle {
+    /// Opens a port and starts its reader thread.
+    fn open(
+        id: SessionId,
+        port_name: &str,
+        identity: Option<DeviceIdentity>,
+        config: PortConfig,
+        framer: Option<Box<dyn Framer>>,
+        epoch: Instant,
+        callback: SessionEventCallback
+    ) -> Result<Self> {
+        let (reader, writer) = open_port(port_name, &config)?;
+        let deframer = framer.map(Deframer::new);
+
+        let session = Arc::new(Session {
+            id,
+            config,
+            epoch,
+            transmit: Arc::new(TransmitQueue::new(id, callback.clone())),
+            callback,
+            state: Mutex::new(SessionState {
+                port_name: port_name.to_string(),
+                identity,
+                auto_reconnect: false,
+                closing: None,
+                reconnect_to: None,
+                disconnected_at: None,
+                closed: None,
+            }),
+            changed: Condvar::new(),
+            stats: Mutex::new(SessionStats::default()),
+            watch_lines: AtomicBool::new(false),
+            writer: Mutex::new(Some(writer)),
+            reader: Mutex::new(None),
+            capture: Mutex::new(None),
+        });
+
+        let thread_session = session.clone();
+        let handle = thread::spawn(move || run_session(thread_session, reader, deframer));
+        *session.reader.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(handle);
+
+        log::info!("Opened {} on {}", id, port_name);
+        Ok(SessionHandle { session })
+    }
+
+    /// Returns the id of the session.
+    pub fn id(&self) -> SessionId {
+        self.session.id
+    }
+
+    /// Returns the operating system name of the port the session is open on.
+    ///
+    /// This changes if the session reconnects to its device under a new name.
+    pub fn port_name(&self) -> String {
+        self.session.port_name()
+    }
+
+    /// Returns the stable identity of the device, if it was known when the
+    /// session was opened or has been learned from discovery since.
+    pub fn identity(&self) -> Option<DeviceIdentity> {
+        self.session.lock_state().identity.clone()
+    }
+
+    /// Returns the settings the port was opened with.
+    pub fn config(&self) -> &PortConfig {
+        &self.session.config
+    }
+
+    /// Returns true until the session has closed and released its port.
+    ///
+    /// A session that is waiting for its device to come back is still open.
+    pub fn is_open(&self) -> bool {
+        self.session.lock_state().closed.is_none()
+    }
+
+    /// Returns true while the port is open and being read.
+    pub fn is_connected(&self) -> bool {
+        let state = self.session.lock_state();
+        state.closed.is_none() && state.disconnected_at.is_none()
+    }
+
+    /// Returns why the session closed, or None while it is still open.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.session.lock_state().closed.clone()
+    }
+
+    /// Returns whether the session reconnects when its device comes back.
+    pub fn auto_reconnect(&self) -> bool {
+        self.session.lock_state().auto_reconnect
+    }
+
+    /// Chooses whether the session survives its device going away.
+    ///
+    /// With auto-reconnect on, losing the device disconnects the session
+    /// instead of closing it. When discovery sees a device with the same
+    /// identity again, possibly under a new port name, the session reopens it
+    /// with the same PortConfig. Sessions on devices without a stable identity
+    /// cannot be recognised again, so they close as usual.
+    pub fn set_auto_reconnect(&self, enabled: bool) {
+        self.session.lock_state().auto_reconnect = enabled;
+        // A disconnected session has nothing to wait for any more
+        self.session.changed.notify_all();
+    }
+
+    /// Returns the session's delivery counters so far.
+    pub fn stats(&self) -> SessionStats {
+        *self.session.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Writes the whole buffer to the port and waits until it has been handed to the driver.
+    ///
+    /// Safe to call from several threads at once; each call's data is written
+    /// without being interleaved with another's. Fails with
+    /// SerialManagerError::SessionDisconnected while the session waits for its
+    /// device to come back.
+    ///
+    /// Written data is published as SerialEvent::DataSent, stamped when the
+    /// write completed.
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        let written = self.with_port(|port| {
+            port.write_all(data)
+                .and_then(|_| port.flush())
+                .map_err(serialport::Error::from)?;
+            Ok(self.session.stamp())
+        })?;
+        self.session.record(Direction::Tx, written, data);
+        self.session.report(SessionEvent::Sent {
+            session: self.session.id,
+            bytes: data.to_vec(),
+            host_timestamp: written.host_timestamp,
+            elapsed: written.elapsed,
+        });
+        Ok(())
+    }
+
+    /// Queues data to be sent at the pace `options` sets, after any
+    /// transmissions queued before. Returns at once; progress and the outcome
+    /// are published as SerialEvent::TransmitProgress and TransmitFinished.
+    ///
+    /// # Errors
+    ///
+    /// Fails with SerialManagerError::Io if the prompt is not a valid regular
+    /// expression, and with SessionClosed once the session is closed.
+    pub fn transmit(&self, data: &[u8], options: TransmitOptions) -> Result<TransmitId> {
+        let handle = self.clone();
+        self.session.transmit.push(data, options, Box::new(move |bytes| handle.write(bytes)))?
+            .ok_or_else(|| SerialManagerError::SessionClosed { port: self.session.port_name() })
+    }
+
+    /// Queues the contents of a file to be sent, like transmit().
+    pub fn transmit_file(&self, path: impl AsRef<Path>, options: TransmitOptions) -> Result<TransmitId> {
+        self.transmit(&std::fs::read(path)?, options)
+    }
+
+    /// Cancels a transmission, whether it is queued or being sent. Returns
+    /// false if it already finished.
+    pub fn cancel_transmit(&self, transmit: TransmitId) -> bool {
+        self.session.transmit.cancel(transmit)
+    }
+
+    /// Starts recording everything the session reads and writes to a capture
+    /// file at `path`, replacing the file if it exists. A capture already in
+    /// progress is finished first.
+    ///
+    /// The capture carries on while the session waits for its device to come
+    /// back, and ends with stop_capture() or when the session closes.
+    pub fn start_capture(&self, path: impl AsRef<Path>) -> Result<()> {
+        let (port_name, closed) = {
+            let state = self.session.lock_state();
+            (state.port_name.clone(), state.closed.is_some())
+        };
+        if closed {
+            return Err(SerialManagerError::SessionClosed { port: port_name });
+        }
+        self.stop_capture()?;
+
+        let started = self.session.stamp();
+        let header = CaptureHeader {
+            port_name,
+            config: self.session.config.clone(),
+            started_at: started.host_timestamp,
+        };
+        let writer = CaptureWriter::new(BufWriter::new(File::create(path.as_ref())?), &header)?;
+        *self.session.capture.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(SessionCapture {
+            writer,
+            started: started.elapsed,
+        });
+        log::info!("Capturing {} to {}", self.session.id, path.as_ref().display());
+        Ok(())
+    }
+
+    /// Ends the capture in progress and flushes it to its file.
+    ///
+    /// Does nothing if the session is not capturing.
+    pub fn stop_capture(&self) -> Result<()> {
+        self.session.finish_capture()
+    }
+
+    /// Returns whether a capture is in progress.
+    pub fn is_capturing(&self) -> bool {
+        self.session.capture.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).is_some()
+    }
+
+    /// Asserts (true) or releases (false) Data Terminal Ready.
+    pub fn set_dtr(&self, asserted: bool) -> Result<()> {
+        self.with_port(|port| port.write_data_terminal_ready(asserted))
+    }
+
+    /// Asserts (true) or releases (false) Request To Send.
+    pub fn set_rts(&self, asserted: bool) -> Result<()> {
+        self.with_port(|port| port.write_request_to_send(asserted))
+    }
+
+    /// Holds the line in the break condition for `duration`. Writes wait until it is over.
+    pub fn send_break(&self, duration: Duration) -> Result<()> {
+        self.with_port(|port| send_break(port, duration))
+    }
+
+    /// Reads the control lines driven by the device.
+    ///
+    /// Not every port has them; pseudo terminals, for one, fail with SerialManagerError::Io.
+    pub fn control_lines(&self) -> Result<ControlLines> {
+        self.with_port(read_control_lines)
+    }
+
+    /// Chooses whether the session watches the control lines driven by the device.
+    ///
+    /// While watching, the reader thread polls the lines between reads, at
+    /// most every 10ms, and publishes SerialEvent::ControlLinesChanged with
+    /// their state when it starts watching and whenever they change. If the
+    /// port cannot report its lines, watching stops again and a warning is logged.
+    pub fn watch_control_lines(&self, enabled: bool) {
+        self.session.watch_lines.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether the session is watching its control lines.
+    pub fn is_watching_control_lines(&self) -> bool {
+        self.session.watch_lines.load(Ordering::Relaxed)
+    }
+
+    /// Runs the steps of a reset sequence, e.g. one of ResetSequence::builtin().
+    ///
+    /// Blocks until the sequence has finished. Writes wait until then, so no
+    /// data reaches a board that is still resetting. Stops at the first step
+    /// that fails.
+    pub fn run_reset_sequence(&self, sequence: &ResetSequence) -> Result<()> {
+        log::info!("Running reset sequence {} on {}", sequence.name, self.session.id);
+        self.with_port(|port| {
+            for step in &sequence.steps {
+                match *step {
+                    ResetStep::Dtr(asserted) => port.write_data_terminal_ready(asserted)?,
+                    ResetStep::Rts(asserted) => port.write_request_to_send(asserted)?,
+                    ResetStep::Break(duration) => send_break(port, duration)?,
+                    ResetStep::Wait(duration) => thread::sleep(duration),
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Runs an operation on the writing half of the port.
+    ///
+    /// Fails with SerialManagerError::SessionDisconnected while the session
+    /// waits for its device to come back, and SessionClosed once it is closed.
+    fn with_port<T>(&self, operation: impl FnOnce(&mut dyn SerialPort) -> serialport::Result<T>) -> Result<T> {
+        let mut writer = self.session.writer.lock()
+            .map_err(|_| SerialManagerError::LockPoisoned("session writer"))?;
+        let Some(port) = writer.as_mut() else {
+            let state = self.session.lock_state();
+            let port = state.port_name.clone();
+            return Err(match state.closed {
+                None => SerialManagerError::SessionDisconnected { port },
+                Some(_) => SerialManagerError::SessionClosed { port },
+            });
+        };
+        operation(&mut **port).map_err(|e| SerialManagerError::from_serialport(&self.session.port_name(), e))
+    }
+
+    /// Closes the session and waits until its port has been released.
+    ///
+    /// Does nothing if the session is already closed.
+    pub fn close(&self) {
+        self.session.request_close(CloseReason::Closed);
+
+        let reader = self.session.reader.lock().ok().and_then(|mut reader| reader.take());
+        match reader {
+            Some(reader) => {
+                if reader.join().is_err() {
+                    log::error!("Reader thread for {} panicked", self.session.id);
+                    let mut state = self.session.lock_state();
+                    state.closed.get_or_insert(CloseReason::Closed);
+                    self.session.changed.notify_all();
+                }
+            }
+            // Another handle is joining the reader, wait for it to finish
+            None => {
+                let state = self.session.lock_state();
+                drop(self.session.changed.wait_while(state, |state| state.closed.is_none()));
+            }
+        }
+    }
+
+    /// Asks the session to close, or to disconnect if it reconnects, without
+    /// waiting for it to do so.
+    pub(crate) fn shutdown(&self, reason: CloseReason) {
+        self.session.request_close(reason);
+    }
+
+    /// Tells the session its device is present on the given port.
+    ///
+    /// A disconnected session reconnects to it. A connected one only does so
+    /// if `reset` is set, meaning the device went away and came back, so the
+    /// port the session has open is stale.
+    fn device_present(&self, port: &PortInfo, reset: bool) {
+        let mut state = self.session.lock_state();
+        if state.closed.is_some() {
+            return;
+        }
+        if state.identity.is_none() && state.port_name == port.port_name {
+            state.identity = port.identity();
+        }
+        if !state.reconnects() || state.identity != port.identity() {
+            return;
+        }
+        if state.disconnected_at.is_some() || reset {
+            state.reconnect_to = Some(port.port_name.clone());
+            if state.disconnected_at.is_none() && state.closing.is_none() {
+                state.closing = Some(CloseReason::DeviceDeparted);
+            }
+            self.session.changed.notify_all();
+        }
+    }
+}
+
+impl fmt::Debug for SessionHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionHandle")
+            .field("id", &self.session.id)
+            .field("port_name", &self.session.port_name())
+            .finish()
+    }
+}
+
+fn send_break(port: &mut dyn SerialPort, duration: Duration) -> serialport::Result<()> {
+    port.set_break()?;
+    thread::sleep(duration);
+    port.clear_break()
+}
+
+fn read_control_lines(port: &mut dyn SerialPort) -> serialport::Result<ControlLines> {
+    Ok(ControlLines {
+        cts: port.read_clear_to_send()?,
+        dsr: port.read_data_set_ready()?,
+        ri: port.read_ring_indicator()?,
+        cd: port.read_carrier_detect()?,
+    })
+}
+
+/// Polls the control lines of a port while its session watches them
+struct LineWatcher {
+    watching: bool,
+    last: Option<ControlLines>,
+    polled_at: Instant,
+}
+
+impl LineWatcher {
+    fn new() -> Self {
+        LineWatcher { watching: false, last: None, polled_at: Instant::now() }
+    }
+
+    /// Reports the lines if they changed since the last poll. Shortens the
+    /// read timeout while watching, so that polls are not held up by reads.
+    fn poll(&mut self, session: &Session, port: &mut dyn SerialPort) {
+        let watch = session.watch_lines.load(Ordering::Relaxed);
+        if watch != self.watching {
+            self.watching = watch;
+            self.last = None;
+            let timeout = if watch {
+                session.config.read_timeout.min(CONTROL_LINE_POLL_INTERVAL)
+            } else {
+                session.config.read_timeout
+            };
+            if let Err(e) = port.set_timeout(timeout) {
+                log::debug!("Could not change the read timeout of {}: {}", session.id, e);
+            }
+        }
+        if !self.watching || (self.last.is_some() && self.polled_at.elapsed() < CONTROL_LINE_POLL_INTERVAL) {
+            return;
+        }
+
+        self.polled_at = Instant::now();
+        match read_control_lines(port) {
+            Ok(lines) if self.last != Some(lines) => {
+                self.last = Some(lines);
+                session.report(SessionEvent::ControlLines { session: session.id, lines });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::warn!("Cannot watch the control lines of {}: {}", session.id, e);
+                session.watch_lines.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Body of a session's reader thread.
+///
+/// Reads the port until it is lost, then either closes the session or, if it
+/// reconnects, waits for the device to come back and carries on with the
+/// reopened port. Finally releases the port and reports why the session closed.
+///
+/// A frame in progress when the port is lost is flushed, since the rest of it
+/// will not arrive.
+fn run_session(
+    session: Arc<Session>,
+    mut port: Box<dyn SerialPort>,
+    mut deframer: Option<Deframer>
+) {
+    let reason = loop {
+        let reason = read_port(&session, &mut *port, deframer.as_mut());
+        drop(port);
+        if let Some((frame, started)) = deframer.as_mut().and_then(|deframer| deframer.flush(session.stamp())) {
+            report_frame(&session, frame, started);
+        }
+        session.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+
+        let (port_name, disconnected_at) = {
+            let mut state = session.lock_state();
+            if reason == CloseReason::Closed || !state.reconnects() {
+                break reason;
+            }
+            let disconnected_at = SystemTime::now();
+            state.closing = None;
+            state.disconnected_at = Some(disconnected_at);
+            (state.port_name.clone(), disconnected_at)
+        };
+        log::warn!("Disconnected {} from {}, waiting for the device to come back", session.id, port_name);
+        session.report(SessionEvent::Disconnected {
+            session: session.id,
+            port_name,
+            reason,
+        });
+
+        match wait_for_device(&session) {
+            Some(reopened) => port = reopened,
+            None if session.lock_state().close_requested() => break CloseReason::Closed,
+            None => break CloseReason::DeviceDeparted,
+        }
+        let port_name = {
+            let mut state = session.lock_state();
+            state.disconnected_at = None;
+            // A departure reported while reopening concerned the old port
+            if !state.close_requested() {
+                state.closing = None;
+            }
+            state.port_name.clone()
+        };
+        log::info!("Reconnected {} on {}", session.id, port_name);
+        session.report(SessionEvent::Reconnected {
+            session: session.id,
+            port_name,
+            disconnected_at,
+        });
+    };
+
+    // Finished before the session counts as closed, so that nothing is sent
+    // or captured once close() returns
+    session.transmit.close();
+    if let Err(e) = session.finish_capture() {
+        log::warn!("Failed to finish capturing {}: {}", session.id, e);
+    }
+    let port_name = {
+        let mut state = session.lock_state();
+        state.closed = Some(reason.clone());
+        state.port_name.clone()
+    };
+    session.changed.notify_all();
+
+    match &reason {
+        CloseReason::Error(e) => log::warn!("Closed {} on {}: {}", session.id, port_name, e),
+        _ => log::info!("Closed {} on {} ({:?})", session.id, port_name, reason),
+    }
+    session.report(SessionEvent::Closed {
+        session: session.id,
+        port_name,
+        reason,
+    });
+}
+
+/// Reads from the port and reports each chunk, or each frame the deframer
+/// completes, until the session is asked to close or disconnect, or reading
+/// fails. Returns the reason reading stopped.
+fn read_port(
+    session: &Session,
+    port: &mut dyn SerialPort,
+    mut deframer: Option<&mut Deframer>
+) -> CloseReason {
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    let mut lines = LineWatcher::new();
+    loop {
+        if let Some(reason) = session.close_requested() {
+            return reason;
+        }
+        lines.poll(session, port);
+        match port.read(&mut chunk) {
+            Ok(0) => {}
+            Ok(count) => {
+                // Taken before anything else so that it is as close to the read as possible
+                let read_at = session.stamp();
+                session.update_stats(|stats| {
+                    stats.chunks_received += 1;
+                    stats.bytes_received += count as u64;
+                });
+                session.record(Direction::Rx, read_at, &chunk[..count]);
+                session.transmit.received(&chunk[..count]);
+                match deframer.as_deref_mut() {
+                    Some(deframer) => {
+                        for (frame, started) in deframer.push(&chunk[..count], read_at) {
+                            report_frame(session, frame, started);
+                        }
+                    }
+                    None => session.report(SessionEvent::Data {
+                        session: session.id,
+                        bytes: chunk[..count].to_vec(),
+                        host_timestamp: read_at.host_timestamp,
+                        elapsed: read_at.elapsed,
+                    }),
+                }
+                continue;
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+            Err(e) => {
+                // A read failing because the session is closing is not an error
+                return session.close_requested()
+                    .unwrap_or_else(|| CloseReason::Error(SerialManagerError::from_port_io(&session.port_name(), e)));
+            }
+        }
+        // Nothing arrived within the read timeout
+        if let Some((frame, started)) = deframer.as_deref_mut().and_then(|deframer| deframer.poll_idle(session.stamp())) {
+            report_frame(session, frame, started);
+        }
+    }
+}
+
+fn report_frame(session: &Session, frame: Frame, started: Stamp) {
+    session.update_stats(|stats| stats.frames_received += 1);
+    session.report(SessionEvent::Frame {
+        session: session.id,
+        frame,
+        host_timestamp: started.host_timestamp,
+        elapsed: started.elapsed,
+    });
+}
+
+/// Waits until the device of a disconnected session is reported present
+/// again and reopens it. Returns None if the session is closed or auto-reconnect
+/// is turned off in the meantime.
+fn wait_for_device(session: &Session) -> Option<Box<dyn SerialPort>> {
+    loop {
+        let port_name = {
+            let state = session.lock_state();
+            let mut state = session.changed
+                .wait_while(state, |state| state.reconnect_to.is_none() && !state.stop_waiting())
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if state.stop_waiting() {
+                return None;
+            }
+            state.reconnect_to.take()?
+        };
+
+        // The device node may take a moment to become usable, e.g. while udev
+        // applies its permissions
+        for attempt in 1..=REOPEN_ATTEMPTS {
+            match open_port(&port_name, &session.config) {
+                Ok((reader, writer)) => {
+                    *session.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(writer);
+                    session.lock_state().port_name = port_name;
+                    return Some(reader);
+                }
+                Err(e) => log::debug!("Reopening {} for {} failed (attempt {}): {}", port_name, session.id, attempt, e),
+            }
+            let state = session.lock_state();
+            let (state, _) = session.changed
+                .wait_timeout_while(state, REOPEN_INTERVAL, |state| !state.stop_waiting())
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if state.stop_waiting() {
+                return None;
+            }
+        }
+        log::warn!("Could not reopen {} for {}, waiting for the device to come back again", port_name, session.id);
+    }
+}
+
+/// The sessions opened through one SerialManager
+pub(crate) struct SessionRegistry {
+    sessions: Mutex<HashMap<SessionId, SessionHandle>>,
+    next_id: AtomicU64,
+    /// Start of the monotonic clock shared by all sessions
+    epoch: Instant,
+    callback: SessionEventCallback,
+}
+
+impl SessionRegistry {
+    pub(crate) fn new(callback: SessionEventCallback) -> Self {
+        SessionRegistry {
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            epoch: Instant::now(),
+            callback,
+        }
+    }
+
+    /// Returns the start of the monotonic clock the sessions' data is stamped with.
+    pub(crate) fn epoch(&self) -> Instant {
+        self.epoch
+    }
+
+    /// Opens a session, unless one is already open on the port.
+    pub(crate) fn open(
+        &self,
+        port_name: &str,
+        identity: Option<DeviceIdentity>,
+        config: PortConfig,
+        framer: Option<Box<dyn Framer>>
+    ) -> Result<SessionHandle> {
+        let mut sessions = self.lock()?;
+        sessions.retain(|_, session| session.is_open());
+        if sessions.values().any(|session| session.port_name() == port_name) {
+            return Err(SerialManagerError::PortBusy { port: port_name.to_string() });
+        }
+
+        let id = SessionId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let session = SessionHandle::open(id, port_name, identity, config, framer, self.epoch, self.callback.clone())?;
+        sessions.insert(id, session.clone());
+        Ok(session)
+    }
+
+    /// Returns the session with the given id, if it is still open.
+    pub(crate) fn get(&self, id: SessionId) -> Result<Option<SessionHandle>> {
+        Ok(self.lock()?.get(&id).filter(|session| session.is_open()).cloned())
+    }
+
+    /// Returns every open session, ordered by id.
+    pub(crate) fn list(&self) -> Result<Vec<SessionHandle>> {
+        let mut sessions: Vec<SessionHandle> = self.lock()?
+            .values()
+            .filter(|session| session.is_open())
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.id());
+        Ok(sessions)
+    }
+
+    /// Asks every session open on the given port to close.
+    pub(crate) fn shutdown_port(&self, port_name: &str, reason: CloseReason) {
+        if let Ok(sessions) = self.lock() {
+            sessions.values()
+                .filter(|session| session.port_name() == port_name)
+                .for_each(|session| session.shutdown(reason.clone()));
+        }
+    }
+
+    /// Tells every session that the device behind the given port is present,
+    /// so that sessions waiting for it reconnect. See SessionHandle::device_present().
+    pub(crate) fn device_present(&self, port: &PortInfo, reset: bool) {
+        if let Ok(sessions) = self.lock() {
+            sessions.values().for_each(|session| session.device_present(port, reset));
+        }
+    }
+
+    /// Counts a DataReceived or FrameReceived event that a subscriber queue
+    /// coalesced or dropped against the session it came from.
+    pub(crate) fn record_overflow(&self, event: &SerialEvent, overflow: Overflow) {
+        let session = match event {
+            SerialEvent::DataReceived { session, .. } | SerialEvent::FrameReceived { session, .. } => session,
+            _ => return,
+        };
+        let Ok(sessions) = self.lock() else { return };
+        let Some(handle) = sessions.get(session) else { return };
+        handle.session.update_stats(|stats| match (event, overflow) {
+            (SerialEvent::DataReceived { .. }, Overflow::Coalesced) => stats.chunks_coalesced += 1,
+            (SerialEvent::DataReceived { bytes, .. }, Overflow::Dropped) => {
+                stats.chunks_dropped += 1;
+                stats.bytes_dropped += bytes.len() as u64;
+            }
+            (_, _) => stats.frames_dropped += 1,
+        });
+    }
+
+    /// Asks every session to close.
+    pub(crate) fn shutdown_all(&self) {
+        if let Ok(sessions) = self.lock() {
+            sessions.values().for_each(|session| session.shutdown(CloseReason::Closed));
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<SessionId, SessionHandle>>> {
+        self.sessions.lock().map_err(|_| SerialManagerError::LockPoisoned("sessions"))
+    }
+}
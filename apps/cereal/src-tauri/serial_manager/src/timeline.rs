@@ -0,0 +1,241 @@
+//! # Merged Timeline
+//!
+//! When two devices talk to each other and each has its own debug port, what
+//! matters is the order things happened in across the ports. A Timeline merges
+//! the data received on and written to several sessions into one stream of
+//! TimelineEntry values, ordered by when they happened.
+//!
+//! ## Ordering
+//!
+//! Every session stamps its data on the manager's monotonic clock when the
+//! read or write returns, so entries from different sessions can be compared
+//! even if the wall clock jumps. Each session reports from its own thread
+//! though, so events can reach the bus slightly out of order. The timeline
+//! holds entries back for a reorder window (DEFAULT_REORDER_WINDOW) and sorts
+//! them before handing them out; entries that arrive later than the window
+//! are handed out as soon as possible, out of order.
+//!
+//! Data is stamped when the read returns, so a read that collected bytes
+//! from a slow stream is dated by its last byte. Entries are only as precise
+//! as PortConfig::read_timeout and the driver's buffering.
+//!
+//! ```rust ignore
+//! let mcu_a = manager.open("/dev/ttyUSB0", PortConfig::default())?;
+//! let mcu_b = manager.open("/dev/ttyUSB1", PortConfig::default())?;
+//! let mut timeline = manager.timeline(&[mcu_a.id(), mcu_b.id()])?;
+//! loop {
+//!     for entry in timeline.recv_timeout(Duration::from_millis(100))? {
+//!         println!("{:>10.3?} {} {:?} {:?}", entry.elapsed, entry.port_name, entry.direction, entry.bytes);
+//!     }
+//! }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Weak,
+    time::{Duration, Instant, SystemTime},
+};
+use serde::{Deserialize, Serialize};
+use crate::bus::{EventReceiver, RecvError, TryRecvError};
+use crate::framing::FrameStatus;
+use crate::manager::SerialEvent;
+use crate::session::{SessionId, SessionRegistry};
+
+/// How long a timeline holds entries back to put them in order
+pub const DEFAULT_REORDER_WINDOW: Duration = Duration::from_millis(100);
+
+/// Queue capacity of a timeline's subscription, larger than the default since
+/// a timeline carries the data of several busy sessions
+pub(crate) const TIMELINE_CAPACITY: usize = 4096;
+
+/// Whether data was received from or sent to the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Read from the port
+    Rx,
+    /// Written to the port
+    Tx,
+}
+
+/// Data received on or written to one session, as it appears in a timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// The session the data belongs to
+    pub session: SessionId,
+    /// The port the session had open at the time
+    pub port_name: String,
+    /// Whether the data was received or sent
+    pub direction: Direction,
+    /// A chunk of received or sent data, or a received frame
+    pub bytes: Vec<u8>,
+    /// The framer's verdict if the entry is a received frame
+    pub frame_status: Option<FrameStatus>,
+    /// Whether the frame's checksum matched, if its framing has one
+    pub crc_ok: Option<bool>,
+    /// Time on the manager's monotonic clock, which entries are ordered by
+    pub elapsed: Duration,
+    /// Wall clock time at the same moment
+    pub host_timestamp: SystemTime,
+}
+
+/// Time-ordered stream of the data of several sessions, see SerialManager::timeline()
+pub struct Timeline {
+    events: EventReceiver,
+    /// Sessions to include, or None for all
+    sessions: Option<HashSet<SessionId>>,
+    registry: Weak<SessionRegistry>,
+    /// Last known port of each session, kept after the session closes
+    port_names: HashMap<SessionId, String>,
+    epoch: Instant,
+    reorder_window: Duration,
+    /// Entries held back, ordered by elapsed
+    pending: VecDeque<TimelineEntry>,
+}
+
+impl Timeline {
+    pub(crate) fn new(
+        events: EventReceiver,
+        sessions: Option<HashSet<SessionId>>,
+        registry: Weak<SessionRegistry>,
+        epoch: Instant
+    ) -> Self {
+        // Sessions that close before their data is read can no longer be looked up
+        let port_names = registry.upgrade()
+            .and_then(|registry| registry.list().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|handle| (handle.id(), handle.port_name()))
+            .collect();
+        Timeline {
+            events,
+            sessions,
+            registry,
+            port_names,
+            epoch,
+            reorder_window: DEFAULT_REORDER_WINDOW,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns how long entries are held back to put them in order.
+    pub fn reorder_window(&self) -> Duration {
+        self.reorder_window
+    }
+
+    /// Changes how long entries are held back. A longer window tolerates more
+    /// scheduling jitter between the sessions' threads, at the cost of latency.
+    pub fn set_reorder_window(&mut self, window: Duration) {
+        self.reorder_window = window;
+    }
+
+    /// Waits up to `timeout` for data and returns the entries that are due, in order.
+    ///
+    /// An entry is due once it is older than the reorder window. The result is
+    /// empty if nothing became due in time. Once the event bus closes, which
+    /// happens when the manager and all handles to its sessions are dropped,
+    /// the entries still held back are returned, and after that RecvError::Closed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with RecvError::Lagged if the timeline fell so far behind that
+    /// events were dropped; entries already held back are kept and the
+    /// timeline carries on with the next call.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Vec<TimelineEntry>, RecvError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Everything queued, so that entries are sorted against all that arrived so far
+            loop {
+                match self.events.try_recv() {
+                    Ok(event) => self.insert(event),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Lagged(missed)) => return Err(RecvError::Lagged(missed)),
+                    Err(TryRecvError::Closed) if self.pending.is_empty() => return Err(RecvError::Closed),
+                    Err(TryRecvError::Closed) => return Ok(self.drain()),
+                }
+            }
+
+            let due = self.take_due();
+            let now = Instant::now();
+            if !due.is_empty() || now >= deadline {
+                return Ok(due);
+            }
+
+            // Sleep until new data arrives, the oldest held back entry is due, or the time is up
+            let mut wait = deadline - now;
+            if let Some(oldest) = self.pending.front() {
+                let due_at = self.epoch + oldest.elapsed + self.reorder_window;
+                wait = wait.min(due_at.saturating_duration_since(now));
+            }
+            match self.events.recv_timeout(wait) {
+                Ok(Some(event)) => self.insert(event),
+                Ok(None) => {}
+                Err(RecvError::Closed) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns every entry held back, in order, without waiting for the reorder window.
+    pub fn drain(&mut self) -> Vec<TimelineEntry> {
+        self.pending.drain(..).collect()
+    }
+
+    /// Removes the entries older than the reorder window.
+    fn take_due(&mut self) -> Vec<TimelineEntry> {
+        let cutoff = self.epoch.elapsed().saturating_sub(self.reorder_window);
+        let due = self.pending.iter().take_while(|entry| entry.elapsed <= cutoff).count();
+        self.pending.drain(..due).collect()
+    }
+
+    /// Turns an event into an entry, if it is data of an included session,
+    /// and holds it back in order. Entries with equal times keep their arrival order.
+    fn insert(&mut self, event: SerialEvent) {
+        let (session, direction, bytes, frame_status, crc_ok, elapsed, host_timestamp) = match event {
+            SerialEvent::DataReceived { session, bytes, host_timestamp, elapsed } => {
+                (session, Direction::Rx, bytes, None, None, elapsed, host_timestamp)
+            }
+            SerialEvent::FrameReceived { session, frame, host_timestamp, elapsed } => {
+                (session, Direction::Rx, frame.bytes, Some(frame.status), frame.crc_ok, elapsed, host_timestamp)
+            }
+            SerialEvent::DataSent { session, bytes, host_timestamp, elapsed } => {
+                (session, Direction::Tx, bytes, None, None, elapsed, host_timestamp)
+            }
+            SerialEvent::SessionReconnected { session, port_name, .. } => {
+                self.port_names.insert(session, port_name);
+                return;
+            }
+            _ => return,
+        };
+        if self.sessions.as_ref().is_some_and(|sessions| !sessions.contains(&session)) {
+            return;
+        }
+
+        let entry = TimelineEntry {
+            session,
+            port_name: self.port_name(session),
+            direction,
+            bytes,
+            frame_status,
+            crc_ok,
+            elapsed,
+            host_timestamp,
+        };
+        let position = self.pending.partition_point(|pending| pending.elapsed <= entry.elapsed);
+        self.pending.insert(position, entry);
+    }
+
+    fn port_name(&mut self, session: SessionId) -> String {
+        if let Some(port_name) = self.port_names.get(&session) {
+            return port_name.clone();
+        }
+        let port_name = self.registry.upgrade()
+            .and_then(|registry| registry.get(session).ok().flatten())
+            .map(|handle| handle.port_name())
+            .unwrap_or_default();
+        if !port_name.is_empty() {
+            self.port_names.insert(session, port_name.clone());
+        }
+        port_name
+    }
+}
@@ -0,0 +1,449 @@
+//! # Transmit Queue
+//!
+//! SessionHandle::write() hands data to the driver as fast as the line takes
+//! it, which overruns devices that process input slowly, such as bootloader
+//! shells fed a pasted config script. SessionHandle::transmit() queues data
+//! to be sent at a pace the device can follow instead.
+//!
+//! ## Pacing
+//!
+//! TransmitOptions sets how a transmission is sent:
+//!
+//! - Text is split into lines, which may be sent with a different line ending.
+//!   A pause can follow every line, and the queue can wait for the device's
+//!   prompt, a regular expression matched against what the session receives,
+//!   before sending the next one.
+//! - Binary data is sent as it is, in blocks.
+//! - Either can pause after every character.
+//!
+//! Transmissions are sent one after the other on a thread of their own. Each
+//! reports its progress as SerialEvent::TransmitProgress and its outcome as
+//! SerialEvent::TransmitFinished. What it writes is published as DataSent and
+//! captured like any other write.
+//!
+//! ## Cancellation
+//!
+//! SessionHandle::cancel_transmit() cancels a transmission, whether it is
+//! queued or being sent, taking effect before its next character. Closing the
+//! session cancels all of them.
+//!
+//! ```rust ignore
+//! let options = TransmitOptions {
+//!     line_delay: Duration::from_millis(20),
+//!     prompt: Some(r"=> $".to_string()),
+//!     ..Default::default()
+//! };
+//! let id = session.transmit_file("uboot-env.txt", options)?;
+//! // ... TransmitProgress events, then TransmitFinished for `id` ...
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use crate::error::{Result, SerialManagerError};
+use crate::framing::LineEnding;
+use crate::session::{SessionEvent, SessionEventCallback, SessionId};
+
+/// Size of the blocks binary data is written and reported in
+const BLOCK_SIZE: usize = 1024;
+
+/// Most received data kept for matching a prompt; older data is discarded
+const MAX_PROMPT_BUFFER: usize = 64 * 1024;
+
+/// Identifies a transmission for as long as its session lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TransmitId(u64);
+
+impl TransmitId {
+    /// Returns the numeric value of the id.
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for TransmitId {
+    fn from(value: u64) -> Self {
+        TransmitId(value)
+    }
+}
+
+impl fmt::Display for TransmitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transmission {}", self.0)
+    }
+}
+
+/// How the data of a transmission is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransmitMode {
+    /// Lines ending in any of "\n", "\r" and "\r\n", each sent followed by
+    /// `line_ending`, or by its own ending if that is LineEnding::Any. A last
+    /// line without an ending gets one too, unless `line_ending` is Any.
+    Text { line_ending: LineEnding },
+    /// Bytes sent as they are. Line delays and prompts do not apply.
+    Binary,
+}
+
+/// How a transmission is paced
+///
+/// Fields missing when deserializing take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransmitOptions {
+    /// Whether the data is text sent line by line, or binary
+    pub mode: TransmitMode,
+    /// Pause after every character
+    pub char_delay: Duration,
+    /// Pause after every line
+    pub line_delay: Duration,
+    /// Regular expression the received data must match after a line was sent
+    /// before the next one is, e.g. the device's shell prompt. The last line
+    /// waits for it too, so the transmission ends once the device is ready.
+    pub prompt: Option<String>,
+    /// How long to wait for the prompt after each line before giving up
+    pub prompt_timeout: Duration,
+}
+
+impl Default for TransmitOptions {
+    /// Text with its own line endings, no delays, no prompt, 5s prompt timeout
+    fn default() -> Self {
+        TransmitOptions {
+            mode: TransmitMode::Text { line_ending: LineEnding::Any },
+            char_delay: Duration::ZERO,
+            line_delay: Duration::ZERO,
+            prompt: None,
+            prompt_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// How a transmission ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransmitOutcome {
+    /// Everything was sent
+    Completed,
+    /// The transmission was cancelled, or the session closed
+    Cancelled,
+    /// The prompt did not arrive within the timeout after the given line, counted from 1
+    PromptTimedOut {
+        /// The line that was not answered by a prompt
+        line: usize,
+    },
+    /// Writing to the port failed
+    Failed(SerialManagerError),
+}
+
+/// A queued transmission, split into the lines or blocks it is sent in
+struct Job {
+    id: TransmitId,
+    units: Vec<Vec<u8>>,
+    total: u64,
+    options: TransmitOptions,
+    prompt: Option<Regex>,
+}
+
+/// Writes to the session's port, see SessionHandle::write()
+pub(crate) type TransmitWriter = Box<dyn Fn(&[u8]) -> Result<()> + Send>;
+
+struct QueueState {
+    next_id: u64,
+    jobs: VecDeque<Job>,
+    /// The transmission being sent
+    current: Option<TransmitId>,
+    /// Whether the transmission being sent was cancelled
+    cancelled: bool,
+    /// Data received since the current line was sent, while waiting for a prompt
+    received: Option<Vec<u8>>,
+    worker: Option<JoinHandle<()>>,
+    /// Set when the session closed; no more transmissions are accepted
+    closed: bool,
+}
+
+/// Transmissions of a session waiting to be sent, and the one being sent
+pub(crate) struct TransmitQueue {
+    session: SessionId,
+    callback: SessionEventCallback,
+    state: Mutex<QueueState>,
+    /// Signalled when data is received while waiting for a prompt, and when
+    /// the current transmission is cancelled
+    changed: Condvar,
+}
+
+impl TransmitQueue {
+    pub(crate) fn new(session: SessionId, callback: SessionEventCallback) -> Self {
+        TransmitQueue {
+            session,
+            callback,
+            state: Mutex::new(QueueState {
+                next_id: 1,
+                jobs: VecDeque::new(),
+                current: None,
+                cancelled: false,
+                received: None,
+                worker: None,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn report(&self, event: SessionEvent) {
+        (self.callback)(event);
+    }
+
+    fn finish(&self, transmit: TransmitId, outcome: TransmitOutcome) {
+        match &outcome {
+            TransmitOutcome::Completed => log::info!("Finished {} on {}", transmit, self.session),
+            _ => log::warn!("Stopped {} on {}: {:?}", transmit, self.session, outcome),
+        }
+        self.report(SessionEvent::TransmitFinished { session: self.session, transmit, outcome });
+    }
+
+    /// Queues data to be sent, starting the thread that sends it if it is not
+    /// running. Returns None if the session closed.
+    pub(crate) fn push(
+        self: &Arc<Self>,
+        data: &[u8],
+        options: TransmitOptions,
+        writer: TransmitWriter
+    ) -> Result<Option<TransmitId>> {
+        let (units, prompt) = match options.mode {
+            TransmitMode::Text { line_ending } => {
+                let prompt = options.prompt.as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid prompt: {}", e)))?;
+                (split_lines(data, line_ending), prompt)
+            }
+            TransmitMode::Binary => (data.chunks(BLOCK_SIZE).map(<[u8]>::to_vec).collect(), None),
+        };
+
+        let mut state = self.lock_state();
+        if state.closed {
+            return Ok(None);
+        }
+        let id = TransmitId(state.next_id);
+        state.next_id += 1;
+        state.jobs.push_back(Job {
+            id,
+            total: units.iter().map(|unit| unit.len() as u64).sum(),
+            units,
+            options,
+            prompt,
+        });
+        if state.worker.is_none() {
+            let queue = self.clone();
+            state.worker = Some(thread::spawn(move || queue.run(writer)));
+        }
+        log::info!("Queued {} on {}", id, self.session);
+        Ok(Some(id))
+    }
+
+    /// Cancels a transmission. Returns false if it is neither queued nor being sent.
+    pub(crate) fn cancel(&self, transmit: TransmitId) -> bool {
+        let mut state = self.lock_state();
+        if state.current == Some(transmit) {
+            state.cancelled = true;
+            self.changed.notify_all();
+            return true;
+        }
+        let Some(index) = state.jobs.iter().position(|job| job.id == transmit) else {
+            return false;
+        };
+        state.jobs.remove(index);
+        drop(state);
+        self.finish(transmit, TransmitOutcome::Cancelled);
+        true
+    }
+
+    /// Cancels all transmissions for good and waits until the one being sent stopped.
+    pub(crate) fn close(&self) {
+        let (jobs, worker) = {
+            let mut state = self.lock_state();
+            state.closed = true;
+            state.cancelled = true;
+            self.changed.notify_all();
+            (std::mem::take(&mut state.jobs), state.worker.take())
+        };
+        for job in jobs {
+            self.finish(job.id, TransmitOutcome::Cancelled);
+        }
+        if let Some(worker) = worker {
+            if worker.join().is_err() {
+                log::error!("Transmit thread for {} panicked", self.session);
+            }
+        }
+    }
+
+    /// Hands received data to a transmission waiting for a prompt.
+    pub(crate) fn received(&self, bytes: &[u8]) {
+        let mut state = self.lock_state();
+        if let Some(received) = state.received.as_mut() {
+            received.extend_from_slice(bytes);
+            if received.len() > MAX_PROMPT_BUFFER {
+                received.drain(..received.len() - MAX_PROMPT_BUFFER);
+            }
+            self.changed.notify_all();
+        }
+    }
+
+    /// Sends queued transmissions until there are none left.
+    fn run(&self, writer: TransmitWriter) {
+        loop {
+            let job = {
+                let mut state = self.lock_state();
+                let Some(job) = state.jobs.pop_front() else {
+                    state.current = None;
+                    // Detaches this thread; the next push starts a new one
+                    state.worker = None;
+                    return;
+                };
+                state.current = Some(job.id);
+                state.cancelled = false;
+                job
+            };
+            let outcome = self.send(&job, &writer);
+            {
+                let mut state = self.lock_state();
+                state.current = None;
+                state.received = None;
+            }
+            self.finish(job.id, outcome);
+        }
+    }
+
+    fn send(&self, job: &Job, writer: &TransmitWriter) -> TransmitOutcome {
+        let mut sent = 0;
+        for (index, unit) in job.units.iter().enumerate() {
+            if job.prompt.is_some() {
+                // Only what the device says in response to this line counts
+                self.lock_state().received = Some(Vec::new());
+            }
+            if let Err(e) = self.write(unit, job.options.char_delay, writer) {
+                return if self.lock_state().cancelled { TransmitOutcome::Cancelled } else { TransmitOutcome::Failed(e) };
+            }
+            if self.lock_state().cancelled {
+                return TransmitOutcome::Cancelled;
+            }
+            sent += unit.len() as u64;
+            self.report(SessionEvent::TransmitProgress {
+                session: self.session,
+                transmit: job.id,
+                sent,
+                total: job.total,
+            });
+
+            if let Some(prompt) = &job.prompt {
+                match self.wait_for_prompt(prompt, job.options.prompt_timeout) {
+                    Some(true) => {}
+                    Some(false) => return TransmitOutcome::PromptTimedOut { line: index + 1 },
+                    None => return TransmitOutcome::Cancelled,
+                }
+            }
+            let last = index + 1 == job.units.len();
+            if matches!(job.options.mode, TransmitMode::Text { .. }) && !last && !self.pause(job.options.line_delay) {
+                return TransmitOutcome::Cancelled;
+            }
+        }
+        TransmitOutcome::Completed
+    }
+
+    /// Writes a line or block, character by character if there is a delay
+    /// between them. Stops early without an error if cancelled.
+    fn write(&self, unit: &[u8], char_delay: Duration, writer: &TransmitWriter) -> Result<()> {
+        if char_delay.is_zero() {
+            return writer(unit);
+        }
+        for byte in unit {
+            writer(std::slice::from_ref(byte))?;
+            if !self.pause(char_delay) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for `duration` unless the transmission is cancelled first, in
+    /// which case it returns false.
+    fn pause(&self, duration: Duration) -> bool {
+        let state = self.lock_state();
+        if duration.is_zero() {
+            return !state.cancelled;
+        }
+        let (state, _) = self.changed
+            .wait_timeout_while(state, duration, |state| !state.cancelled)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        !state.cancelled
+    }
+
+    /// Waits until the data received since the line was sent matches the
+    /// prompt. Returns whether it did before the timeout, or None if the
+    /// transmission was cancelled.
+    fn wait_for_prompt(&self, prompt: &Regex, timeout: Duration) -> Option<bool> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock_state();
+        loop {
+            if state.cancelled {
+                return None;
+            }
+            if state.received.as_deref().is_some_and(|received| prompt.is_match(received)) {
+                state.received = None;
+                return Some(true);
+            }
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) else {
+                state.received = None;
+                return Some(false);
+            };
+            state = self.changed
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+}
+
+/// Splits text into lines, each followed by `line_ending` or its own ending.
+fn split_lines(data: &[u8], line_ending: LineEnding) -> Vec<Vec<u8>> {
+    let ending: Option<&[u8]> = match line_ending {
+        LineEnding::Lf => Some(b"\n"),
+        LineEnding::Cr => Some(b"\r"),
+        LineEnding::CrLf => Some(b"\r\n"),
+        LineEnding::Any => None,
+    };
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let own_ending = match (data[index], data.get(index + 1)) {
+            (b'\r', Some(b'\n')) => 2,
+            (b'\r' | b'\n', _) => 1,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let mut line = data[start..index].to_vec();
+        line.extend_from_slice(ending.unwrap_or(&data[index..index + own_ending]));
+        lines.push(line);
+        index += own_ending;
+        start = index;
+    }
+    if start < data.len() {
+        let mut line = data[start..].to_vec();
+        line.extend_from_slice(ending.unwrap_or_default());
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,319 @@
+//! # Kernel Uevent Hotplug Backend
+//!
+//! On Linux the kernel broadcasts a "uevent" over a netlink socket whenever a device
+//! is added to or removed from the system. Listening to these lets the DiscoveryService
+//! react to USB serial devices immediately, instead of waiting for the next poll, and
+//! catches short bootloader re-enumerations that a 2 second poll would miss.
+//!
+//! ## Message Format
+//!
+//! A kernel uevent is a sequence of NUL terminated strings. The first is a summary of
+//! the form `ACTION@DEVPATH`, followed by `KEY=VALUE` pairs:
+//!
+//! ```text
+//! add@/devices/pci0000:00/.../tty/ttyACM0\0ACTION=add\0DEVPATH=/devices/...\0SUBSYSTEM=tty\0DEVNAME=ttyACM0\0
+//! ```
+//!
+//! Only `tty` subsystem `add`/`remove` events for real (non-virtual) devices are turned
+//! into PortEvents.
+//!
+//! ## Testing
+//!
+//! The source of raw messages is abstracted behind the UeventSource trait, so tests can
+//! feed synthetic messages through an InjectedUeventSource instead of a netlink socket.
+
+use std::{
+    fs, io,
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    time::Duration,
+};
+use crate::discovery::PortEvent;
+use crate::port_info::{PortInfo, PortType};
+
+/// The kind of change a uevent describes
+#[derive(Debug, Clone, PartialEq)]
+pub enum UeventAction {
+    /// A device was added to the system
+    Add,
+    /// A device was removed from the system
+    Remove,
+    /// Any other action (change, bind, unbind, move, ...)
+    Other(String),
+}
+
+/// A parsed kernel uevent message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uevent {
+    /// What happened to the device
+    pub action: UeventAction,
+    /// Kernel device path, relative to /sys
+    pub devpath: String,
+    /// Subsystem the device belongs to (e.g. "tty", "usb")
+    pub subsystem: Option<String>,
+    /// Device node name relative to /dev, if the device has one
+    pub devname: Option<String>,
+}
+
+impl Uevent {
+    /// Parses a raw kernel uevent message.
+    ///
+    /// Returns None for messages that are not kernel uevents, such as the
+    /// "libudev" tagged messages re-broadcast by udevd.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let mut fields = message
+            .split(|b| *b == 0)
+            .filter(|f| !f.is_empty())
+            .map(String::from_utf8_lossy);
+
+        let header = fields.next()?;
+        let (action, devpath) = header.split_once('@')?;
+
+        let mut uevent = Uevent {
+            action: match action {
+                "add" => UeventAction::Add,
+                "remove" => UeventAction::Remove,
+                other => UeventAction::Other(other.to_string()),
+            },
+            devpath: devpath.to_string(),
+            subsystem: None,
+            devname: None,
+        };
+
+        for field in fields {
+            if let Some((key, value)) = field.split_once('=') {
+                match key {
+                    "SUBSYSTEM" => uevent.subsystem = Some(value.to_string()),
+                    "DEVNAME" => uevent.devname = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Some(uevent)
+    }
+
+    /// Returns the /dev path of the device node this uevent refers to.
+    ///
+    /// Falls back to the last component of the devpath when the message carries
+    /// no DEVNAME, which matches how the kernel names tty nodes.
+    pub fn port_name(&self) -> Option<String> {
+        let name = match &self.devname {
+            Some(devname) => devname.as_str(),
+            None => self.devpath.rsplit('/').next()?,
+        };
+        if name.is_empty() {
+            return None;
+        }
+        if name.starts_with('/') {
+            Some(name.to_string())
+        } else {
+            Some(format!("/dev/{}", name))
+        }
+    }
+
+    /// Converts this uevent into a PortEvent if it describes a serial port
+    /// appearing or disappearing.
+    ///
+    /// The event only carries the port name; use port_info() to look up the
+    /// rest of the metadata while the device is still present.
+    /// Virtual ttys (consoles, ptys) live under /devices/virtual and are ignored.
+    pub fn port_event(&self) -> Option<PortEvent> {
+        if self.subsystem.as_deref() != Some("tty") || self.devpath.contains("/virtual/") {
+            return None;
+        }
+        let port_name = self.port_name()?;
+        match self.action {
+            UeventAction::Add => Some(PortEvent::Added(PortInfo::new(port_name))),
+            UeventAction::Remove => Some(PortEvent::Removed(PortInfo::new(port_name))),
+            UeventAction::Other(_) => None,
+        }
+    }
+
+    /// Reads the port metadata for this device from sysfs.
+    ///
+    /// Walks up from the tty device to the enclosing USB device, if any, and reads
+    /// its descriptor attributes. Missing attributes are left as None, so this
+    /// returns a bare PortInfo when the device has already disappeared.
+    pub fn port_info(&self) -> PortInfo {
+        let mut info = PortInfo::new(self.port_name().unwrap_or_default());
+        let device = Path::new("/sys").join(self.devpath.trim_start_matches('/'));
+
+        if let Some(usb) = device.ancestors().find(|dir| dir.join("idVendor").is_file()) {
+            let read = |attr: &str| {
+                fs::read_to_string(usb.join(attr))
+                    .ok()
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            let read_id = |attr: &str| read(attr).and_then(|value| u16::from_str_radix(&value, 16).ok());
+
+            info.port_type = PortType::Usb;
+            info.vid = read_id("idVendor");
+            info.pid = read_id("idProduct");
+            info.serial_number = read("serial");
+            info.manufacturer = read("manufacturer");
+            info.product = read("product");
+            info.location = usb.file_name().map(|name| name.to_string_lossy().into_owned());
+        } else if self.devpath.contains("/usb") {
+            info.port_type = PortType::Usb;
+        } else if self.devpath.contains("/pci") {
+            info.port_type = PortType::Pci;
+        }
+
+        info
+    }
+}
+
+/// A source of raw uevent messages.
+///
+/// Implemented by the netlink socket on Linux and by InjectedUeventSource for tests.
+pub trait UeventSource: Send {
+    /// Waits up to `timeout` for the next raw message.
+    ///
+    /// Returns Ok(None) if no message arrived in time, and an error if the source
+    /// has failed or been closed and should no longer be used.
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// A UeventSource fed from an in-process channel.
+///
+/// Used to drive the hotplug backend with synthetic messages in tests.
+pub struct InjectedUeventSource {
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl InjectedUeventSource {
+    /// Creates a new source and the sender used to inject messages into it.
+    ///
+    /// Dropping the sender closes the source.
+    pub fn channel() -> (Sender<Vec<u8>>, Self) {
+        let (sender, receiver) = mpsc::channel();
+        (sender, InjectedUeventSource { receiver })
+    }
+}
+
+impl UeventSource for InjectedUeventSource {
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "uevent injector was dropped",
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use netlink::NetlinkUeventSource;
+
+#[cfg(target_os = "linux")]
+mod netlink {
+    use std::{io, mem, os::fd::{AsRawFd, FromRawFd, OwnedFd}, time::Duration};
+    use super::UeventSource;
+
+    /// Multicast group the kernel broadcasts uevents on
+    const KERNEL_UEVENT_GROUP: u32 = 1;
+
+    /// Large enough for any uevent the kernel will send
+    const UEVENT_BUFFER_SIZE: usize = 8192;
+
+    /// A UeventSource reading kernel uevents from a NETLINK_KOBJECT_UEVENT socket.
+    pub struct NetlinkUeventSource {
+        fd: OwnedFd,
+        buffer: Vec<u8>,
+    }
+
+    impl NetlinkUeventSource {
+        /// Opens and binds a netlink socket to the kernel uevent multicast group.
+        ///
+        /// Fails if netlink is unavailable, e.g. in restricted sandboxes.
+        pub fn open() -> io::Result<Self> {
+            // SAFETY: plain socket(2) call, the returned descriptor is owned immediately.
+            let raw = unsafe {
+                libc::socket(
+                    libc::AF_NETLINK,
+                    libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                    libc::NETLINK_KOBJECT_UEVENT,
+                )
+            };
+            if raw < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: raw is a freshly created, valid descriptor nobody else owns.
+            let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+            // SAFETY: sockaddr_nl is plain old data, all-zero is a valid value.
+            let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = KERNEL_UEVENT_GROUP;
+
+            // SAFETY: addr is a valid sockaddr_nl and the length matches its size.
+            let result = unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                )
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(NetlinkUeventSource {
+                fd,
+                buffer: vec![0; UEVENT_BUFFER_SIZE],
+            })
+        }
+    }
+
+    impl AsRawFd for NetlinkUeventSource {
+        fn as_raw_fd(&self) -> std::os::fd::RawFd {
+            self.fd.as_raw_fd()
+        }
+    }
+
+    impl UeventSource for NetlinkUeventSource {
+        fn recv(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as libc::c_int;
+
+            // SAFETY: pollfd points to a single valid pollfd structure.
+            let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                return if err.kind() == io::ErrorKind::Interrupted { Ok(None) } else { Err(err) };
+            }
+            if ready == 0 {
+                return Ok(None);
+            }
+
+            // SAFETY: buffer is valid for writes of buffer.len() bytes.
+            let len = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    self.buffer.as_mut_ptr() as *mut libc::c_void,
+                    self.buffer.len(),
+                    0,
+                )
+            };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                return match err.kind() {
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => Ok(None),
+                    // The kernel dropped messages because we were too slow; keep listening
+                    _ if err.raw_os_error() == Some(libc::ENOBUFS) => Ok(None),
+                    _ => Err(err),
+                };
+            }
+
+            Ok(Some(self.buffer[..len as usize].to_vec()))
+        }
+    }
+}
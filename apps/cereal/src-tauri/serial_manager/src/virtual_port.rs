@@ -0,0 +1,133 @@
+//! # Virtual Ports
+//!
+//! A virtual port is a pseudo terminal standing in for a serial device, so that
+//! applications and firmware tooling can be tested without hardware. The slave
+//! side is an ordinary tty such as /dev/pts/3, which sessions open with the
+//! usual API. The master side is the device: a VirtualDevice holds it, and what
+//! is written to one side is read from the other.
+//!
+//! Virtual ports take part in discovery like real ones. Creating one is
+//! reported as SerialEvent::DeviceArrived and dropping its VirtualDevice as
+//! SerialEvent::DeviceDeparted, and the port filter applies to them.
+//!
+//! ## Identity
+//!
+//! Every virtual port has a name, which becomes its product string and, as
+//! the location "virtual:<name>", its DeviceIdentity. A virtual port created
+//! again under the same name is therefore the same device to discovery and to
+//! sessions: its departure waits out the settle window, and a session with
+//! auto-reconnect turned on follows it to its new pseudo terminal.
+//!
+//! ## Limitations
+//!
+//! Pseudo terminals ignore line settings such as the baud rate and have no
+//! modem control lines, so setting DTR/RTS or reading CTS and friends fails
+//! with SerialManagerError::Io. Breaks do work. Only available on Unix.
+//!
+//! ```rust ignore
+//! let mut device = manager.create_virtual_port("mcu-a")?;
+//! let session = manager.open(device.port_name(), PortConfig::default())?;
+//! device.write_all(b"booted\r\n")?;   // published as DataReceived on the session
+//! session.write(b"status\r\n")?;      // read back from the device
+//! drop(device);                        // published as DeviceDeparted
+//! ```
+
+use std::{
+    io::{self, Read, Write},
+    time::Duration,
+};
+use serialport::{SerialPort, TTYPort};
+use crate::discovery::{DiscoveryService, VirtualPorts};
+use crate::error::{Result, SerialManagerError};
+use crate::port_info::{PortInfo, PortType};
+
+/// How long reads from the device end wait for data by default
+const DEFAULT_DEVICE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The device end of a virtual port
+///
+/// Reading returns what sessions wrote to the port, writing makes data
+/// arrive on the port. Dropping it removes the port, as if the device had
+/// been unplugged.
+pub struct VirtualDevice {
+    name: String,
+    port: PortInfo,
+    master: TTYPort,
+    /// Keeps the port's tty open while no session has it open, since the
+    /// device end fails with EIO whenever nobody holds it
+    _slave: TTYPort,
+    ports: VirtualPorts,
+}
+
+impl VirtualDevice {
+    /// Creates a pseudo terminal pair and adds its slave side to discovery.
+    pub(crate) fn create(name: &str, discovery: &DiscoveryService) -> Result<Self> {
+        let pty_error = |e: serialport::Error| SerialManagerError::from(io::Error::from(e));
+        let (mut master, slave) = TTYPort::pair().map_err(pty_error)?;
+        master.set_timeout(DEFAULT_DEVICE_TIMEOUT).map_err(pty_error)?;
+        let port_name = slave.name()
+            .ok_or_else(|| SerialManagerError::from(io::Error::other("pseudo terminal has no name")))?;
+
+        let port = PortInfo {
+            port_type: PortType::Virtual,
+            product: Some(name.to_string()),
+            location: Some(format!("virtual:{}", name)),
+            ..PortInfo::new(port_name)
+        };
+        discovery.add_virtual_port(port.clone())?;
+        log::info!("Created virtual port {} on {}", name, port.port_name);
+
+        Ok(VirtualDevice {
+            name: name.to_string(),
+            port,
+            master,
+            _slave: slave,
+            ports: discovery.virtual_ports(),
+        })
+    }
+
+    /// Returns the name the port was created with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the operating system name of the port, for opening a session on it.
+    pub fn port_name(&self) -> &str {
+        &self.port.port_name
+    }
+
+    /// Returns the port as discovery reports it.
+    pub fn port(&self) -> &PortInfo {
+        &self.port
+    }
+
+    /// Changes how long reads wait for data before failing with io::ErrorKind::TimedOut.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.master.set_timeout(timeout).map_err(|e| SerialManagerError::from(io::Error::from(e)))
+    }
+}
+
+impl Read for VirtualDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.master.read(buf)
+    }
+}
+
+impl Write for VirtualDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        // Like an unplugged device, sessions on the port see their reads fail
+        // and discovery reports the departure once the settle window is over
+        log::info!("Removing virtual port {} on {}", self.name, self.port.port_name);
+        self.ports.remove(&self.port.port_name);
+    }
+}
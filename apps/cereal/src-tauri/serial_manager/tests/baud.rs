@@ -0,0 +1,122 @@
+use std::time::Duration;
+use serial_manager::{rank_samples, BaudScore};
+
+/// Boot log of an ESP32 as captured at its real rate, 115200 baud
+const BOOT_LOG: &[u8] = b"ets Jun  8 2016 00:22:57\r\n\r\nrst:0x1 (POWERON_RESET),boot:0x13 (SPI_FAST_FLASH_BOOT)\r\n\
+configsip: 0, SPIWP:0xee\r\nclk_drv:0x00,q_drv:0x00,d_drv:0x00,cs0_drv:0x00,hd_drv:0x00,wp_drv:0x00\r\n\
+mode:DIO, clock div:2\r\nload:0x3fff0030,len:1344\r\nload:0x40078000,len:13964\r\nentry 0x40080400\r\n\
+I (29) boot: ESP-IDF v5.1.2 2nd stage bootloader\r\nI (29) boot: compile time Jan 10 2024 12:00:00\r\n";
+
+/// What a UART listening at `rx_baud` makes of `data` sent at `tx_baud` with
+/// 8N1 framing. Characters with a bad stop bit come out as NUL, as they do
+/// from the Linux tty layer.
+fn receive_at(data: &[u8], tx_baud: u32, rx_baud: u32) -> Vec<u8> {
+    // The line level over time, sampled at a rate both baud rates divide well into
+    const TICKS_PER_SECOND: u64 = 36_864_000;
+    let tx_bit = TICKS_PER_SECOND / tx_baud as u64;
+    let rx_bit = TICKS_PER_SECOND / rx_baud as u64;
+    let mut bits = Vec::new();
+    for &byte in data {
+        bits.push(false);
+        bits.extend((0..8).map(|bit| byte & (1 << bit) != 0));
+        bits.push(true);
+    }
+    let level = |tick: u64| bits.get((tick / tx_bit) as usize).copied().unwrap_or(true);
+    let end = bits.len() as u64 * tx_bit;
+
+    let mut received = Vec::new();
+    let mut tick = 0;
+    while tick < end {
+        if level(tick) {
+            tick += 1;
+            continue;
+        }
+        // Falling edge: sample the middle of every bit from here
+        let sample = |bit: u64| level(tick + rx_bit / 2 + bit * rx_bit);
+        let byte = (0..8).filter(|&bit| sample(bit + 1)).fold(0u8, |byte, bit| byte | (1 << bit));
+        received.push(if sample(9) { byte } else { 0 });
+        tick += 10 * rx_bit - rx_bit / 2;
+    }
+    received
+}
+
+#[test]
+fn clean_text_scores_high() {
+    let score = BaudScore::of(BOOT_LOG);
+    assert_eq!(score.printable_ratio, 1.0);
+    assert_eq!(score.error_ratio, 0.0);
+    assert!(score.newline_regularity > 0.95, "{:?}", score);
+    assert!(score.confidence() > 0.9);
+}
+
+#[test]
+fn simulated_uart_receives_matching_rate_exactly() {
+    assert_eq!(receive_at(BOOT_LOG, 115_200, 115_200), BOOT_LOG);
+}
+
+#[test]
+fn wrong_rates_score_low() {
+    for rx_baud in [9_600, 57_600, 230_400, 460_800] {
+        let garbage = receive_at(BOOT_LOG, 115_200, rx_baud);
+        let score = BaudScore::of(&garbage);
+        assert!(score.confidence() < 0.5, "{} baud scored {:?}", rx_baud, score);
+    }
+}
+
+#[test]
+fn samples_are_ranked_by_confidence() {
+    let candidates = [9_600, 57_600, 115_200, 230_400];
+    let samples: Vec<(u32, Vec<u8>)> = candidates
+        .iter()
+        .map(|&rx_baud| (rx_baud, receive_at(BOOT_LOG, 115_200, rx_baud)))
+        .collect();
+
+    let ranked = rank_samples(samples.iter().map(|(baud_rate, sample)| (*baud_rate, sample.as_slice())));
+    assert_eq!(ranked.len(), candidates.len());
+    assert_eq!(ranked[0].baud_rate, 115_200);
+    assert!(ranked[0].confidence > 2.0 * ranked[1].confidence, "{:?}", ranked);
+    assert!(ranked.windows(2).all(|pair| pair[0].confidence >= pair[1].confidence));
+}
+
+#[test]
+fn little_data_means_little_confidence() {
+    assert_eq!(BaudScore::of(b"").confidence(), 0.0);
+    assert!(BaudScore::of(b"ok\r\n").confidence() < 0.1);
+}
+
+#[test]
+fn utf8_text_is_printable() {
+    let score = BaudScore::of("Temperatur: 21,5 °C\n".as_bytes());
+    assert_eq!(score.printable_ratio, 1.0);
+    assert_eq!(score.error_ratio, 0.0);
+
+    // A multi-byte character cut off at the end of the sample is not an error
+    let cut = &"µs\n".as_bytes()[..1];
+    assert_eq!(BaudScore::of(cut).error_ratio, 0.0);
+}
+
+#[cfg(unix)]
+#[test]
+fn detect_baud_samples_every_candidate() {
+    use std::io::Write;
+    use serialport::{SerialPort, TTYPort};
+
+    // A pseudo terminal ignores the rate, so every candidate sees clean text
+    let (mut master, slave) = TTYPort::pair().unwrap();
+    let port_name = slave.name().unwrap();
+    let writer = std::thread::spawn(move || {
+        for _ in 0..40 {
+            if master.write_all(b"I (29) boot: ready\r\n").is_err() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    });
+
+    let ranked = serial_manager::detect_baud(&port_name, &[9_600, 115_200], Duration::from_millis(150)).unwrap();
+    assert_eq!(ranked.len(), 2);
+    assert!(ranked.iter().all(|candidate| candidate.score.bytes > 0));
+    assert!(ranked[0].score.printable_ratio > 0.9, "{:?}", ranked);
+    writer.join().unwrap();
+    drop(slave);
+}
@@ -0,0 +1,115 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use serial_manager::{EventBus, Overflow, PortInfo, RecvError, SerialEvent, TryRecvError, MAX_COALESCED_BYTES};
+
+fn arrived(port_name: &str) -> SerialEvent {
+    SerialEvent::DeviceArrived { port: PortInfo::new(port_name), timestamp: SystemTime::UNIX_EPOCH }
+}
+
+#[test]
+fn subscribers_receive_independently() {
+    let bus = EventBus::new();
+    let first = bus.subscribe(8, vec![arrived("/dev/ttyACM0")]);
+    bus.publish(arrived("/dev/ttyACM1"));
+    let second = bus.subscribe(8, vec![]);
+    bus.publish(arrived("/dev/ttyACM2"));
+
+    assert_eq!(first.try_recv(), Ok(arrived("/dev/ttyACM0")));
+    assert_eq!(first.try_recv(), Ok(arrived("/dev/ttyACM1")));
+    assert_eq!(first.try_recv(), Ok(arrived("/dev/ttyACM2")));
+    assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+
+    assert_eq!(second.try_recv(), Ok(arrived("/dev/ttyACM2")));
+    assert_eq!(second.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn slow_subscriber_is_told_how_much_it_missed() {
+    let bus = EventBus::new();
+    let slow = bus.subscribe(2, vec![]);
+    for i in 0..5 {
+        bus.publish(arrived(&format!("/dev/ttyACM{}", i)));
+    }
+
+    assert_eq!(slow.recv(), Err(RecvError::Lagged(3)));
+    assert_eq!(slow.recv(), Ok(arrived("/dev/ttyACM3")));
+    assert_eq!(slow.recv(), Ok(arrived("/dev/ttyACM4")));
+    assert_eq!(slow.recv_timeout(Duration::from_millis(10)), Ok(None));
+}
+
+#[test]
+fn dropping_the_bus_closes_receivers_after_draining() {
+    let bus = EventBus::new();
+    let receiver = bus.subscribe(8, vec![]);
+    bus.publish(arrived("/dev/ttyACM0"));
+    drop(bus);
+
+    assert_eq!(receiver.recv(), Ok(arrived("/dev/ttyACM0")));
+    assert_eq!(receiver.recv(), Err(RecvError::Closed));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn stream_wakes_on_publish_and_ends_when_bus_closes() {
+    use futures::StreamExt;
+
+    let bus = EventBus::new();
+    let mut events = bus.subscribe(8, vec![arrived("/dev/ttyACM0")]).into_stream();
+    assert_eq!(events.next().await, Some(arrived("/dev/ttyACM0")));
+
+    let publisher = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        bus.publish(arrived("/dev/ttyACM1"));
+    });
+    assert_eq!(events.next().await, Some(arrived("/dev/ttyACM1")));
+
+    publisher.await.unwrap();
+    assert_eq!(events.next().await, None);
+}
+
+fn data(session: u64, bytes: &[u8]) -> SerialEvent {
+    SerialEvent::DataReceived {
+        session: session.into(),
+        bytes: bytes.to_vec(),
+        host_timestamp: SystemTime::UNIX_EPOCH,
+        elapsed: Duration::ZERO,
+    }
+}
+
+#[test]
+fn full_queue_coalesces_data_from_the_same_session() {
+    let bus = EventBus::new();
+    let overflows = Arc::new(Mutex::new(Vec::new()));
+    let recorded = overflows.clone();
+    bus.set_overflow_handler(Box::new(move |event, overflow| recorded.lock().unwrap().push((event.clone(), overflow))));
+
+    let slow = bus.subscribe(2, vec![]);
+    bus.publish(data(1, b"ab"));
+    bus.publish(data(1, b"cd"));
+    bus.publish(data(1, b"ef"));
+    bus.publish(data(2, b"gh"));
+
+    // "ef" was appended to "cd"; "gh" is another session's, so the oldest event made room
+    assert_eq!(slow.recv(), Err(RecvError::Lagged(1)));
+    assert_eq!(slow.recv(), Ok(data(1, b"cdef")));
+    assert_eq!(slow.recv(), Ok(data(2, b"gh")));
+    assert_eq!(*overflows.lock().unwrap(), vec![
+        (data(1, b"ef"), Overflow::Coalesced),
+        (data(1, b"ab"), Overflow::Dropped),
+    ]);
+}
+
+#[test]
+fn coalescing_stops_at_the_size_limit() {
+    let bus = EventBus::new();
+    let slow = bus.subscribe(1, vec![]);
+    let half = vec![0; MAX_COALESCED_BYTES / 2];
+    bus.publish(data(1, &half));
+    bus.publish(data(1, &half));
+    bus.publish(data(1, b"x"));
+
+    assert_eq!(slow.recv(), Err(RecvError::Lagged(1)));
+    assert_eq!(slow.recv(), Ok(data(1, b"x")));
+}
@@ -0,0 +1,217 @@
+use std::{
+    io::{Cursor, ErrorKind},
+    time::{Duration, SystemTime},
+};
+use serial_manager::{
+    CaptureHeader, CaptureReader, CaptureRecord, CaptureWriter, Direction, Parity, PortConfig, SerialManagerError,
+};
+
+fn header() -> CaptureHeader {
+    CaptureHeader {
+        port_name: "/dev/ttyUSB0".to_string(),
+        config: PortConfig { baud_rate: 9600, parity: Parity::Even, ..Default::default() },
+        started_at: SystemTime::UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789),
+    }
+}
+
+/// A capture of a device that boots and answers a command
+fn capture() -> Vec<u8> {
+    let mut writer = CaptureWriter::new(Vec::new(), &header()).unwrap();
+    writer.write_record(Direction::Rx, Duration::from_micros(1500), b"boot\r\n").unwrap();
+    writer.write_record(Direction::Tx, Duration::from_millis(20), b"version\r").unwrap();
+    writer.write_record(Direction::Rx, Duration::from_millis(25), b"").unwrap();
+    writer.write_record(Direction::Rx, Duration::from_millis(31), b"1.2.3\r\n").unwrap();
+    writer.finish().unwrap()
+}
+
+#[test]
+fn captures_round_trip() {
+    let mut reader = CaptureReader::new(Cursor::new(capture())).unwrap();
+    assert_eq!(reader.header(), &header());
+
+    let records: Vec<CaptureRecord> = (&mut reader).collect::<Result<_, _>>().unwrap();
+    let summary: Vec<(Direction, Duration, &[u8])> = records
+        .iter()
+        .map(|record| (record.direction, record.elapsed, record.bytes.as_slice()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Direction::Rx, Duration::from_micros(1500), &b"boot\r\n"[..]),
+            (Direction::Tx, Duration::from_millis(20), &b"version\r"[..]),
+            (Direction::Rx, Duration::from_millis(25), &b""[..]),
+            (Direction::Rx, Duration::from_millis(31), &b"1.2.3\r\n"[..]),
+        ]
+    );
+    assert_eq!(reader.next_record().unwrap(), None);
+}
+
+#[test]
+fn truncated_captures_keep_their_complete_records() {
+    let mut bytes = capture();
+    bytes.truncate(bytes.len() - 3);
+
+    let records: Vec<_> = CaptureReader::new(Cursor::new(bytes)).unwrap().collect();
+    assert_eq!(records.len(), 4);
+    assert!(records[..3].iter().all(Result::is_ok));
+    assert!(matches!(&records[3], Err(SerialManagerError::Io { kind: ErrorKind::UnexpectedEof, .. })));
+}
+
+#[test]
+fn other_files_are_rejected() {
+    let result = CaptureReader::new(Cursor::new(b"PK\x03\x04 not a capture at all".to_vec()));
+    assert!(matches!(result, Err(SerialManagerError::Io { kind: ErrorKind::InvalidData, .. })));
+
+    let mut future = capture();
+    future[4] = 2;
+    let result = CaptureReader::new(Cursor::new(future));
+    assert!(matches!(result, Err(SerialManagerError::Io { kind: ErrorKind::InvalidData, .. })));
+}
+
+#[cfg(unix)]
+mod sessions {
+    use std::{
+        io::{Read, Write},
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+    use serial_manager::{
+        CaptureHeader, CaptureReader, CaptureWriter, Direction, EventReceiver, PortConfig, PortFilter, ReplayTiming,
+        SerialEvent, SerialManager,
+    };
+
+    /// A capture file in the temporary directory, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("serial_manager-{}-{}.smcp", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Collects the data received by any session until `count` bytes arrived,
+    /// with when each chunk of it was read
+    fn receive(events: &EventReceiver, count: usize) -> (Vec<u8>, Vec<Duration>) {
+        let mut bytes = Vec::new();
+        let mut stamps = Vec::new();
+        while bytes.len() < count {
+            match events.recv_timeout(Duration::from_secs(3)) {
+                Ok(Some(SerialEvent::DataReceived { bytes: chunk, elapsed, .. })) => {
+                    bytes.extend(chunk);
+                    stamps.push(elapsed);
+                }
+                Ok(Some(_)) => {}
+                other => panic!("only received {:?}: {:?}", String::from_utf8_lossy(&bytes), other),
+            }
+        }
+        (bytes, stamps)
+    }
+
+    #[test]
+    fn sessions_capture_what_they_read_and_write() {
+        let file = TempFile::new("session");
+        let manager = SerialManager::new(PortFilter::allow_all());
+        let events = manager.subscribe().unwrap();
+        let mut device = manager.create_virtual_port("capture-device").unwrap();
+        let config = PortConfig { baud_rate: 57_600, ..Default::default() };
+        let session = manager.open(device.port_name(), config).unwrap();
+
+        device.write_all(b"before").unwrap();
+        receive(&events, 6);
+        session.start_capture(&file.0).unwrap();
+        assert!(session.is_capturing());
+        device.write_all(b"hello").unwrap();
+        receive(&events, 5);
+        session.write(b"cmd").unwrap();
+        let mut command = [0; 3];
+        device.read_exact(&mut command).unwrap();
+        session.stop_capture().unwrap();
+        assert!(!session.is_capturing());
+        session.write(b"after").unwrap();
+
+        let mut capture = CaptureReader::open(&file.0).unwrap();
+        assert_eq!(capture.header().port_name, device.port_name());
+        assert_eq!(capture.header().config.baud_rate, 57_600);
+        let records: Vec<_> = (&mut capture).collect::<Result<_, _>>().unwrap();
+        let received: Vec<u8> = records
+            .iter()
+            .filter(|record| record.direction == Direction::Rx)
+            .flat_map(|record| record.bytes.clone())
+            .collect();
+        assert_eq!(received, b"hello");
+        let sent = records.last().unwrap();
+        assert_eq!((sent.direction, sent.bytes.as_slice()), (Direction::Tx, &b"cmd"[..]));
+        assert!(records.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+    }
+
+    #[test]
+    fn closing_a_session_finishes_its_capture() {
+        let file = TempFile::new("close");
+        let manager = SerialManager::new(PortFilter::allow_all());
+        let device = manager.create_virtual_port("closing-device").unwrap();
+        let session = manager.open(device.port_name(), PortConfig::default()).unwrap();
+        session.start_capture(&file.0).unwrap();
+        session.write(b"bye").unwrap();
+        session.close();
+
+        assert!(!session.is_capturing());
+        let records: Vec<_> = CaptureReader::open(&file.0).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].bytes, b"bye");
+        assert!(session.start_capture(&file.0).is_err());
+    }
+
+    /// Replays two chunks captured 300ms apart and returns how far apart they arrived
+    fn replay_gap(name: &str, timing: ReplayTiming) -> Duration {
+        let header = CaptureHeader {
+            port_name: "/dev/ttyACM0".to_string(),
+            config: PortConfig::default(),
+            started_at: SystemTime::now(),
+        };
+        let mut writer = CaptureWriter::new(Vec::new(), &header).unwrap();
+        writer.write_record(Direction::Rx, Duration::ZERO, b"first").unwrap();
+        writer.write_record(Direction::Tx, Duration::from_millis(100), b"ignored").unwrap();
+        writer.write_record(Direction::Rx, Duration::from_millis(300), b"second").unwrap();
+        let capture = CaptureReader::new(std::io::Cursor::new(writer.finish().unwrap())).unwrap();
+
+        let manager = SerialManager::new(PortFilter::allow_all());
+        let events = manager.subscribe().unwrap();
+        let replay = manager.replay_capture(name, capture, timing).unwrap();
+        assert_eq!(replay.header().port_name, "/dev/ttyACM0");
+        let session = manager.open(replay.port_name(), replay.header().config.clone()).unwrap();
+        session.write(b"the application talks too").unwrap();
+
+        replay.start();
+        let (received, stamps) = receive(&events, 11);
+        assert_eq!(received, b"firstsecond");
+        assert_eq!(replay.wait().unwrap(), 11);
+        assert!(replay.is_finished());
+
+        // The port stays after the replay finished, until it is dropped
+        assert!(manager.get_ports().unwrap().iter().any(|port| port.port_name == replay.port_name()));
+        stamps[stamps.len() - 1] - stamps[0]
+    }
+
+    #[test]
+    fn replays_keep_the_original_timing() {
+        let gap = replay_gap("replay-original", ReplayTiming::Original);
+        assert!(gap >= Duration::from_millis(280), "{:?}", gap);
+        assert!(gap < Duration::from_millis(600), "{:?}", gap);
+    }
+
+    #[test]
+    fn replays_can_be_sped_up() {
+        let gap = replay_gap("replay-scaled", ReplayTiming::Scaled(10.0));
+        assert!(gap >= Duration::from_millis(20), "{:?}", gap);
+        assert!(gap < Duration::from_millis(200), "{:?}", gap);
+
+        let gap = replay_gap("replay-fast", ReplayTiming::AsFastAsPossible);
+        assert!(gap < Duration::from_millis(20), "{:?}", gap);
+    }
+}
@@ -0,0 +1,36 @@
+use std::{collections::HashSet, time::Duration};
+use serial_manager::{ResetSequence, ResetStep};
+
+#[test]
+fn builtin_sequences_can_be_found_by_name() {
+    let builtin = ResetSequence::builtin();
+    let names: HashSet<&str> = builtin.iter().map(|sequence| sequence.name.as_str()).collect();
+    assert_eq!(names.len(), builtin.len(), "names must be unique");
+
+    for name in ["esp32-bootloader", "esp32-reset", "arduino-reset", "stm32-bootloader"] {
+        assert_eq!(ResetSequence::find(name).map(|sequence| sequence.name), Some(name.to_string()));
+    }
+    assert_eq!(ResetSequence::find("no-such-board"), None);
+}
+
+#[test]
+fn esp32_bootloader_entry_holds_io0_low_across_reset() {
+    let sequence = ResetSequence::find("esp32-bootloader").unwrap();
+
+    // EN is released (RTS off) while IO0 is held low (DTR on), then IO0 is released
+    let release_en = sequence.steps.iter().position(|step| *step == ResetStep::Rts(false)).unwrap();
+    let hold_io0 = sequence.steps.iter().position(|step| *step == ResetStep::Dtr(true)).unwrap();
+    assert!(hold_io0 < release_en);
+    assert_eq!(sequence.steps.last(), Some(&ResetStep::Dtr(false)));
+}
+
+#[test]
+fn sequences_round_trip_through_json() {
+    let sequence = ResetSequence {
+        name: "custom".to_string(),
+        description: "Pulse RTS".to_string(),
+        steps: vec![ResetStep::Rts(true), ResetStep::Wait(Duration::from_millis(10)), ResetStep::Rts(false)],
+    };
+    let json = serde_json::to_string(&sequence).unwrap();
+    assert_eq!(serde_json::from_str::<ResetSequence>(&json).unwrap(), sequence);
+}
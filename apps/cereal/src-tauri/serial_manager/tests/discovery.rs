@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex, OnceLock, Weak},
+    time::Duration,
+};
+use serial_manager::{
+    DeviceIdentity, DiscoveryConfig, DiscoveryService, MockClock, MockEnumerator, PortEvent, PortFilter, PortInfo, PortMatcher, PortType,
+    SerialManagerError,
+};
+
+/// Discovery driven by a scripted enumerator and a clock that only moves when told to
+struct Harness {
+    discovery: DiscoveryService,
+    known_ports: Arc<Mutex<HashMap<String, PortInfo>>>,
+    events: mpsc::Receiver<PortEvent>,
+    clock: MockClock,
+    config: DiscoveryConfig,
+}
+
+impl Harness {
+    fn start(enumerator: &MockEnumerator, filter: PortFilter, config: DiscoveryConfig) -> Self {
+        let known_ports = Arc::new(Mutex::new(HashMap::new()));
+        let (event_sender, events) = mpsc::channel();
+        let clock = MockClock::new();
+
+        let discovery = DiscoveryService::spawn_with_enumerator(
+            known_ports.clone(),
+            filter,
+            config.clone(),
+            Box::new(move |event| {
+                let _ = event_sender.send(event);
+            }),
+            Box::new(enumerator.clone()),
+            Arc::new(clock.clone()),
+        );
+
+        // Let the initial scan (tick 0) complete
+        clock.advance(Duration::ZERO);
+        Harness { discovery, known_ports, events, clock, config }
+    }
+
+    /// Runs discovery up to and including the next scan.
+    fn next_scan(&self) -> Vec<PortEvent> {
+        self.clock.advance(self.config.poll_interval);
+        self.events.try_iter().collect()
+    }
+
+    fn known(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.known_ports.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+fn usb_port(port_name: &str, serial: &str) -> PortInfo {
+    PortInfo {
+        port_type: PortType::Usb,
+        vid: Some(0x0483),
+        pid: Some(0x5740),
+        serial_number: Some(serial.to_string()),
+        ..PortInfo::new(port_name)
+    }
+}
+
+#[test]
+fn reports_arrival_and_departure() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, PortInfo::new("/dev/ttyS0"))
+        .add_at(2, PortInfo::new("/dev/ttyS1"))
+        .remove_at(3, "/dev/ttyS0");
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+
+    assert_eq!(harness.events.try_iter().collect::<Vec<_>>(), vec![PortEvent::Added(PortInfo::new("/dev/ttyS0"))]);
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.next_scan(), vec![PortEvent::Added(PortInfo::new("/dev/ttyS1"))]);
+    assert_eq!(harness.next_scan(), vec![PortEvent::Removed(PortInfo::new("/dev/ttyS0"))]);
+    assert_eq!(harness.known(), vec!["/dev/ttyS1"]);
+    assert_eq!(enumerator.ticks(), 4);
+}
+
+#[test]
+fn renamed_device_is_reported_as_moved() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, usb_port("/dev/ttyACM0", "205C3A8B4E53"))
+        .remove_at(1, "/dev/ttyACM0")
+        .add_at(1, usb_port("/dev/ttyACM1", "205C3A8B4E53"));
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+    harness.events.try_iter().for_each(drop);
+
+    match &harness.next_scan()[..] {
+        [PortEvent::Moved { from, to, .. }] => {
+            assert_eq!(from.port_name, "/dev/ttyACM0");
+            assert_eq!(to.port_name, "/dev/ttyACM1");
+        }
+        other => panic!("unexpected events {:?}", other),
+    }
+    assert_eq!(harness.known(), vec!["/dev/ttyACM1"]);
+}
+
+#[test]
+fn by_id_link_resolved_late_still_identifies_the_device() {
+    // No serial number, so the by-id link outranks the location once udev has made it
+    let adapter = PortInfo {
+        port_type: PortType::Usb,
+        vid: Some(0x1a86),
+        pid: Some(0x7523),
+        location: Some("1-2".to_string()),
+        ..PortInfo::new("/dev/ttyUSB0")
+    };
+    let linked = |port_name: &str| PortInfo {
+        by_id: Some("/dev/serial/by-id/usb-1a86_USB_Serial-if00-port0".to_string()),
+        ..PortInfo { port_name: port_name.to_string(), ..adapter.clone() }
+    };
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, adapter.clone())
+        .add_at(1, linked("/dev/ttyUSB0"))
+        .remove_at(2, "/dev/ttyUSB0")
+        .add_at(2, linked("/dev/ttyUSB1"));
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+    assert_eq!(harness.events.try_iter().collect::<Vec<_>>(), vec![PortEvent::Added(adapter.clone())]);
+
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.known_ports.lock().unwrap()["/dev/ttyUSB0"], linked("/dev/ttyUSB0"));
+
+    match &harness.next_scan()[..] {
+        [PortEvent::Moved { from, to, identity }] => {
+            assert_eq!(from.port_name, "/dev/ttyUSB0");
+            assert_eq!(to.port_name, "/dev/ttyUSB1");
+            assert_eq!(*identity, DeviceIdentity::ById("/dev/serial/by-id/usb-1a86_USB_Serial-if00-port0".to_string()));
+        }
+        other => panic!("unexpected events {:?}", other),
+    }
+}
+
+#[test]
+fn identified_device_settles_before_removal() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, usb_port("/dev/ttyACM0", "205C3A8B4E53"))
+        .remove_at(1, "/dev/ttyACM0");
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+    harness.events.try_iter().for_each(drop);
+
+    // Still within the settle window right after the scan that lost it
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.known(), vec!["/dev/ttyACM0"]);
+
+    harness.clock.advance(harness.config.settle_window);
+    let events: Vec<PortEvent> = harness.events.try_iter().collect();
+    assert_eq!(events, vec![PortEvent::Removed(usb_port("/dev/ttyACM0", "205C3A8B4E53"))]);
+    assert!(harness.known().is_empty());
+}
+
+#[test]
+fn brief_dropout_is_reported_as_reenumeration() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, usb_port("/dev/ttyACM0", "205C3A8B4E53"))
+        .remove_at(1, "/dev/ttyACM0")
+        .add_at(2, usb_port("/dev/ttyACM0", "205C3A8B4E53"));
+    // The device is missed by one scan, so it must be back within one poll interval
+    let config = DiscoveryConfig { settle_window: Duration::from_secs(5), ..Default::default() };
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), config);
+    harness.events.try_iter().for_each(drop);
+
+    assert_eq!(harness.next_scan(), vec![]);
+    match &harness.next_scan()[..] {
+        [PortEvent::Reenumerated { port, stability, .. }] => {
+            assert_eq!(port.port_name, "/dev/ttyACM0");
+            assert_eq!(stability.reenumerations, 1);
+        }
+        other => panic!("unexpected events {:?}", other),
+    }
+}
+
+#[test]
+fn filter_hides_ports_until_changed() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, PortInfo::new("/dev/ttyS0"))
+        .add_at(1, usb_port("/dev/ttyACM0", "205C3A8B4E53"));
+    let only_usb = PortFilter {
+        include: vec![PortMatcher { vid: Some(0x0483), ..Default::default() }],
+        exclude: vec![],
+    };
+    let harness = Harness::start(&enumerator, only_usb, DiscoveryConfig::default());
+
+    assert_eq!(harness.events.try_iter().count(), 0);
+    assert_eq!(harness.next_scan(), vec![PortEvent::Added(usb_port("/dev/ttyACM0", "205C3A8B4E53"))]);
+
+    harness.discovery.set_filter(PortFilter::allow_all());
+    let events: Vec<PortEvent> = harness.events.try_iter().collect();
+    assert_eq!(events, vec![PortEvent::Added(PortInfo::new("/dev/ttyS0"))]);
+    assert_eq!(harness.known(), vec!["/dev/ttyACM0", "/dev/ttyS0"]);
+}
+
+#[test]
+fn callout_node_is_preferred_over_dialin_node() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, PortInfo::new("/dev/tty.usbmodem1101"))
+        .add_at(0, PortInfo::new("/dev/cu.usbmodem1101"))
+        .add_at(0, PortInfo::new("/dev/tty.Bluetooth-Incoming-Port"));
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+
+    assert_eq!(harness.known(), vec!["/dev/cu.usbmodem1101", "/dev/tty.Bluetooth-Incoming-Port"]);
+}
+
+#[test]
+fn failed_enumeration_keeps_known_ports() {
+    let enumerator = MockEnumerator::new();
+    enumerator
+        .add_at(0, PortInfo::new("/dev/ttyS0"))
+        .fail_at(1)
+        .fail_at(2);
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+    harness.events.try_iter().for_each(drop);
+
+    // The failure is reported once, not on every scan, and nothing is removed
+    assert!(matches!(&harness.next_scan()[..], [PortEvent::Error(SerialManagerError::Enumeration(_))]));
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.known(), vec!["/dev/ttyS0"]);
+}
+
+#[test]
+fn paused_discovery_does_not_scan_until_resumed() {
+    let enumerator = MockEnumerator::new();
+    enumerator.add_at(1, PortInfo::new("/dev/ttyS0"));
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+
+    harness.discovery.pause();
+    assert!(harness.discovery.is_paused());
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(harness.next_scan(), vec![]);
+    assert_eq!(enumerator.ticks(), 1);
+
+    // Resuming catches up straight away, without waiting for the interval
+    harness.discovery.resume();
+    harness.clock.advance(Duration::ZERO);
+    assert_eq!(harness.events.try_iter().collect::<Vec<_>>(), vec![PortEvent::Added(PortInfo::new("/dev/ttyS0"))]);
+    assert_eq!(enumerator.ticks(), 2);
+}
+
+#[test]
+fn rescan_now_and_interval_changes_take_effect_immediately() {
+    let enumerator = MockEnumerator::new();
+    enumerator.add_at(1, PortInfo::new("/dev/ttyS0"));
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+
+    harness.discovery.rescan_now();
+    harness.clock.advance(Duration::ZERO);
+    assert_eq!(harness.events.try_iter().collect::<Vec<_>>(), vec![PortEvent::Added(PortInfo::new("/dev/ttyS0"))]);
+    assert_eq!(enumerator.ticks(), 2);
+
+    harness.discovery.set_scan_interval(Duration::from_millis(500));
+    assert_eq!(harness.discovery.scan_interval(), Duration::from_millis(500));
+    harness.clock.advance(Duration::from_secs(1));
+    assert_eq!(enumerator.ticks(), 4);
+}
+
+#[test]
+fn callback_can_call_back_into_discovery() {
+    let enumerator = MockEnumerator::new();
+    enumerator.add_at(1, PortInfo::new("/dev/ttyS0"));
+    let known_ports = Arc::new(Mutex::new(HashMap::new()));
+    let service: Arc<OnceLock<Weak<DiscoveryService>>> = Arc::new(OnceLock::new());
+    let (sender, seen) = mpsc::channel();
+    let clock = MockClock::new();
+
+    let (callback_ports, callback_service) = (known_ports.clone(), service.clone());
+    let discovery = Arc::new(DiscoveryService::spawn_with_enumerator(
+        known_ports,
+        PortFilter::allow_all(),
+        DiscoveryConfig::default(),
+        Box::new(move |_| {
+            // Both need locks that discovery holds while it works out the events
+            let known = callback_ports.lock().unwrap().len();
+            let filter = callback_service.get().and_then(Weak::upgrade).map(|discovery| discovery.filter());
+            let _ = sender.send((known, filter.is_some()));
+        }),
+        Box::new(enumerator.clone()),
+        Arc::new(clock.clone()),
+    ));
+    service.set(Arc::downgrade(&discovery)).unwrap();
+    clock.advance(Duration::ZERO);
+
+    discovery.rescan_now();
+    clock.advance(Duration::ZERO);
+    assert_eq!(seen.recv_timeout(Duration::from_secs(1)), Ok((1, true)));
+}
+
+#[test]
+fn shutdown_and_join_reports_clean_exit() {
+    let enumerator = MockEnumerator::new();
+    let harness = Harness::start(&enumerator, PortFilter::allow_all(), DiscoveryConfig::default());
+
+    assert!(harness.discovery.shutdown_and_join(Duration::from_secs(1)));
+    // Joining again just reports how the worker exited
+    assert!(harness.discovery.shutdown_and_join(Duration::ZERO));
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use serial_manager::{DiscoveryConfig, DiscoveryService, InjectedUeventSource, PortEvent, PortFilter, PortInfo, PortMatcher, PortType};
+
+fn usb_port(port_name: &str, vid: u16, pid: u16, serial: &str, product: &str) -> PortInfo {
+    PortInfo {
+        port_type: PortType::Usb,
+        vid: Some(vid),
+        pid: Some(pid),
+        serial_number: Some(serial.to_string()),
+        manufacturer: Some("STMicroelectronics".to_string()),
+        product: Some(product.to_string()),
+        ..PortInfo::new(port_name)
+    }
+}
+
+#[test]
+fn matcher_criteria_must_all_hold() {
+    let port = usb_port("/dev/ttyACM0", 0x0483, 0x5740, "205C3A8B4E53", "STM32 Virtual ComPort");
+
+    assert!(PortMatcher::default().matches(&port));
+    assert!(PortMatcher { vid: Some(0x0483), pid: Some(0x5740), ..Default::default() }.matches(&port));
+    assert!(!PortMatcher { vid: Some(0x0483), pid: Some(0x374b), ..Default::default() }.matches(&port));
+    assert!(PortMatcher { serial_number: Some("205C*".into()), ..Default::default() }.matches(&port));
+    assert!(PortMatcher { manufacturer: Some("stmicro".into()), product: Some("ComPort".into()), ..Default::default() }.matches(&port));
+    assert!(PortMatcher { path: Some("/dev/ttyACM?".into()), ..Default::default() }.matches(&port));
+    assert!(!PortMatcher { path: Some("/dev/ttyUSB*".into()), ..Default::default() }.matches(&port));
+
+    // USB criteria never match ports without USB metadata
+    assert!(!PortMatcher { vid: Some(0x0483), ..Default::default() }.matches(&PortInfo::new("/dev/ttyS0")));
+
+    let by_id = "/dev/serial/by-id/usb-STMicroelectronics_STM32_205C3A8B4E53-if00";
+    let linked = PortInfo { by_id: Some(by_id.to_string()), ..port.clone() };
+    let matcher = PortMatcher { by_id: Some("*STM32_205C*".into()), ..Default::default() };
+    assert!(matcher.matches(&linked));
+    assert!(!matcher.matches(&port));
+}
+
+#[test]
+fn exclude_overrides_include() {
+    let filter = PortFilter {
+        include: vec![PortMatcher { vid: Some(0x0483), ..Default::default() }],
+        exclude: vec![PortMatcher { serial_number: Some("DEAD*".into()), ..Default::default() }],
+    };
+
+    assert!(filter.accepts(&usb_port("/dev/ttyACM0", 0x0483, 0x5740, "205C3A8B4E53", "VCP")));
+    assert!(!filter.accepts(&usb_port("/dev/ttyACM1", 0x0483, 0x5740, "DEADBEEF", "VCP")));
+    assert!(!filter.accepts(&usb_port("/dev/ttyUSB0", 0x0403, 0x6001, "A50285BI", "FT232R")));
+    assert!(PortFilter::allow_all().accepts(&PortInfo::new("/dev/ttyS0")));
+}
+
+fn tty_add(name: &str) -> Vec<u8> {
+    format!("add@/devices/pci0000:00/usb1/1-2/tty/{name}\0ACTION=add\0SUBSYSTEM=tty\0DEVNAME={name}\0").into_bytes()
+}
+
+#[test]
+fn changing_filter_reports_delta() {
+    let known_ports = Arc::new(Mutex::new(HashMap::new()));
+    let (event_sender, events) = mpsc::channel();
+    let (injector, source) = InjectedUeventSource::channel();
+    let only_acm = PortFilter {
+        include: vec![PortMatcher { path: Some("/dev/ttyACM*".into()), ..Default::default() }],
+        exclude: vec![],
+    };
+
+    let discovery = DiscoveryService::spawn_with_uevent_source(
+        known_ports.clone(),
+        only_acm,
+        DiscoveryConfig::default(),
+        Box::new(move |event| {
+            let _ = event_sender.send(event);
+        }),
+        Box::new(source),
+    );
+
+    let timeout = Duration::from_secs(1);
+    injector.send(tty_add("ttyUSB0")).unwrap();
+    injector.send(tty_add("ttyACM0")).unwrap();
+    match events.recv_timeout(timeout).unwrap() {
+        PortEvent::Added(port) => assert_eq!(port.port_name, "/dev/ttyACM0"),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(!known_ports.lock().unwrap().contains_key("/dev/ttyUSB0"));
+
+    // Switching to a USB-only filter drops ACM0 and reveals the hidden USB0
+    discovery.set_filter(PortFilter {
+        include: vec![PortMatcher { path: Some("/dev/ttyUSB*".into()), ..Default::default() }],
+        exclude: vec![],
+    });
+    let mut delta: Vec<PortEvent> = events.try_iter().collect();
+    delta.sort_by_key(|e| matches!(e, PortEvent::Removed(_)));
+    assert!(matches!(&delta[..], [PortEvent::Added(a), PortEvent::Removed(r)]
+        if a.port_name == "/dev/ttyUSB0" && r.port_name == "/dev/ttyACM0"));
+
+    let known: Vec<String> = known_ports.lock().unwrap().keys().cloned().collect();
+    assert_eq!(known, vec!["/dev/ttyUSB0".to_string()]);
+}
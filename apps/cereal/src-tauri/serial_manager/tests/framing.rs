@@ -0,0 +1,200 @@
+use std::time::Duration;
+use serial_manager::{
+    crc16_x25, CobsFramer, FixedFramer, Frame, FrameStatus, Framer, FramingConfig, FramingKind, HdlcFramer, LengthPrefix,
+    LengthPrefixedFramer, LineEnding, LineFramer, SlipFramer,
+};
+
+/// Feeds the chunks to the framer one after another and collects the frames
+fn frames(framer: &mut dyn Framer, chunks: &[&[u8]]) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    for chunk in chunks {
+        framer.push(chunk, &mut frames);
+    }
+    frames
+}
+
+fn complete(bytes: &[u8]) -> Frame {
+    Frame::new(bytes.to_vec(), FrameStatus::Complete)
+}
+
+/// Encodes a payload the way an HDLC device would send it
+fn hdlc_encode(payload: &[u8]) -> Vec<u8> {
+    let mut raw = payload.to_vec();
+    raw.extend_from_slice(&crc16_x25(payload).to_le_bytes());
+    let mut encoded = vec![0x7E];
+    for byte in raw {
+        if byte == 0x7E || byte == 0x7D {
+            encoded.extend_from_slice(&[0x7D, byte ^ 0x20]);
+        } else {
+            encoded.push(byte);
+        }
+    }
+    encoded.push(0x7E);
+    encoded
+}
+
+#[test]
+fn lines_are_split_across_chunks() {
+    let mut framer = LineFramer::new(LineEnding::Lf, 64, None);
+    assert_eq!(
+        frames(&mut framer, &[b"boot", b"ing\nready\n\nv1", b".2"]),
+        vec![complete(b"booting"), complete(b"ready"), complete(b"")]
+    );
+    assert_eq!(framer.buffered(), 4);
+    assert_eq!(framer.flush(), Some(Frame::new(b"v1.2".to_vec(), FrameStatus::Flushed)));
+    assert_eq!(framer.flush(), None);
+}
+
+#[test]
+fn line_endings_are_configurable() {
+    let input: &[&[u8]] = &[b"a\r\nb\rc\nd\r", b"\ne"];
+
+    let mut any = LineFramer::new(LineEnding::Any, 64, None);
+    assert_eq!(frames(&mut any, input), vec![complete(b"a"), complete(b"b"), complete(b"c"), complete(b"d")]);
+
+    let mut crlf = LineFramer::new(LineEnding::CrLf, 64, None);
+    assert_eq!(frames(&mut crlf, input), vec![complete(b"a"), complete(b"b\rc\nd")]);
+
+    let mut cr = LineFramer::new(LineEnding::Cr, 64, None);
+    assert_eq!(frames(&mut cr, input), vec![complete(b"a"), complete(b"\nb"), complete(b"c\nd")]);
+}
+
+#[test]
+fn long_lines_are_cut_without_losing_bytes() {
+    let mut framer = LineFramer::new(LineEnding::Lf, 4, None);
+    assert_eq!(
+        frames(&mut framer, &[b"0123456789\n"]),
+        vec![
+            Frame::new(b"0123".to_vec(), FrameStatus::Truncated),
+            Frame::new(b"4567".to_vec(), FrameStatus::Truncated),
+            complete(b"89"),
+        ]
+    );
+}
+
+#[test]
+fn fixed_frames() {
+    let mut framer = FixedFramer::new(3, None);
+    assert_eq!(frames(&mut framer, &[b"abcd", b"ef", b"g"]), vec![complete(b"abc"), complete(b"def")]);
+    assert_eq!(framer.flush(), Some(Frame::new(b"g".to_vec(), FrameStatus::Flushed)));
+}
+
+#[test]
+fn length_prefixed_frames() {
+    let mut framer = LengthPrefixedFramer::new(LengthPrefix::U16Be, 64, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0x00], &[0x03, b'a', b'b'], &[b'c', 0x00, 0x00, 0x00, 0x01, b'z']]),
+        vec![complete(b"abc"), complete(b""), complete(b"z")]
+    );
+
+    let mut framer = LengthPrefixedFramer::new(LengthPrefix::U32Le, 64, None);
+    assert_eq!(frames(&mut framer, &[&[0x02, 0, 0, 0, 0xAA, 0xBB]]), vec![complete(&[0xAA, 0xBB])]);
+}
+
+#[test]
+fn oversized_length_prefixed_frame_is_skipped() {
+    let mut framer = LengthPrefixedFramer::new(LengthPrefix::U8, 2, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0x04, 1, 2, 3, 4, 0x01, 5]]),
+        vec![Frame::new(vec![1, 2], FrameStatus::Truncated), complete(&[5])]
+    );
+}
+
+#[test]
+fn slip_frames_are_unescaped() {
+    let mut framer = SlipFramer::new(64, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0xC0, 0x01, 0xDB, 0xDC, 0x02], &[0xDB, 0xDD, 0xC0, 0xC0, 0x03, 0xC0]]),
+        vec![complete(&[0x01, 0xC0, 0x02, 0xDB]), complete(&[0x03])]
+    );
+}
+
+#[test]
+fn slip_bad_escape_is_malformed() {
+    let mut framer = SlipFramer::new(64, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0x01, 0xDB, 0x02, 0xC0, 0x04, 0xC0]]),
+        vec![Frame::new(vec![0x01, 0x02], FrameStatus::Malformed), complete(&[0x04])]
+    );
+}
+
+#[test]
+fn cobs_frames_are_decoded() {
+    let mut framer = CobsFramer::new(64, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0x03, 0x11, 0x22, 0x02], &[0x33, 0x00, 0x00, 0x01, 0x01, 0x00]]),
+        vec![complete(&[0x11, 0x22, 0x00, 0x33]), complete(&[0x00])]
+    );
+
+    // A code pointing past the end of the frame
+    assert_eq!(
+        frames(&mut framer, &[&[0x05, 0x11, 0x00]]),
+        vec![Frame::new(vec![0x05, 0x11], FrameStatus::Malformed)]
+    );
+}
+
+#[test]
+fn cobs_long_runs_decode() {
+    let payload: Vec<u8> = (1..=254).collect();
+    let mut encoded = vec![0xFF];
+    encoded.extend_from_slice(&payload);
+    encoded.extend_from_slice(&[0x01, 0x00]);
+
+    let mut framer = CobsFramer::new(300, None);
+    assert_eq!(frames(&mut framer, &[&encoded]), vec![complete(&payload)]);
+}
+
+#[test]
+fn hdlc_checks_crc() {
+    assert_eq!(crc16_x25(b"123456789"), 0x906E);
+
+    let payload = [0xFF, 0x03, 0x7E, 0x7D, 0x42];
+    let mut encoded = hdlc_encode(&payload);
+    let mut framer = HdlcFramer::new(64, None);
+    let received = frames(&mut framer, &[&encoded[..4], &encoded[4..]]);
+    assert_eq!(received, vec![Frame { crc_ok: Some(true), ..complete(&payload) }]);
+
+    // Corrupt a payload byte
+    encoded[1] ^= 0x01;
+    let received = frames(&mut framer, &[&encoded]);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].status, FrameStatus::Complete);
+    assert_eq!(received[0].crc_ok, Some(false));
+}
+
+#[test]
+fn hdlc_short_and_aborted_frames_are_malformed() {
+    let mut framer = HdlcFramer::new(64, None);
+    assert_eq!(
+        frames(&mut framer, &[&[0x7E, 0x01, 0x7E, 0x02, 0x7D, 0x7E]]),
+        vec![Frame::new(vec![0x01], FrameStatus::Malformed), Frame::new(vec![0x02], FrameStatus::Malformed)]
+    );
+}
+
+#[test]
+fn oversized_packets_are_truncated_until_next_delimiter() {
+    let mut framer = SlipFramer::new(2, None);
+    assert_eq!(
+        frames(&mut framer, &[&[1, 2, 3, 4, 0xC0, 5, 0xC0]]),
+        vec![Frame::new(vec![1, 2], FrameStatus::Truncated), complete(&[5])]
+    );
+
+    let mut framer = HdlcFramer::new(1, None);
+    let received = frames(&mut framer, &[&hdlc_encode(&[1, 2, 3])]);
+    assert_eq!(received.len(), 1);
+    assert_eq!((received[0].status, received[0].crc_ok), (FrameStatus::Truncated, None));
+}
+
+#[test]
+fn config_builds_framers() {
+    let config = FramingConfig {
+        kind: FramingKind::Fixed { length: 2 },
+        idle_timeout: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let mut framer = config.build();
+    assert_eq!(framer.idle_timeout(), Some(Duration::from_millis(20)));
+    assert_eq!(frames(&mut *framer, &[b"abc"]), vec![complete(b"ab")]);
+
+    assert_eq!(FramingConfig::default().kind, FramingKind::Lines { ending: LineEnding::Any });
+}
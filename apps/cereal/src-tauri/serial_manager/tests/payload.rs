@@ -0,0 +1,39 @@
+use std::io::ErrorKind;
+use serial_manager::{SendLineEnding, SendMode, SerialManagerError};
+
+fn is_invalid_input<T: std::fmt::Debug>(result: Result<T, SerialManagerError>) -> bool {
+    matches!(result, Err(SerialManagerError::Io { kind: ErrorKind::InvalidInput, .. }))
+}
+
+#[test]
+fn text_gets_its_line_ending() {
+    let text = |line_ending| SendMode::Text { line_ending }.encode(r"AT\r").unwrap();
+    assert_eq!(text(SendLineEnding::CrLf), b"AT\\r\r\n");
+    assert_eq!(text(SendLineEnding::Lf), b"AT\\r\n");
+    assert_eq!(text(SendLineEnding::Cr), b"AT\\r\r");
+    assert_eq!(text(SendLineEnding::None), b"AT\\r");
+    assert_eq!(SendMode::default().encode("é").unwrap(), "é\r\n".as_bytes());
+}
+
+#[test]
+fn escapes_are_replaced() {
+    let escaped = SendMode::Escaped { line_ending: SendLineEnding::None };
+    assert_eq!(escaped.encode(r"\x1b[2J\e\r\n\t\\\0\033\177 \x41").unwrap(), b"\x1b[2J\x1b\r\n\t\\\0\x1b\x7f A");
+    assert_eq!(SendMode::Escaped { line_ending: SendLineEnding::Cr }.encode(r"\x1a").unwrap(), b"\x1a\r");
+
+    for payload in [r"\q", r"\x4", r"\xZZ", r"\400", "trailing \\"] {
+        assert!(is_invalid_input(escaped.encode(payload)), "{}", payload);
+    }
+}
+
+#[test]
+fn hex_is_parsed_in_the_usual_notations() {
+    for payload in ["DE AD BE EF", "de:ad:be:ef", "0xDE, 0xAD, 0xBE, 0xEF", "DEADBEEF", " de-ad beef\n"] {
+        assert_eq!(SendMode::Hex.encode(payload).unwrap(), [0xde, 0xad, 0xbe, 0xef], "{}", payload);
+    }
+    assert_eq!(SendMode::Hex.encode("").unwrap(), b"");
+
+    for payload in ["DEA", "0x", "GG", "DE AD B"] {
+        assert!(is_invalid_input(SendMode::Hex.encode(payload)), "{}", payload);
+    }
+}
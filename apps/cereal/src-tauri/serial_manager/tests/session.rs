@@ -0,0 +1,328 @@
+#![cfg(unix)]
+
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use serial_manager::{
+    CloseReason, DiscoveryConfig, EventReceiver, FrameStatus, FramingConfig, MockClock, MockEnumerator, PortConfig,
+    PortFilter, PortInfo, PortType, ResetSequence, ResetStep, SerialEvent, SerialManager, SerialManagerError,
+};
+use serialport::{SerialPort, TTYPort};
+
+/// A pseudo terminal standing in for a device: the session opens the slave
+/// side by name, the test plays the device on the master side.
+fn device() -> (TTYPort, TTYPort, String) {
+    let (mut master, slave) = TTYPort::pair().expect("failed to create pseudo terminal");
+    master.set_timeout(Duration::from_secs(1)).unwrap();
+    let port_name = slave.name().unwrap();
+    (master, slave, port_name)
+}
+
+fn next_session_event(events: &EventReceiver) -> SerialEvent {
+    loop {
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(Some(
+                event @ (SerialEvent::DataReceived { .. }
+                | SerialEvent::FrameReceived { .. }
+                | SerialEvent::ControlLinesChanged { .. }
+                | SerialEvent::SessionDisconnected { .. }
+                | SerialEvent::SessionReconnected { .. }
+                | SerialEvent::DataGap { .. }
+                | SerialEvent::SessionClosed { .. }),
+            )) => return event,
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("no session event"),
+            Err(e) => panic!("event bus failed: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn received_data_is_published_with_timestamps() {
+    let (mut master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    let before = SystemTime::now();
+    master.write_all(b"hello").unwrap();
+    let mut received = Vec::new();
+    while received.len() < 5 {
+        match next_session_event(&events) {
+            SerialEvent::DataReceived { session: id, bytes, host_timestamp, .. } => {
+                assert_eq!(id, session.id());
+                assert!(host_timestamp >= before);
+                received.extend_from_slice(&bytes);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    assert_eq!(received, b"hello");
+
+    let stats = session.stats();
+    assert_eq!(stats.bytes_received, 5);
+    assert_eq!((stats.chunks_coalesced, stats.chunks_dropped), (0, 0));
+}
+
+#[test]
+fn framed_session_publishes_lines() {
+    let (mut master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let events = manager.subscribe().unwrap();
+    let config = PortConfig {
+        framing: Some(FramingConfig { idle_timeout: Some(Duration::from_millis(100)), ..Default::default() }),
+        ..Default::default()
+    };
+    let session = manager.open(&port_name, config).unwrap();
+
+    let before = SystemTime::now();
+    master.write_all(b"boot").unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    master.write_all(b"ed\r\n> ").unwrap();
+
+    let mut frames = Vec::new();
+    while frames.len() < 2 {
+        match next_session_event(&events) {
+            SerialEvent::FrameReceived { session: id, frame, host_timestamp, .. } => {
+                assert_eq!(id, session.id());
+                assert!(host_timestamp >= before);
+                frames.push((frame.bytes, frame.status));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    // The prompt has no line ending and is flushed once the device goes quiet
+    assert_eq!(frames, vec![(b"booted".to_vec(), FrameStatus::Complete), (b"> ".to_vec(), FrameStatus::Flushed)]);
+    assert_eq!(session.stats().frames_received, 2);
+}
+
+#[test]
+fn session_writes_to_port() {
+    let (mut master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    session.write(b"ping").unwrap();
+    let mut reply = [0; 4];
+    master.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"ping");
+}
+
+#[test]
+fn slow_subscriber_overflow_is_counted() {
+    let (mut master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let slow = manager.subscribe_with_capacity(1).unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    // Separate writes arrive as separate reads, which pile up behind the slow subscriber
+    for _ in 0..20 {
+        master.write_all(&[0x55; 16]).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    std::thread::sleep(Duration::from_millis(100));
+
+    let stats = session.stats();
+    assert_eq!(stats.bytes_received, 320);
+    assert!(stats.chunks_coalesced + stats.chunks_dropped > 0, "{:?}", stats);
+    assert_eq!(manager.session_stats(session.id()).unwrap(), Some(stats));
+    drop(slow);
+}
+
+#[test]
+fn break_and_timed_sequences_run_on_any_port() {
+    let (_master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    session.send_break(Duration::from_millis(10)).unwrap();
+    let sequence = ResetSequence {
+        name: "break-and-wait".to_string(),
+        description: String::new(),
+        steps: vec![ResetStep::Break(Duration::from_millis(10)), ResetStep::Wait(Duration::from_millis(10))],
+    };
+    session.run_reset_sequence(&sequence).unwrap();
+}
+
+#[test]
+fn ports_without_control_lines_report_errors() {
+    // Pseudo terminals have no modem control lines
+    let (_master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    assert!(matches!(session.control_lines(), Err(SerialManagerError::Io { .. })));
+    assert!(matches!(session.set_dtr(true), Err(SerialManagerError::Io { .. })));
+    assert!(session.run_reset_sequence(&ResetSequence::find("esp32-reset").unwrap()).is_err());
+
+    // Watching gives up instead of reporting made-up line states
+    session.watch_control_lines(true);
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!session.is_watching_control_lines());
+    session.close();
+    assert!(matches!(next_session_event(&events), SerialEvent::SessionClosed { .. }));
+}
+
+#[test]
+fn port_can_only_be_opened_once() {
+    let (_master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    assert_eq!(
+        manager.open(&port_name, PortConfig::default()).unwrap_err(),
+        SerialManagerError::PortBusy { port: port_name.clone() }
+    );
+
+    // Closing releases the port
+    session.close();
+    manager.open(&port_name, PortConfig::default()).unwrap().close();
+}
+
+#[test]
+fn close_releases_port_and_is_published() {
+    let (_master, _slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+    assert_eq!(manager.session(session.id()).unwrap().map(|s| s.id()), Some(session.id()));
+
+    session.close();
+    assert!(!session.is_open());
+    assert_eq!(session.close_reason(), Some(CloseReason::Closed));
+    assert_eq!(session.write(b"late").unwrap_err(), SerialManagerError::SessionClosed { port: port_name.clone() });
+    assert!(manager.session(session.id()).unwrap().is_none());
+
+    match next_session_event(&events) {
+        SerialEvent::SessionClosed { session: id, port_name: closed_port, reason, .. } => {
+            assert_eq!(id, session.id());
+            assert_eq!(closed_port, port_name);
+            assert_eq!(reason, CloseReason::Closed);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    // Closing twice is harmless
+    session.close();
+}
+
+#[test]
+fn opening_missing_port_fails() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    assert!(manager.open("/dev/serial-manager-missing", PortConfig::default()).is_err());
+    assert!(manager.sessions().unwrap().is_empty());
+}
+
+fn usb_device(port_name: &str) -> PortInfo {
+    PortInfo {
+        port_type: PortType::Usb,
+        vid: Some(0x0483),
+        pid: Some(0x5740),
+        serial_number: Some("205C3A8B4E53".to_string()),
+        ..PortInfo::new(port_name)
+    }
+}
+
+/// A manager whose discovery sees exactly what the enumerator is scripted to show
+fn scripted_manager(enumerator: &MockEnumerator) -> (SerialManager, MockClock) {
+    let clock = MockClock::new();
+    let manager = SerialManager::with_enumerator(
+        PortFilter::allow_all(),
+        DiscoveryConfig::default(),
+        Box::new(enumerator.clone()),
+        Arc::new(clock.clone()),
+    );
+    clock.advance(Duration::ZERO);
+    (manager, clock)
+}
+
+#[test]
+fn auto_reconnect_follows_device_to_new_port() {
+    let (master, slave, first_port) = device();
+    let enumerator = MockEnumerator::new();
+    enumerator.add_at(0, usb_device(&first_port));
+    let (manager, clock) = scripted_manager(&enumerator);
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&first_port, PortConfig::default()).unwrap();
+    session.set_auto_reconnect(true);
+    assert!(session.identity().is_some());
+    // Created up front so that it cannot reuse the first port's name
+    let (mut master_after_reset, _slave_after_reset, second_port) = device();
+
+    // The board resets and its port goes away...
+    drop((master, slave));
+    match next_session_event(&events) {
+        SerialEvent::SessionDisconnected { session: id, port_name, .. } => {
+            assert_eq!(id, session.id());
+            assert_eq!(port_name, first_port);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(session.is_open() && !session.is_connected());
+    assert_eq!(session.write(b"lost").unwrap_err(), SerialManagerError::SessionDisconnected { port: first_port.clone() });
+
+    // ...and comes back under another name
+    enumerator.remove_at(1, &first_port).add_at(1, usb_device(&second_port));
+    clock.advance(DiscoveryConfig::default().poll_interval);
+
+    match next_session_event(&events) {
+        SerialEvent::SessionReconnected { session: id, port_name, .. } => {
+            assert_eq!(id, session.id());
+            assert_eq!(port_name, second_port);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    match next_session_event(&events) {
+        SerialEvent::DataGap { session: id, since, until } => {
+            assert_eq!(id, session.id());
+            assert!(since <= until);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(session.is_connected());
+    assert_eq!(session.port_name(), second_port);
+
+    session.write(b"ok").unwrap();
+    let mut reply = [0; 2];
+    master_after_reset.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"ok");
+}
+
+#[test]
+fn disconnected_session_can_be_closed() {
+    let (master, slave, port_name) = device();
+    let enumerator = MockEnumerator::new();
+    enumerator.add_at(0, usb_device(&port_name));
+    let (manager, _clock) = scripted_manager(&enumerator);
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+    session.set_auto_reconnect(true);
+
+    drop((master, slave));
+    assert!(matches!(next_session_event(&events), SerialEvent::SessionDisconnected { .. }));
+
+    session.close();
+    match next_session_event(&events) {
+        SerialEvent::SessionClosed { reason, .. } => assert_eq!(reason, CloseReason::Closed),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(!session.is_open());
+}
+
+#[test]
+fn lost_device_closes_session_without_auto_reconnect() {
+    let (master, slave, port_name) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let events = manager.subscribe().unwrap();
+    let session = manager.open(&port_name, PortConfig::default()).unwrap();
+
+    drop((master, slave));
+    match next_session_event(&events) {
+        SerialEvent::SessionClosed { reason, .. } => assert!(matches!(reason, CloseReason::Error(_)), "{:?}", reason),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(!session.is_open());
+}
@@ -0,0 +1,104 @@
+#![cfg(unix)]
+
+use std::{
+    io::{Read, Write},
+    thread,
+    time::Duration,
+};
+use serial_manager::{
+    Direction, FrameStatus, FramingConfig, PortConfig, PortFilter, SerialManager, Timeline, TimelineEntry,
+};
+use serialport::{SerialPort, TTYPort};
+
+/// A pseudo terminal standing in for a device, see tests/session.rs
+fn device() -> (TTYPort, TTYPort, String) {
+    let (mut master, slave) = TTYPort::pair().expect("failed to create pseudo terminal");
+    master.set_timeout(Duration::from_secs(1)).unwrap();
+    let port_name = slave.name().unwrap();
+    (master, slave, port_name)
+}
+
+/// Collects timeline entries until `count` have arrived
+fn collect(timeline: &mut Timeline, count: usize) -> Vec<TimelineEntry> {
+    let mut entries = Vec::new();
+    for _ in 0..50 {
+        entries.extend(timeline.recv_timeout(Duration::from_millis(100)).unwrap());
+        if entries.len() >= count {
+            return entries;
+        }
+    }
+    panic!("only {} of {} timeline entries arrived: {:?}", entries.len(), count, entries);
+}
+
+#[test]
+fn sessions_are_merged_in_time_order() {
+    let (mut device_a, _slave_a, port_a) = device();
+    let (mut device_b, _slave_b, port_b) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let framing = PortConfig { framing: Some(FramingConfig::default()), ..Default::default() };
+    let session_a = manager.open(&port_a, framing.clone()).unwrap();
+    let session_b = manager.open(&port_b, framing).unwrap();
+    let mut timeline = manager.timeline(&[]).unwrap();
+
+    // The devices take turns, like two MCUs exchanging messages
+    device_a.write_all(b"ping 1\n").unwrap();
+    thread::sleep(Duration::from_millis(30));
+    device_b.write_all(b"pong 1\n").unwrap();
+    thread::sleep(Duration::from_millis(30));
+    session_a.write(b"reset\n").unwrap();
+    thread::sleep(Duration::from_millis(30));
+    device_a.write_all(b"ping 2\n").unwrap();
+    thread::sleep(Duration::from_millis(30));
+    device_b.write_all(b"pong 2\n").unwrap();
+
+    let entries = collect(&mut timeline, 5);
+    let summary: Vec<(&str, Direction, &[u8])> = entries
+        .iter()
+        .map(|entry| (entry.port_name.as_str(), entry.direction, entry.bytes.as_slice()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (port_a.as_str(), Direction::Rx, &b"ping 1"[..]),
+            (port_b.as_str(), Direction::Rx, &b"pong 1"[..]),
+            (port_a.as_str(), Direction::Tx, &b"reset\n"[..]),
+            (port_a.as_str(), Direction::Rx, &b"ping 2"[..]),
+            (port_b.as_str(), Direction::Rx, &b"pong 2"[..]),
+        ]
+    );
+    assert!(entries.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+    assert_eq!(entries[1].session, session_b.id());
+    assert_eq!(entries[0].frame_status, Some(FrameStatus::Complete));
+    assert_eq!(entries[2].frame_status, None);
+
+    let mut written = [0; 6];
+    device_a.read_exact(&mut written).unwrap();
+    assert_eq!(&written, b"reset\n");
+}
+
+#[test]
+fn timeline_only_includes_chosen_sessions() {
+    let (mut device_a, _slave_a, port_a) = device();
+    let (mut device_b, _slave_b, port_b) = device();
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let session_a = manager.open(&port_a, PortConfig::default()).unwrap();
+    manager.open(&port_b, PortConfig::default()).unwrap();
+    let mut timeline = manager.timeline(&[session_a.id()]).unwrap();
+    timeline.set_reorder_window(Duration::from_millis(10));
+
+    device_b.write_all(b"noise").unwrap();
+    device_a.write_all(b"signal").unwrap();
+
+    let entries = collect(&mut timeline, 1);
+    let received: Vec<u8> = entries.iter().flat_map(|entry| entry.bytes.clone()).collect();
+    assert!(entries.iter().all(|entry| entry.session == session_a.id() && entry.port_name == port_a));
+    assert!(b"signal".starts_with(&received));
+
+    // Held back entries can be taken without waiting for the reorder window
+    timeline.set_reorder_window(Duration::from_secs(60));
+    session_a.write(b"ack").unwrap();
+    assert!(timeline.recv_timeout(Duration::from_millis(50)).unwrap().is_empty());
+    let sent: Vec<TimelineEntry> = timeline.drain().into_iter().filter(|entry| entry.direction == Direction::Tx).collect();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].bytes, b"ack");
+}
@@ -0,0 +1,183 @@
+#![cfg(unix)]
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    thread,
+    time::{Duration, Instant},
+};
+use serial_manager::{
+    EventReceiver, LineEnding, PortConfig, PortFilter, SerialEvent, SerialManager, SerialManagerError, SessionHandle,
+    TransmitId, TransmitMode, TransmitOptions, TransmitOutcome, VirtualDevice,
+};
+
+/// A session on a virtual device, with a subscription to its events
+fn open(manager: &SerialManager, name: &str) -> (VirtualDevice, SessionHandle, EventReceiver) {
+    let events = manager.subscribe().unwrap();
+    let device = manager.create_virtual_port(name).unwrap();
+    let session = manager.open(device.port_name(), PortConfig::default()).unwrap();
+    (device, session, events)
+}
+
+/// Waits for the next TransmitProgress or TransmitFinished event
+fn next_transmit_event(events: &EventReceiver) -> SerialEvent {
+    loop {
+        match events.recv_timeout(Duration::from_secs(3)) {
+            Ok(Some(event @ (SerialEvent::TransmitProgress { .. } | SerialEvent::TransmitFinished { .. }))) => {
+                return event
+            }
+            Ok(Some(_)) => {}
+            other => panic!("no transmit event: {:?}", other),
+        }
+    }
+}
+
+/// Waits for the given transmission to finish, returning its outcome and the progress reported
+fn outcome(events: &EventReceiver, id: TransmitId) -> (TransmitOutcome, Vec<(u64, u64)>) {
+    let mut progress = Vec::new();
+    loop {
+        match next_transmit_event(events) {
+            SerialEvent::TransmitProgress { transmit, sent, total, .. } if transmit == id => progress.push((sent, total)),
+            SerialEvent::TransmitFinished { transmit, outcome, .. } if transmit == id => return (outcome, progress),
+            _ => {}
+        }
+    }
+}
+
+/// Reads from the device until `count` bytes arrived
+fn read(device: &mut VirtualDevice, count: usize) -> Vec<u8> {
+    let mut bytes = vec![0; count];
+    device.read_exact(&mut bytes).unwrap();
+    bytes
+}
+
+/// Asserts that nothing arrives on the device for a while
+fn assert_quiet(device: &mut VirtualDevice) {
+    device.set_timeout(Duration::from_millis(100)).unwrap();
+    let mut byte = [0];
+    match device.read(&mut byte) {
+        Err(e) if e.kind() == ErrorKind::TimedOut => {}
+        other => panic!("expected nothing, got {:?} {:?}", other, byte),
+    }
+    device.set_timeout(Duration::from_secs(1)).unwrap();
+}
+
+#[test]
+fn lines_are_sent_with_new_endings_and_delays() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (mut device, session, events) = open(&manager, "transmit-lines");
+    let options = TransmitOptions {
+        mode: TransmitMode::Text { line_ending: LineEnding::Cr },
+        line_delay: Duration::from_millis(100),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    let id = session.transmit(b"setenv a 1\nsetenv b 2\r\nsaveenv", options).unwrap();
+    assert_eq!(read(&mut device, 11), b"setenv a 1\r");
+    assert_eq!(read(&mut device, 11), b"setenv b 2\r");
+    assert_eq!(read(&mut device, 8), b"saveenv\r");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let (outcome, progress) = outcome(&events, id);
+    assert_eq!(outcome, TransmitOutcome::Completed);
+    assert_eq!(progress, vec![(11, 30), (22, 30), (30, 30)]);
+}
+
+#[test]
+fn prompts_pace_the_lines() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (mut device, session, events) = open(&manager, "transmit-prompt");
+    let options = TransmitOptions { prompt: Some(r"=> $".to_string()), ..Default::default() };
+
+    // The prompt of an earlier command does not count
+    device.write_all(b"=> ").unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let id = session.transmit(b"first\nsecond\n", options).unwrap();
+    for line in [&b"first\n"[..], b"second\n"] {
+        assert_eq!(read(&mut device, line.len()), line);
+        assert_quiet(&mut device);
+        device.write_all(b"\r\nok\r\n=> ").unwrap();
+    }
+    assert_eq!(outcome(&events, id).0, TransmitOutcome::Completed);
+}
+
+#[test]
+fn missing_prompts_time_out() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (mut device, session, events) = open(&manager, "transmit-timeout");
+    let options = TransmitOptions {
+        prompt: Some("ready".to_string()),
+        prompt_timeout: Duration::from_millis(100),
+        ..Default::default()
+    };
+
+    let id = session.transmit(b"reset\nstatus\n", options).unwrap();
+    assert_eq!(read(&mut device, 6), b"reset\n");
+    device.write_all(b"busy\r\n").unwrap();
+    assert_eq!(outcome(&events, id).0, TransmitOutcome::PromptTimedOut { line: 1 });
+    assert_quiet(&mut device);
+
+    let invalid = TransmitOptions { prompt: Some("(".to_string()), ..Default::default() };
+    assert!(matches!(
+        session.transmit(b"x\n", invalid),
+        Err(SerialManagerError::Io { kind: ErrorKind::InvalidInput, .. })
+    ));
+}
+
+#[test]
+fn files_are_sent_in_blocks() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (mut device, session, events) = open(&manager, "transmit-file");
+    let path = std::env::temp_dir().join(format!("serial_manager-{}-firmware.bin", std::process::id()));
+    let firmware: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &firmware).unwrap();
+
+    let options = TransmitOptions { mode: TransmitMode::Binary, ..Default::default() };
+    let id = session.transmit_file(&path, options).unwrap();
+    assert_eq!(read(&mut device, 2500), firmware);
+    let (outcome, progress) = outcome(&events, id);
+    assert_eq!(outcome, TransmitOutcome::Completed);
+    assert_eq!(progress, vec![(1024, 2500), (2048, 2500), (2500, 2500)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn transmissions_can_be_cancelled() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (mut device, session, events) = open(&manager, "transmit-cancel");
+    let slow = TransmitOptions { char_delay: Duration::from_millis(10), ..Default::default() };
+
+    let current = session.transmit(&[b'x'; 500], slow.clone()).unwrap();
+    let queued = session.transmit(b"never sent", slow).unwrap();
+    assert_eq!(read(&mut device, 3), b"xxx");
+
+    assert!(session.cancel_transmit(queued));
+    assert!(matches!(
+        next_transmit_event(&events),
+        SerialEvent::TransmitFinished { transmit, outcome: TransmitOutcome::Cancelled, .. } if transmit == queued
+    ));
+    assert!(session.cancel_transmit(current));
+    assert_eq!(outcome(&events, current).0, TransmitOutcome::Cancelled);
+    assert!(!session.cancel_transmit(current));
+
+    device.set_timeout(Duration::from_millis(100)).unwrap();
+    let mut rest = Vec::new();
+    let _ = device.read_to_end(&mut rest);
+    assert!(rest.len() < 100, "sent {} more bytes", rest.len());
+    assert!(rest.iter().all(|&byte| byte == b'x'));
+}
+
+#[test]
+fn closing_the_session_cancels_transmissions() {
+    let manager = SerialManager::new(PortFilter::allow_all());
+    let (_device, session, events) = open(&manager, "transmit-close");
+    let slow = TransmitOptions { line_delay: Duration::from_secs(10), ..Default::default() };
+
+    let id = session.transmit(b"one\ntwo\n", slow).unwrap();
+    assert!(matches!(next_transmit_event(&events), SerialEvent::TransmitProgress { .. }));
+    let started = Instant::now();
+    session.close();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(outcome(&events, id).0, TransmitOutcome::Cancelled);
+    assert!(matches!(session.transmit(b"late\n", TransmitOptions::default()), Err(SerialManagerError::SessionClosed { .. })));
+}
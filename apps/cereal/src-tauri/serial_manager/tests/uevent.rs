@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use serial_manager::{
+    DiscoveryConfig, DiscoveryService, InjectedUeventSource, PortEvent, PortFilter, PortInfo, PortType, Uevent, UeventAction,
+};
+
+fn uevent(action: &str, devpath: &str, fields: &[&str]) -> Vec<u8> {
+    let mut message = format!("{}@{}\0ACTION={}\0DEVPATH={}\0", action, devpath, action, devpath);
+    for field in fields {
+        message.push_str(field);
+        message.push('\0');
+    }
+    message.into_bytes()
+}
+
+const ACM0: &str = "/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0/tty/ttyACM0";
+
+#[test]
+fn parses_tty_add() {
+    let message = uevent("add", ACM0, &["SUBSYSTEM=tty", "DEVNAME=ttyACM0", "MAJOR=166", "MINOR=0"]);
+    let parsed = Uevent::parse(&message).unwrap();
+
+    assert_eq!(parsed.action, UeventAction::Add);
+    assert_eq!(parsed.devpath, ACM0);
+    assert_eq!(parsed.subsystem.as_deref(), Some("tty"));
+    assert_eq!(parsed.port_event(), Some(PortEvent::Added(PortInfo::new("/dev/ttyACM0"))));
+    // The device does not exist in sysfs, but its path still tells us the bus
+    assert_eq!(parsed.port_info().port_type, PortType::Usb);
+}
+
+#[test]
+fn ignores_non_tty_and_virtual_devices() {
+    let usb = uevent("add", "/devices/pci0000:00/0000:00:14.0/usb1/1-2", &["SUBSYSTEM=usb"]);
+    assert_eq!(Uevent::parse(&usb).unwrap().port_event(), None);
+
+    let pty = uevent("add", "/devices/virtual/tty/ptmx", &["SUBSYSTEM=tty", "DEVNAME=ptmx"]);
+    assert_eq!(Uevent::parse(&pty).unwrap().port_event(), None);
+
+    let change = uevent("change", ACM0, &["SUBSYSTEM=tty", "DEVNAME=ttyACM0"]);
+    assert_eq!(Uevent::parse(&change).unwrap().port_event(), None);
+}
+
+#[test]
+fn ignores_udev_messages() {
+    assert_eq!(Uevent::parse(b"libudev\0\xfe\xed\xca\xfe"), None);
+}
+
+#[test]
+fn injected_uevents_drive_discovery() {
+    let known_ports = Arc::new(Mutex::new(HashMap::new()));
+    let (event_sender, events) = mpsc::channel();
+    let (injector, source) = InjectedUeventSource::channel();
+
+    let _discovery = DiscoveryService::spawn_with_uevent_source(
+        known_ports.clone(),
+        PortFilter::allow_all(),
+        DiscoveryConfig::default(),
+        Box::new(move |event| {
+            let _ = event_sender.send(event);
+        }),
+        Box::new(source),
+    );
+
+    let timeout = Duration::from_secs(1);
+    injector.send(uevent("add", ACM0, &["SUBSYSTEM=tty", "DEVNAME=ttyACM0"])).unwrap();
+    // A duplicate add must not produce a second event
+    injector.send(uevent("add", ACM0, &["SUBSYSTEM=tty", "DEVNAME=ttyACM0"])).unwrap();
+    match events.recv_timeout(timeout).unwrap() {
+        PortEvent::Added(port) => assert_eq!(port.port_name, "/dev/ttyACM0"),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(known_ports.lock().unwrap().contains_key("/dev/ttyACM0"));
+
+    injector.send(uevent("remove", ACM0, &["SUBSYSTEM=tty", "DEVNAME=ttyACM0"])).unwrap();
+    match events.recv_timeout(timeout).unwrap() {
+        PortEvent::Removed(port) => assert_eq!(port.port_name, "/dev/ttyACM0"),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(known_ports.lock().unwrap().is_empty());
+}
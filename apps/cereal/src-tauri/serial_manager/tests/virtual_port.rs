@@ -0,0 +1,165 @@
+#![cfg(unix)]
+
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+use serial_manager::{
+    DiscoveryConfig, EventReceiver, FrameStatus, FramingConfig, PortConfig, PortFilter, PortMatcher, PortType,
+    SerialEvent, SerialManager, SerialManagerError,
+};
+
+/// A manager whose discovery reports departures of identifiable devices after `settle_window`
+fn manager(settle_window: Duration) -> SerialManager {
+    SerialManager::with_config(PortFilter::allow_all(), DiscoveryConfig { settle_window, ..Default::default() })
+}
+
+/// Waits for the next discovery event about the given port
+fn next_device_event(events: &EventReceiver, port_name: &str) -> SerialEvent {
+    next_event(events, |event| match event {
+        SerialEvent::DeviceArrived { port, .. }
+        | SerialEvent::DeviceDeparted { port, .. }
+        | SerialEvent::DeviceReenumerated { port, .. } => port.port_name == port_name,
+        SerialEvent::DeviceMoved { from, to, .. } => from.port_name == port_name || to.port_name == port_name,
+        _ => false,
+    })
+}
+
+/// Waits for the next event published by a session, other than what it sent
+fn next_session_event(events: &EventReceiver) -> SerialEvent {
+    next_event(events, |event| {
+        !matches!(
+            event,
+            SerialEvent::DeviceArrived { .. }
+                | SerialEvent::DeviceDeparted { .. }
+                | SerialEvent::DeviceMoved { .. }
+                | SerialEvent::DeviceReenumerated { .. }
+                | SerialEvent::DiscoveryError { .. }
+                | SerialEvent::DataSent { .. }
+        )
+    })
+}
+
+fn next_event(events: &EventReceiver, wanted: impl Fn(&SerialEvent) -> bool) -> SerialEvent {
+    loop {
+        match events.recv_timeout(Duration::from_secs(3)) {
+            Ok(Some(event)) if wanted(&event) => return event,
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("no matching event"),
+            Err(e) => panic!("event bus failed: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn virtual_ports_arrive_and_depart() {
+    let manager = manager(Duration::ZERO);
+    let events = manager.subscribe().unwrap();
+
+    let device = manager.create_virtual_port("bench-a").unwrap();
+    let port_name = device.port_name().to_string();
+    match next_device_event(&events, &port_name) {
+        SerialEvent::DeviceArrived { port, .. } => {
+            assert_eq!(port.port_type, PortType::Virtual);
+            assert_eq!(port.display_name(), "bench-a");
+            assert!(port.identity().is_some());
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(manager.get_ports().unwrap().iter().any(|port| port.port_name == port_name));
+
+    // Names are unique, like the devices they stand for
+    assert!(matches!(manager.create_virtual_port("bench-a"), Err(SerialManagerError::PortBusy { .. })));
+
+    drop(device);
+    match next_device_event(&events, &port_name) {
+        SerialEvent::DeviceDeparted { port, .. } => assert_eq!(port.port_name, port_name),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(!manager.get_ports().unwrap().iter().any(|port| port.port_name == port_name));
+}
+
+#[test]
+fn filter_applies_to_virtual_ports() {
+    let filter = PortFilter {
+        exclude: vec![PortMatcher { product: Some("hidden".into()), ..Default::default() }],
+        ..Default::default()
+    };
+    let manager = SerialManager::new(filter);
+    let hidden = manager.create_virtual_port("hidden-device").unwrap();
+    let shown = manager.create_virtual_port("shown-device").unwrap();
+
+    let ports = manager.get_ports().unwrap();
+    assert!(ports.iter().any(|port| port.port_name == shown.port_name()));
+    assert!(!ports.iter().any(|port| port.port_name == hidden.port_name()));
+}
+
+#[test]
+fn sessions_talk_to_virtual_devices() {
+    let manager = manager(Duration::ZERO);
+    let events = manager.subscribe().unwrap();
+    let mut device = manager.create_virtual_port("modem").unwrap();
+    let port_name = device.port_name().to_string();
+    let config = PortConfig { framing: Some(FramingConfig::default()), ..Default::default() };
+    let session = manager.open(&port_name, config).unwrap();
+
+    device.write_all(b"RING\r\nOK\r\n").unwrap();
+    let mut lines = Vec::new();
+    while lines.len() < 2 {
+        match next_session_event(&events) {
+            SerialEvent::FrameReceived { session: id, frame, .. } => {
+                assert_eq!(id, session.id());
+                assert_eq!(frame.status, FrameStatus::Complete);
+                lines.push(frame.bytes);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    assert_eq!(lines, vec![b"RING".to_vec(), b"OK".to_vec()]);
+
+    session.write(b"ATA\r").unwrap();
+    let mut command = [0; 4];
+    device.read_exact(&mut command).unwrap();
+    assert_eq!(&command, b"ATA\r");
+}
+
+#[test]
+fn sessions_reconnect_to_recreated_virtual_ports() {
+    let manager = manager(Duration::from_millis(500));
+    let events = manager.subscribe().unwrap();
+    let device = manager.create_virtual_port("board").unwrap();
+    let first_port = device.port_name().to_string();
+    let session = manager.open(&first_port, PortConfig::default()).unwrap();
+    session.set_auto_reconnect(true);
+    assert!(session.identity().is_some());
+
+    // The board resets: its port goes away and comes back, maybe under another name
+    drop(device);
+    match next_session_event(&events) {
+        SerialEvent::SessionDisconnected { session: id, port_name, .. } => {
+            assert_eq!(id, session.id());
+            assert_eq!(port_name, first_port);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    let mut device = manager.create_virtual_port("board").unwrap();
+    let second_port = device.port_name().to_string();
+
+    match next_session_event(&events) {
+        SerialEvent::SessionReconnected { session: id, port_name, .. } => {
+            assert_eq!(id, session.id());
+            assert_eq!(port_name, second_port);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(matches!(next_session_event(&events), SerialEvent::DataGap { .. }));
+
+    device.write_all(b"back").unwrap();
+    match next_session_event(&events) {
+        SerialEvent::DataReceived { session: id, bytes, .. } => {
+            assert_eq!(id, session.id());
+            assert!(b"back".starts_with(&bytes));
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
@@ -0,0 +1,288 @@
+//! # Scrollback Export
+//!
+//! A ScrollbackExport writes a range of a Scrollback's lines to a file to
+//! attach to a bug report: as plain text, as CSV with a header row, or as
+//! JSON Lines with one object per line. Like ScrollbackSearch it works in
+//! steps, so a large export can run in the background, report its progress
+//! and let data be appended to the scrollback in between.
+//!
+//! The range is fixed when the export starts. Lines appended afterwards are
+//! not exported; a line that was in progress is exported as it is by the
+//! time the export reaches it.
+//!
+//! ## Text
+//!
+//! Without escaping, data that is not valid UTF-8 is written as U+FFFD. With
+//! ExportOptions::escape_non_printable, control characters, invalid bytes and
+//! backslashes are written as `\xNN` instead, so that binary data survives the
+//! export and every line of the log stays one line in the file.
+//!
+//! ```rust ignore
+//! let file = BufWriter::new(File::create("boot.csv")?);
+//! let options = ExportOptions { format: ExportFormat::Csv, relative_times: true, ..Default::default() };
+//! let mut export = ScrollbackExport::new(file, &mut scrollback, "/dev/ttyUSB0", ExportRange::All, options)?;
+//! while !export.is_finished() {
+//!     export.step(&mut scrollback, 10_000)?;
+//!     println!("{} of {} lines", export.exported(), export.total());
+//! }
+//! export.finish()?;
+//! ```
+
+use std::{
+    borrow::Cow,
+    io::{self, Result, Write},
+    time::{Duration, SystemTime},
+};
+use serde::{Deserialize, Serialize};
+use serial_manager::Direction;
+use crate::scrollback::{RawLine, Scrollback};
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One line of text per line, its timestamps and direction first
+    Text,
+    /// A header row, then one row per line
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Which lines to export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportRange {
+    /// Every line in the scrollback
+    All,
+    /// Lines from `start` up to but not including `end`, counted from 0
+    Lines { start: u64, end: u64 },
+    /// Lines whose first byte was read or written from `from` up to and including `until`
+    Time { from: SystemTime, until: SystemTime },
+}
+
+/// What an export writes for each line
+///
+/// Fields missing when deserializing take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// File format to write
+    pub format: ExportFormat,
+    /// Write when each line was read or written, in UTC as RFC 3339
+    pub host_timestamps: bool,
+    /// Write the seconds from the first exported line to each line
+    pub relative_times: bool,
+    /// Write control characters and bytes that are not valid UTF-8 as `\xNN`
+    pub escape_non_printable: bool,
+}
+
+impl Default for ExportOptions {
+    /// Plain text with host timestamps, without relative times or escaping
+    fn default() -> Self {
+        ExportOptions {
+            format: ExportFormat::Text,
+            host_timestamps: true,
+            relative_times: false,
+            escape_non_printable: false,
+        }
+    }
+}
+
+/// A line as written to JSON Lines
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative: Option<f64>,
+    direction: Direction,
+    port: &'a str,
+    text: &'a str,
+}
+
+/// An export of a range of a Scrollback's lines to a writer
+pub struct ScrollbackExport<W: Write> {
+    writer: W,
+    port_name: String,
+    options: ExportOptions,
+    start: u64,
+    end: u64,
+    /// Next line to export
+    next: u64,
+    /// Timestamp of the first exported line, which relative times count from
+    base: Option<SystemTime>,
+}
+
+impl<W: Write> ScrollbackExport<W> {
+    /// Starts exporting the lines of `scrollback` in `range`, writing the CSV
+    /// header if there is one. Lines beyond the end of the scrollback are
+    /// left out of the range.
+    ///
+    /// Fails with an InvalidInput error if the range ends before it starts.
+    pub fn new(
+        mut writer: W,
+        scrollback: &mut Scrollback,
+        port_name: &str,
+        range: ExportRange,
+        options: ExportOptions
+    ) -> Result<Self> {
+        let (start, end) = match range {
+            ExportRange::All => (0, scrollback.len()),
+            ExportRange::Lines { start, end } if start <= end => (start, end),
+            ExportRange::Time { from, until } if from <= until => (
+                scrollback.position_of_time(from)?,
+                scrollback.position_of_time(until + Duration::from_nanos(1))?,
+            ),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "export range ends before it starts")),
+        };
+        let end = end.min(scrollback.len());
+        let start = start.min(end);
+
+        if options.format == ExportFormat::Csv {
+            let mut columns = Vec::new();
+            if options.host_timestamps {
+                columns.push("timestamp");
+            }
+            if options.relative_times {
+                columns.push("relative");
+            }
+            columns.extend(["direction", "port", "text"]);
+            writeln!(writer, "{}", columns.join(","))?;
+        }
+        Ok(ScrollbackExport {
+            writer,
+            port_name: port_name.to_string(),
+            options,
+            start,
+            end,
+            next: start,
+            base: None,
+        })
+    }
+
+    /// Returns the number of lines exported so far.
+    pub fn exported(&self) -> u64 {
+        self.next - self.start
+    }
+
+    /// Returns the number of lines in the range.
+    pub fn total(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Returns whether every line in the range was exported.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.end
+    }
+
+    /// Exports up to `max_lines` more lines and returns how many it exported.
+    pub fn step(&mut self, scrollback: &mut Scrollback, max_lines: usize) -> Result<u64> {
+        let count = (self.end - self.next).min(max_lines as u64) as usize;
+        if count == 0 {
+            return Ok(0);
+        }
+        let lines = scrollback.raw_lines(self.next, count)?;
+        for line in &lines {
+            self.write_line(line)?;
+            self.next += 1;
+        }
+        Ok(lines.len() as u64)
+    }
+
+    /// Flushes the export and returns the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_line(&mut self, line: &RawLine) -> Result<()> {
+        let base = *self.base.get_or_insert(line.timestamp);
+        let timestamp = self.options.host_timestamps
+            .then(|| humantime::format_rfc3339_millis(line.timestamp).to_string());
+        let relative = self.options.relative_times.then(|| seconds_between(base, line.timestamp));
+        let text = if self.options.escape_non_printable {
+            Cow::Owned(escape_non_printable(&line.bytes))
+        } else {
+            String::from_utf8_lossy(&line.bytes)
+        };
+        let direction = match line.direction {
+            Direction::Rx => "RX",
+            Direction::Tx => "TX",
+        };
+
+        match self.options.format {
+            ExportFormat::Text => {
+                if let Some(timestamp) = &timestamp {
+                    write!(self.writer, "[{}] ", timestamp)?;
+                }
+                if let Some(relative) = relative {
+                    write!(self.writer, "+{:.3}s ", relative)?;
+                }
+                writeln!(self.writer, "{} {}", direction, text)?;
+            }
+            ExportFormat::Csv => {
+                let mut fields = Vec::new();
+                fields.extend(timestamp);
+                fields.extend(relative.map(|relative| format!("{:.3}", relative)));
+                fields.push(direction.to_string());
+                fields.push(csv_field(&self.port_name).into_owned());
+                fields.push(csv_field(&text).into_owned());
+                writeln!(self.writer, "{}", fields.join(","))?;
+            }
+            ExportFormat::JsonLines => {
+                let json = JsonLine {
+                    line: self.next,
+                    timestamp,
+                    relative,
+                    direction: line.direction,
+                    port: &self.port_name,
+                    text: &text,
+                };
+                serde_json::to_writer(&mut self.writer, &json).map_err(io::Error::from)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Seconds from `base` to `time`, negative if the wall clock was set back
+fn seconds_between(base: SystemTime, time: SystemTime) -> f64 {
+    match time.duration_since(base) {
+        Ok(after) => after.as_secs_f64(),
+        Err(before) => -before.duration().as_secs_f64(),
+    }
+}
+
+/// Quotes a CSV field if it needs to be
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Decodes `bytes` as UTF-8, writing control characters, invalid bytes and
+/// backslashes as `\xNN`
+fn escape_non_printable(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c.is_control() || c == '\\' {
+                let mut encoded = [0; 4];
+                for byte in c.encode_utf8(&mut encoded).bytes() {
+                    text.push_str(&format!("\\x{:02x}", byte));
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            text.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    text
+}
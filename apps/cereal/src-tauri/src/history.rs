@@ -0,0 +1,102 @@
+//! # Command History
+//!
+//! Each board has its handful of commands that get typed over and over. A
+//! CommandHistory remembers what was sent, per connection profile, in a JSON
+//! file, so the commands for a board can be recalled the next day. Sessions
+//! opened without a profile share the history under the empty name.
+//!
+//! Sending a command that is already in the history moves it to the end
+//! rather than adding it again, and only the latest DEFAULT_HISTORY_LENGTH
+//! commands of each profile are kept.
+//!
+//! ```rust ignore
+//! let mut history = CommandHistory::open(config_dir.join("history.json"))?;
+//! history.push("STM32 debug", HistoryEntry { payload: "reset".to_string(), mode: SendMode::default() })?;
+//! let last = history.recall("STM32 debug", 0);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Result},
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+use serial_manager::SendMode;
+use crate::write_json;
+
+/// Commands kept per profile
+pub const DEFAULT_HISTORY_LENGTH: usize = 100;
+
+/// A command that was sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The payload as it was typed
+    pub payload: String,
+    /// How it was turned into bytes
+    pub mode: SendMode,
+}
+
+/// Commands sent with each connection profile, kept in a JSON file
+pub struct CommandHistory {
+    path: PathBuf,
+    /// Commands by profile name, oldest first
+    histories: BTreeMap<String, Vec<HistoryEntry>>,
+}
+
+impl CommandHistory {
+    /// Starts without any history, without reading `path`. The file there is
+    /// replaced when a command is first pushed.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        CommandHistory { path: path.as_ref().to_path_buf(), histories: BTreeMap::new() }
+    }
+
+    /// Loads the history saved at `path`, or starts without any if there is
+    /// no file yet. Fails with an InvalidData error if the file is not a
+    /// history file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let histories = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid history file: {}", e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(CommandHistory { path, histories })
+    }
+
+    /// Returns the path of the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the commands sent with a profile, oldest first.
+    pub fn entries(&self, profile: &str) -> &[HistoryEntry] {
+        self.histories.get(profile).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the command sent `back` commands before the latest one, so 0
+    /// recalls the latest.
+    pub fn recall(&self, profile: &str, back: usize) -> Option<&HistoryEntry> {
+        self.entries(profile).iter().rev().nth(back)
+    }
+
+    /// Adds a command to the end of a profile's history and writes the file.
+    pub fn push(&mut self, profile: &str, entry: HistoryEntry) -> Result<()> {
+        let entries = self.histories.entry(profile.to_string()).or_default();
+        entries.retain(|sent| *sent != entry);
+        entries.push(entry);
+        if entries.len() > DEFAULT_HISTORY_LENGTH {
+            entries.drain(..entries.len() - DEFAULT_HISTORY_LENGTH);
+        }
+        write_json(&self.path, &self.histories)
+    }
+
+    /// Forgets the commands sent with a profile and writes the file.
+    pub fn clear(&mut self, profile: &str) -> Result<()> {
+        if self.histories.remove(profile).is_some() {
+            write_json(&self.path, &self.histories)?;
+        }
+        Ok(())
+    }
+}
@@ -1,114 +1,1331 @@
-use tauri::{command, AppHandle, Emitter};
+use tauri::{command, AppHandle, Emitter, Manager, RunEvent};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, SystemTime};
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
 // Import from your crate
-use serial_manager::{SerialManager, SerialEvent};
+use serial_manager::{
+    SerialManager, SerialEvent, PortInfo, PortFilter, DeviceIdentity, DeviceStability, DiscoveryConfig, CancellationToken,
+    BaudCandidate, CloseReason, ControlLines, Direction, EventReceiver, FrameStatus, PortConfig, RecvError, ReplayTiming, ResetSequence,
+    SendMode, SessionHandle, SessionId, SessionStats, Timeline, TimelineEntry, TransmitId, TransmitOptions, TransmitOutcome,
+};
+#[cfg(unix)]
+use serial_manager::{CaptureReader, Replay};
+
+pub mod export;
+pub mod history;
+pub mod profile;
+pub mod scrollback;
+pub mod search;
+
+use export::{ExportOptions, ExportRange, ScrollbackExport};
+use history::{CommandHistory, HistoryEntry};
+use profile::{ConnectionProfile, ProfileStore};
+use scrollback::{Scrollback, ScrollbackLine};
+use search::{ScrollbackSearch, SearchQuery};
 
 /// Serializable version of SerialEvent for Tauri frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum TauriSerialEvent {
     DeviceArrived { 
-        port_name: String, 
+        port: PortInfo, 
         timestamp: u64 // Unix timestamp in milliseconds
     },
     DeviceDeparted { 
-        port_name: String, 
+        port: PortInfo, 
         timestamp: u64 
     },
+    DeviceMoved {
+        from: PortInfo,
+        to: PortInfo,
+        identity: DeviceIdentity,
+        timestamp: u64
+    },
+    DeviceReenumerated {
+        port: PortInfo,
+        identity: DeviceIdentity,
+        stability: DeviceStability,
+        timestamp: u64
+    },
+    DiscoveryError {
+        message: String,
+        timestamp: u64
+    },
+    SessionClosed {
+        session: SessionId,
+        port_name: String,
+        reason: String,
+        timestamp: u64
+    },
+    DataReceived {
+        session: SessionId,
+        bytes: Vec<u8>,
+        host_timestamp: u64
+    },
+    DataSent {
+        session: SessionId,
+        bytes: Vec<u8>,
+        host_timestamp: u64
+    },
+    FrameReceived {
+        session: SessionId,
+        bytes: Vec<u8>,
+        status: FrameStatus,
+        crc_ok: Option<bool>,
+        host_timestamp: u64
+    },
+    ControlLinesChanged {
+        session: SessionId,
+        lines: ControlLines,
+        timestamp: u64
+    },
+    TransmitProgress {
+        session: SessionId,
+        transmit: TransmitId,
+        sent: u64,
+        total: u64,
+        timestamp: u64
+    },
+    TransmitFinished {
+        session: SessionId,
+        transmit: TransmitId,
+        completed: bool,
+        outcome: String,
+        timestamp: u64
+    },
+    SessionDisconnected {
+        session: SessionId,
+        port_name: String,
+        reason: String,
+        timestamp: u64
+    },
+    SessionReconnected {
+        session: SessionId,
+        port_name: String,
+        timestamp: u64
+    },
+    DataGap {
+        session: SessionId,
+        since: u64,
+        until: u64
+    },
+}
+
+/// Describes why a session closed, for display in the frontend
+fn describe_close_reason(reason: &CloseReason) -> String {
+    match reason {
+        CloseReason::Closed => "closed".to_string(),
+        CloseReason::DeviceDeparted => "device disconnected".to_string(),
+        CloseReason::Error(error) => error.to_string(),
+    }
+}
+
+/// Describes how a transmission ended, for display in the frontend
+fn describe_transmit_outcome(outcome: &TransmitOutcome) -> String {
+    match outcome {
+        TransmitOutcome::Completed => "completed".to_string(),
+        TransmitOutcome::Cancelled => "cancelled".to_string(),
+        TransmitOutcome::PromptTimedOut { line } => format!("no prompt after line {}", line),
+        TransmitOutcome::Failed(error) => error.to_string(),
+    }
+}
+
+/// Converts a SystemTime into a Unix timestamp in milliseconds
+fn unix_millis(timestamp: SystemTime) -> u64 {
+    timestamp
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
 }
 
 impl From<SerialEvent> for TauriSerialEvent {
     fn from(event: SerialEvent) -> Self {
         match event {
-            SerialEvent::DeviceArrived { port_name, timestamp } => {
-                let timestamp_ms = timestamp
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis() as u64;
-                
-                TauriSerialEvent::DeviceArrived { port_name, timestamp: timestamp_ms }
+            SerialEvent::DeviceArrived { port, timestamp } => {
+                TauriSerialEvent::DeviceArrived { port, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DeviceDeparted { port, timestamp } => {
+                TauriSerialEvent::DeviceDeparted { port, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DeviceMoved { from, to, identity, timestamp } => {
+                TauriSerialEvent::DeviceMoved { from, to, identity, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DeviceReenumerated { port, identity, stability, timestamp } => {
+                TauriSerialEvent::DeviceReenumerated { port, identity, stability, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DiscoveryError { error, timestamp } => {
+                TauriSerialEvent::DiscoveryError { message: error.to_string(), timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DataReceived { session, bytes, host_timestamp, .. } => {
+                TauriSerialEvent::DataReceived { session, bytes, host_timestamp: unix_millis(host_timestamp) }
             },
-            SerialEvent::DeviceDeparted { port_name, timestamp } => {
-                let timestamp_ms = timestamp
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis() as u64;
-                
-                TauriSerialEvent::DeviceDeparted { port_name, timestamp: timestamp_ms }
+            SerialEvent::DataSent { session, bytes, host_timestamp, .. } => {
+                TauriSerialEvent::DataSent { session, bytes, host_timestamp: unix_millis(host_timestamp) }
+            },
+            SerialEvent::FrameReceived { session, frame, host_timestamp, .. } => {
+                TauriSerialEvent::FrameReceived {
+                    session,
+                    bytes: frame.bytes,
+                    status: frame.status,
+                    crc_ok: frame.crc_ok,
+                    host_timestamp: unix_millis(host_timestamp)
+                }
+            },
+            SerialEvent::ControlLinesChanged { session, lines, timestamp } => {
+                TauriSerialEvent::ControlLinesChanged { session, lines, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::TransmitProgress { session, transmit, sent, total, timestamp } => {
+                TauriSerialEvent::TransmitProgress { session, transmit, sent, total, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::TransmitFinished { session, transmit, outcome, timestamp } => {
+                TauriSerialEvent::TransmitFinished {
+                    session,
+                    transmit,
+                    completed: outcome == TransmitOutcome::Completed,
+                    outcome: describe_transmit_outcome(&outcome),
+                    timestamp: unix_millis(timestamp)
+                }
+            },
+            SerialEvent::SessionDisconnected { session, port_name, reason, timestamp } => {
+                TauriSerialEvent::SessionDisconnected {
+                    session,
+                    port_name,
+                    reason: describe_close_reason(&reason),
+                    timestamp: unix_millis(timestamp)
+                }
+            },
+            SerialEvent::SessionReconnected { session, port_name, timestamp } => {
+                TauriSerialEvent::SessionReconnected { session, port_name, timestamp: unix_millis(timestamp) }
+            },
+            SerialEvent::DataGap { session, since, until } => {
+                TauriSerialEvent::DataGap { session, since: unix_millis(since), until: unix_millis(until) }
+            },
+            SerialEvent::SessionClosed { session, port_name, reason, timestamp } => {
+                TauriSerialEvent::SessionClosed {
+                    session,
+                    port_name,
+                    reason: describe_close_reason(&reason),
+                    timestamp: unix_millis(timestamp)
+                }
             },
         }
     }
 }
 
 /// Forwards serial device events to the Tauri frontend
-fn start_event_forwarder(app_handle: AppHandle, receiver: Receiver<SerialEvent>) {
-    thread::spawn(move || {
-        println!("🎧 Serial device event forwarder started");
+fn start_event_forwarder(app_handle: AppHandle, mut events: impl Stream<Item = SerialEvent> + Send + Unpin + 'static) {
+    tauri::async_runtime::spawn(async move {
+        log::info!("🎧 Serial device event forwarder started");
         
+        // The stream ends once the manager is dropped
+        while let Some(event) = events.next().await {
+            // Convert to serializable format
+            let tauri_event = TauriSerialEvent::from(event.clone());
+            
+            // Log to console for debugging
+            match &event {
+                SerialEvent::DeviceArrived { port, timestamp } => {
+                    log::info!("🔌 Device CONNECTED: {} ({}) at {:?}", port.port_name, port.display_name(), timestamp);
+                }
+                SerialEvent::DeviceDeparted { port, timestamp } => {
+                    log::info!("🔌 Device DISCONNECTED: {} ({}) at {:?}", port.port_name, port.display_name(), timestamp);
+                }
+                SerialEvent::DeviceMoved { from, to, timestamp, .. } => {
+                    log::info!("🔌 Device MOVED: {} -> {} ({}) at {:?}", from.port_name, to.port_name, to.display_name(), timestamp);
+                }
+                SerialEvent::DeviceReenumerated { port, stability, timestamp, .. } => {
+                    log::info!("🔌 Device RE-ENUMERATED: {} ({} times{}) at {:?}", port.port_name, stability.reenumerations,
+                        if stability.unstable { ", unstable" } else { "" }, timestamp);
+                }
+                SerialEvent::DiscoveryError { error, timestamp } => {
+                    log::warn!("🔌 Discovery ERROR: {} at {:?}", error, timestamp);
+                }
+                SerialEvent::DataReceived { session, bytes, .. } => {
+                    log::trace!("🔌 Data RECEIVED: {} bytes on {}", bytes.len(), session);
+                }
+                SerialEvent::DataSent { session, bytes, .. } => {
+                    log::trace!("🔌 Data SENT: {} bytes on {}", bytes.len(), session);
+                }
+                SerialEvent::FrameReceived { session, frame, .. } => {
+                    log::trace!("🔌 Frame RECEIVED: {} bytes on {} ({:?})", frame.bytes.len(), session, frame.status);
+                }
+                SerialEvent::ControlLinesChanged { session, lines, .. } => {
+                    log::debug!("🔌 Control lines CHANGED: {} CTS={} DSR={} RI={} CD={}", session, lines.cts, lines.dsr, lines.ri, lines.cd);
+                }
+                SerialEvent::TransmitProgress { session, transmit, sent, total, .. } => {
+                    log::debug!("🔌 Transmit PROGRESS: {} on {}, {} of {} bytes", transmit, session, sent, total);
+                }
+                SerialEvent::TransmitFinished { session, transmit, outcome, .. } => {
+                    log::info!("🔌 Transmit FINISHED: {} on {} ({})", transmit, session, describe_transmit_outcome(outcome));
+                }
+                SerialEvent::SessionDisconnected { session, port_name, reason, timestamp } => {
+                    log::warn!("🔌 Session DISCONNECTED: {} on {} ({}) at {:?}", session, port_name, describe_close_reason(reason), timestamp);
+                }
+                SerialEvent::SessionReconnected { session, port_name, timestamp } => {
+                    log::info!("🔌 Session RECONNECTED: {} on {} at {:?}", session, port_name, timestamp);
+                }
+                SerialEvent::DataGap { session, since, until } => {
+                    log::info!("🔌 Data GAP: {} from {:?} to {:?}", session, since, until);
+                }
+                SerialEvent::SessionClosed { session, port_name, reason, timestamp } => {
+                    log::info!("🔌 Session CLOSED: {} on {} ({}) at {:?}", session, port_name, describe_close_reason(reason), timestamp);
+                }
+            }
+            
+            // Emit to frontend; the data stream goes to its own channel so device listeners never see it
+            let channel = match tauri_event {
+                TauriSerialEvent::DataReceived { .. }
+                | TauriSerialEvent::DataSent { .. }
+                | TauriSerialEvent::FrameReceived { .. }
+                | TauriSerialEvent::DataGap { .. } => "serial-data",
+                _ => "serial-device-event",
+            };
+            if let Err(e) = app_handle.emit(channel, &tauri_event) {
+                log::error!("Failed to emit serial event: {}", e);
+            }
+        }
+        
+        log::info!("🎧 Serial device event forwarder stopped");
+    });
+}
+
+/// One row of the combined log of all ports, for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct TauriTimelineEntry {
+    session: SessionId,
+    port_name: String,
+    direction: Direction,
+    bytes: Vec<u8>,
+    status: Option<FrameStatus>,
+    crc_ok: Option<bool>,
+    /// Milliseconds since the manager started, on the clock all ports share
+    elapsed_ms: f64,
+    timestamp: u64,
+}
+
+impl From<TimelineEntry> for TauriTimelineEntry {
+    fn from(entry: TimelineEntry) -> Self {
+        TauriTimelineEntry {
+            session: entry.session,
+            port_name: entry.port_name,
+            direction: entry.direction,
+            bytes: entry.bytes,
+            status: entry.frame_status,
+            crc_ok: entry.crc_ok,
+            elapsed_ms: entry.elapsed.as_secs_f64() * 1000.0,
+            timestamp: unix_millis(entry.host_timestamp),
+        }
+    }
+}
+
+/// Forwards the merged, time-ordered data of all sessions to the frontend in batches
+fn start_timeline_forwarder(app_handle: AppHandle, mut timeline: Timeline) {
+    std::thread::spawn(move || {
+        log::info!("🎧 Serial timeline forwarder started");
         loop {
-            // Block and wait for events directly on the receiver
-            match receiver.recv() {
-                Ok(event) => {
-                    // Convert to serializable format
-                    let tauri_event = TauriSerialEvent::from(event.clone());
-                    
-                    // Log to console for debugging
-                    match &event {
-                        SerialEvent::DeviceArrived { port_name, timestamp } => {
-                            println!("🔌 Device CONNECTED: {} at {:?}", port_name, timestamp);
-                        }
-                        SerialEvent::DeviceDeparted { port_name, timestamp } => {
-                            println!("🔌 Device DISCONNECTED: {} at {:?}", port_name, timestamp);
+            match timeline.recv_timeout(Duration::from_millis(100)) {
+                Ok(entries) if entries.is_empty() => {}
+                Ok(entries) => {
+                    let batch: Vec<TauriTimelineEntry> = entries.into_iter().map(TauriTimelineEntry::from).collect();
+                    if let Err(e) = app_handle.emit("serial-timeline", &batch) {
+                        log::error!("Failed to emit timeline entries: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => log::warn!("Serial timeline fell behind, {} events dropped", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+        log::info!("🎧 Serial timeline forwarder stopped");
+    });
+}
+
+/// Scrollback of every session that received or sent data, kept after the
+/// session closes so its log can still be read
+struct Scrollbacks {
+    dir: PathBuf,
+    /// Each scrollback has its own lock, so a search or export going through
+    /// one does not hold up the others
+    stores: Mutex<HashMap<SessionId, Arc<Mutex<Scrollback>>>>,
+    /// Port each session last had open, for exports
+    port_names: Mutex<HashMap<SessionId, String>>,
+    next_export: AtomicU64,
+}
+
+impl Scrollbacks {
+    /// Stores scrollbacks in `dir`, removing any left behind by an earlier run.
+    fn new(dir: PathBuf) -> Self {
+        if dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                log::warn!("Failed to clear old scrollbacks in {}: {}", dir.display(), e);
+            }
+        }
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("Failed to create scrollback directory {}: {}", dir.display(), e);
+        }
+        Scrollbacks {
+            dir,
+            stores: Mutex::new(HashMap::new()),
+            port_names: Mutex::new(HashMap::new()),
+            next_export: AtomicU64::new(0),
+        }
+    }
+
+    /// Appends timeline entries to the scrollbacks of their sessions and
+    /// returns the sessions that got new data.
+    fn append(&self, entries: &[TimelineEntry]) -> HashSet<SessionId> {
+        let mut changed = HashSet::new();
+        for entry in entries {
+            {
+                let mut port_names = self.port_names.lock().unwrap();
+                if port_names.get(&entry.session) != Some(&entry.port_name) {
+                    port_names.insert(entry.session, entry.port_name.clone());
+                }
+            }
+            let Some(scrollback) = self.get_or_create(entry.session) else {
+                continue;
+            };
+            let mut scrollback = scrollback.lock().unwrap();
+            let result = match entry.frame_status {
+                Some(_) => scrollback.append_line(entry.direction, entry.host_timestamp, &entry.bytes),
+                None => scrollback.append(entry.direction, entry.host_timestamp, &entry.bytes),
+            };
+            match result {
+                Ok(()) => {
+                    changed.insert(entry.session);
+                }
+                Err(e) => log::error!("Failed to append to scrollback of {}: {}", entry.session, e),
+            }
+        }
+        changed
+    }
+
+    /// Returns the scrollback of a session, if anything was logged on it.
+    fn get(&self, session: SessionId) -> Option<Arc<Mutex<Scrollback>>> {
+        self.stores.lock().unwrap().get(&session).cloned()
+    }
+
+    /// Returns the scrollback of a session, creating it if needed.
+    fn get_or_create(&self, session: SessionId) -> Option<Arc<Mutex<Scrollback>>> {
+        match self.stores.lock().unwrap().entry(session) {
+            Entry::Occupied(store) => Some(store.get().clone()),
+            Entry::Vacant(vacant) => match Scrollback::create(self.dir.join(format!("session-{}.log", session))) {
+                Ok(scrollback) => Some(vacant.insert(Arc::new(Mutex::new(scrollback))).clone()),
+                Err(e) => {
+                    log::error!("Failed to create scrollback for {}: {}", session, e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Writes a marker line into every scrollback where `missed` timeline
+    /// events were dropped, and returns the sessions that got it. The
+    /// timeline does not say whose data was lost, so every session is marked.
+    fn mark_gap(&self, missed: u64) -> Vec<SessionId> {
+        let marker = format!("--- {} events dropped, the log is incomplete here ---", missed);
+        let now = SystemTime::now();
+        let stores: Vec<_> = self.stores.lock().unwrap().iter().map(|(&session, store)| (session, store.clone())).collect();
+        let mut marked = Vec::new();
+        for (session, scrollback) in stores {
+            match scrollback.lock().unwrap().append_line(Direction::Rx, now, marker.as_bytes()) {
+                Ok(()) => marked.push(session),
+                Err(e) => log::error!("Failed to mark the gap in the scrollback of {}: {}", session, e),
+            }
+        }
+        marked
+    }
+
+    fn len(&self, session: SessionId) -> u64 {
+        self.get(session).map_or(0, |scrollback| scrollback.lock().unwrap().len())
+    }
+}
+
+/// Sent on "serial-log-appended" when lines were added to a session's scrollback
+#[derive(Debug, Clone, Serialize)]
+struct TauriLogAppended {
+    session: SessionId,
+    /// Number of lines now in the scrollback
+    length: u64,
+}
+
+/// Sent on "serial-log-incomplete" when the scrollback writer fell behind
+/// and data is missing from a session's scrollback
+#[derive(Debug, Clone, Serialize)]
+struct TauriLogIncomplete {
+    session: SessionId,
+    /// Number of timeline events dropped, across all sessions
+    missed: u64,
+}
+
+fn emit_log_appended(app_handle: &AppHandle, scrollbacks: &Scrollbacks, session: SessionId) {
+    let appended = TauriLogAppended { session, length: scrollbacks.len(session) };
+    if let Err(e) = app_handle.emit("serial-log-appended", &appended) {
+        log::error!("Failed to emit log length: {}", e);
+    }
+}
+
+/// Appends the data of all sessions to their scrollbacks, telling the
+/// frontend which logs grew at most every 100ms. If the writer falls
+/// behind, the lost data is marked in every scrollback.
+fn start_scrollback_writer(app_handle: AppHandle, mut timeline: Timeline) {
+    std::thread::spawn(move || {
+        log::info!("🎧 Serial scrollback writer started");
+        let scrollbacks = app_handle.state::<Scrollbacks>();
+        loop {
+            match timeline.recv_timeout(Duration::from_millis(100)) {
+                Ok(entries) if entries.is_empty() => {}
+                Ok(entries) => {
+                    for session in scrollbacks.append(&entries) {
+                        emit_log_appended(&app_handle, &scrollbacks, session);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Serial scrollback fell behind, {} events dropped", missed);
+                    for session in scrollbacks.mark_gap(missed) {
+                        emit_log_appended(&app_handle, &scrollbacks, session);
+                        if let Err(e) = app_handle.emit("serial-log-incomplete", &TauriLogIncomplete { session, missed }) {
+                            log::error!("Failed to emit incomplete log: {}", e);
                         }
                     }
-                    
-                    // Emit to frontend
-                    if let Err(e) = app_handle.emit("serial-device-event", &tauri_event) {
-                        eprintln!("Failed to emit serial event: {}", e);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+        log::info!("🎧 Serial scrollback writer stopped");
+    });
+}
+
+#[command]
+fn get_serial_ports(manager: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<Vec<PortInfo>, String> {
+    manager.lock().unwrap().get_ports().map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_port_filter(manager: tauri::State<Arc<Mutex<SerialManager>>>) -> PortFilter {
+    manager.lock().unwrap().filter()
+}
+
+#[command]
+fn set_port_filter(manager: tauri::State<Arc<Mutex<SerialManager>>>, filter: PortFilter) {
+    manager.lock().unwrap().set_filter(filter);
+}
+
+#[command]
+fn pause_discovery(manager: tauri::State<Arc<Mutex<SerialManager>>>) {
+    manager.lock().unwrap().pause();
+}
+
+#[command]
+fn resume_discovery(manager: tauri::State<Arc<Mutex<SerialManager>>>) {
+    manager.lock().unwrap().resume();
+}
+
+#[command]
+fn rescan_ports(manager: tauri::State<Arc<Mutex<SerialManager>>>) {
+    manager.lock().unwrap().rescan_now();
+}
+
+#[command]
+fn get_scan_interval(manager: tauri::State<Arc<Mutex<SerialManager>>>) -> u64 {
+    manager.lock().unwrap().scan_interval().as_millis() as u64
+}
+
+#[command]
+fn set_scan_interval(manager: tauri::State<Arc<Mutex<SerialManager>>>, interval_ms: u64) {
+    manager.lock().unwrap().set_scan_interval(Duration::from_millis(interval_ms));
+}
+
+#[command]
+fn open_port(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    port_name: String,
+    config: PortConfig,
+    auto_reconnect: Option<bool>
+) -> Result<SessionId, String> {
+    let session = manager.lock().unwrap()
+        .open(&port_name, config)
+        .map_err(|e| e.to_string())?;
+    session.set_auto_reconnect(auto_reconnect.unwrap_or(false));
+    Ok(session.id())
+}
+
+// Detection listens for a while at every rate, so it runs off the main thread
+#[command]
+async fn detect_baud(port_name: String, candidates: Option<Vec<u32>>, duration_ms: u64) -> Result<Vec<BaudCandidate>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        serial_manager::detect_baud(&port_name, &candidates.unwrap_or_default(), Duration::from_millis(duration_ms))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[command]
+fn close_port(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId) -> Result<(), String> {
+    // Look the session up first so the manager is not locked while the reader stops
+    let session = manager.lock().unwrap().session(session).map_err(|e| e.to_string())?;
+    if let Some(session) = session {
+        session.close();
+    }
+    Ok(())
+}
+
+/// Looks up an open session, so that the manager is not locked while using it
+fn find_session(manager: &Mutex<SerialManager>, session: SessionId) -> Result<SessionHandle, String> {
+    manager.lock().unwrap()
+        .session(session)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is not open", session))
+}
+
+#[command]
+fn write_port(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId, data: Vec<u8>) -> Result<(), String> {
+    find_session(&manager, session)?.write(&data).map_err(|e| e.to_string())
+}
+
+#[command]
+fn set_control_lines(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    session: SessionId,
+    dtr: Option<bool>,
+    rts: Option<bool>
+) -> Result<(), String> {
+    let session = find_session(&manager, session)?;
+    if let Some(dtr) = dtr {
+        session.set_dtr(dtr).map_err(|e| e.to_string())?;
+    }
+    if let Some(rts) = rts {
+        session.set_rts(rts).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[command]
+fn get_control_lines(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId) -> Result<ControlLines, String> {
+    find_session(&manager, session)?.control_lines().map_err(|e| e.to_string())
+}
+
+#[command]
+fn watch_control_lines(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId, enabled: bool) -> Result<(), String> {
+    find_session(&manager, session)?.watch_control_lines(enabled);
+    Ok(())
+}
+
+#[command]
+fn list_reset_sequences() -> Vec<ResetSequence> {
+    ResetSequence::builtin()
+}
+
+// Breaks and reset sequences sleep between steps, so they run off the main thread
+#[command]
+async fn send_break(
+    manager: tauri::State<'_, Arc<Mutex<SerialManager>>>,
+    session: SessionId,
+    duration_ms: u64
+) -> Result<(), String> {
+    let session = find_session(&manager, session)?;
+    tauri::async_runtime::spawn_blocking(move || session.send_break(Duration::from_millis(duration_ms)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+async fn run_reset_sequence(
+    manager: tauri::State<'_, Arc<Mutex<SerialManager>>>,
+    session: SessionId,
+    name: String
+) -> Result<(), String> {
+    let sequence = ResetSequence::find(&name).ok_or_else(|| format!("unknown reset sequence {}", name))?;
+    let session = find_session(&manager, session)?;
+    tauri::async_runtime::spawn_blocking(move || session.run_reset_sequence(&sequence))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_session_stats(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId) -> Result<SessionStats, String> {
+    manager.lock().unwrap()
+        .session_stats(session)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is not open", session))
+}
+
+/// Loopback devices behind virtual ports, keyed by port name, each with the flag that stops it
+#[derive(Default)]
+struct VirtualPorts(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// Creates a virtual port whose device echoes back whatever is sent to it
+#[cfg(unix)]
+#[command]
+fn create_virtual_port(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    virtual_ports: tauri::State<VirtualPorts>,
+    name: String
+) -> Result<PortInfo, String> {
+    use std::io::{ErrorKind, Read, Write};
+
+    let mut device = manager.lock().unwrap().create_virtual_port(&name).map_err(|e| e.to_string())?;
+    device.set_timeout(Duration::from_millis(100)).map_err(|e| e.to_string())?;
+    let port = device.port().clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    virtual_ports.0.lock().unwrap().insert(port.port_name.clone(), stop.clone());
+
+    std::thread::spawn(move || {
+        let mut buffer = [0; 1024];
+        while !stop.load(Ordering::Relaxed) {
+            match device.read(&mut buffer) {
+                Ok(0) => {}
+                Ok(n) => {
+                    if let Err(e) = device.write_all(&buffer[..n]) {
+                        log::warn!("Virtual port {} failed to echo: {}", device.name(), e);
                     }
                 }
-                Err(_) => {
-                    println!("🔌 Event channel closed, stopping forwarder");
-                    break; // Channel closed, exit gracefully
+                // Reads fail while no session has the port open
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(_) => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+        // Dropping the device removes the port
+    });
+    Ok(port)
+}
+
+#[cfg(not(unix))]
+#[command]
+fn create_virtual_port(_name: String) -> Result<PortInfo, String> {
+    Err("virtual ports are not supported on this platform".to_string())
+}
+
+#[command]
+fn destroy_virtual_port(virtual_ports: tauri::State<VirtualPorts>, port_name: String) -> Result<(), String> {
+    let stop = virtual_ports.0.lock().unwrap()
+        .remove(&port_name)
+        .ok_or_else(|| format!("{} is not a virtual port", port_name))?;
+    stop.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[command]
+fn transmit(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    session: SessionId,
+    data: Vec<u8>,
+    options: TransmitOptions
+) -> Result<TransmitId, String> {
+    find_session(&manager, session)?.transmit(&data, options).map_err(|e| e.to_string())
+}
+
+#[command]
+fn transmit_file(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    session: SessionId,
+    path: String,
+    options: TransmitOptions
+) -> Result<TransmitId, String> {
+    find_session(&manager, session)?.transmit_file(&path, options).map_err(|e| e.to_string())
+}
+
+#[command]
+fn cancel_transmit(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId, transmit: TransmitId) -> Result<bool, String> {
+    Ok(find_session(&manager, session)?.cancel_transmit(transmit))
+}
+
+#[command]
+fn start_capture(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId, path: String) -> Result<(), String> {
+    find_session(&manager, session)?.start_capture(&path).map_err(|e| e.to_string())
+}
+
+#[command]
+fn stop_capture(manager: tauri::State<Arc<Mutex<SerialManager>>>, session: SessionId) -> Result<(), String> {
+    find_session(&manager, session)?.stop_capture().map_err(|e| e.to_string())
+}
+
+/// Captures being replayed through virtual ports, keyed by port name
+#[cfg(unix)]
+#[derive(Default)]
+struct Replays(Mutex<HashMap<String, Replay>>);
+
+/// Creates a virtual port that replays a capture file once start_replay is called
+#[cfg(unix)]
+#[command]
+fn replay_capture(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    replays: tauri::State<Replays>,
+    name: String,
+    path: String,
+    timing: ReplayTiming
+) -> Result<PortInfo, String> {
+    let capture = CaptureReader::open(&path).map_err(|e| e.to_string())?;
+    let replay = manager.lock().unwrap().replay_capture(&name, capture, timing).map_err(|e| e.to_string())?;
+    let port = replay.port().clone();
+    replays.0.lock().unwrap().insert(port.port_name.clone(), replay);
+    Ok(port)
+}
+
+#[cfg(unix)]
+#[command]
+fn start_replay(replays: tauri::State<Replays>, port_name: String) -> Result<(), String> {
+    replays.0.lock().unwrap()
+        .get(&port_name)
+        .ok_or_else(|| format!("{} is not replaying a capture", port_name))?
+        .start();
+    Ok(())
+}
+
+/// Stops a replay and removes its virtual port
+#[cfg(unix)]
+#[command]
+fn stop_replay(replays: tauri::State<Replays>, port_name: String) -> Result<(), String> {
+    let replay = replays.0.lock().unwrap()
+        .remove(&port_name)
+        .ok_or_else(|| format!("{} is not replaying a capture", port_name))?;
+    // Dropping the replay waits for its thread, so do it without holding the map
+    drop(replay);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+#[command]
+fn replay_capture(_name: String, _path: String, _timing: ReplayTiming) -> Result<PortInfo, String> {
+    Err("replaying captures is not supported on this platform".to_string())
+}
+
+#[cfg(not(unix))]
+#[command]
+fn start_replay(_port_name: String) -> Result<(), String> {
+    Err("replaying captures is not supported on this platform".to_string())
+}
+
+#[cfg(not(unix))]
+#[command]
+fn stop_replay(_port_name: String) -> Result<(), String> {
+    Err("replaying captures is not supported on this platform".to_string())
+}
+
+/// A line of a session's log, for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct TauriLogLine {
+    direction: Direction,
+    timestamp: u64,
+    text: String,
+}
+
+impl From<ScrollbackLine> for TauriLogLine {
+    fn from(line: ScrollbackLine) -> Self {
+        TauriLogLine { direction: line.direction, timestamp: unix_millis(line.timestamp), text: line.text }
+    }
+}
+
+#[command]
+fn get_log_chunk(scrollbacks: tauri::State<Scrollbacks>, session: SessionId, offset: u64, limit: usize) -> Result<Vec<TauriLogLine>, String> {
+    let Some(scrollback) = scrollbacks.get(session) else {
+        return Ok(Vec::new());
+    };
+    let lines = scrollback.lock().unwrap().lines(offset, limit).map_err(|e| e.to_string())?;
+    Ok(lines.into_iter().map(TauriLogLine::from).collect())
+}
+
+#[command]
+fn get_log_length(scrollbacks: tauri::State<Scrollbacks>, session: SessionId) -> u64 {
+    scrollbacks.len(session)
+}
+
+/// Which lines to export, with times in milliseconds since the Unix epoch
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TauriExportRange {
+    All,
+    Lines { start: u64, end: u64 },
+    Time { from: u64, until: u64 },
+}
+
+impl From<TauriExportRange> for ExportRange {
+    fn from(range: TauriExportRange) -> Self {
+        let time = |millis| std::time::UNIX_EPOCH + Duration::from_millis(millis);
+        match range {
+            TauriExportRange::All => ExportRange::All,
+            TauriExportRange::Lines { start, end } => ExportRange::Lines { start, end },
+            TauriExportRange::Time { from, until } => ExportRange::Time { from: time(from), until: time(until) },
+        }
+    }
+}
+
+/// Sent on "serial-export-progress" as an export is written
+#[derive(Debug, Clone, Serialize)]
+struct TauriExportProgress {
+    session: SessionId,
+    export: u64,
+    exported: u64,
+    total: u64,
+    finished: bool,
+    /// Why the export stopped, if it failed
+    error: Option<String>,
+}
+
+/// Lines an export writes before letting data be appended again
+const EXPORT_STEP: usize = 10_000;
+
+/// Writes an export in steps, reporting its progress after each
+fn run_export(app_handle: AppHandle, session: SessionId, id: u64, mut export: ScrollbackExport<std::io::BufWriter<std::fs::File>>) {
+    let scrollbacks = app_handle.state::<Scrollbacks>();
+    let emit = |progress: TauriExportProgress| {
+        if let Err(e) = app_handle.emit("serial-export-progress", &progress) {
+            log::error!("Failed to emit export progress: {}", e);
+        }
+    };
+    let result = loop {
+        if export.is_finished() {
+            break Ok(());
+        }
+        let step = match scrollbacks.get(session) {
+            Some(scrollback) => export.step(&mut scrollback.lock().unwrap(), EXPORT_STEP).map_err(|e| e.to_string()),
+            None => Err(format!("the log of {} is gone", session)),
+        };
+        if let Err(e) = step {
+            break Err(e);
+        }
+        emit(TauriExportProgress { session, export: id, exported: export.exported(), total: export.total(), finished: false, error: None });
+    };
+
+    let (exported, total) = (export.exported(), export.total());
+    let error = result.and_then(|()| export.finish().map(drop).map_err(|e| e.to_string())).err();
+    match &error {
+        Some(e) => log::error!("Export of {} failed: {}", session, e),
+        None => log::info!("Exported {} lines of {}", exported, session),
+    }
+    emit(TauriExportProgress { session, export: id, exported, total, finished: true, error });
+}
+
+/// Starts exporting a range of a session's log to `path` in the background.
+/// Returns the id its "serial-export-progress" events carry.
+#[command]
+fn export_session(
+    app_handle: AppHandle,
+    scrollbacks: tauri::State<Scrollbacks>,
+    session: SessionId,
+    path: String,
+    range: TauriExportRange,
+    options: ExportOptions
+) -> Result<u64, String> {
+    let port_name = scrollbacks.port_names.lock().unwrap().get(&session).cloned().unwrap_or_default();
+    let export = {
+        let store = scrollbacks.get(session).ok_or_else(|| format!("nothing was logged on {}", session))?;
+        let mut scrollback = store.lock().unwrap();
+        let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+        ScrollbackExport::new(std::io::BufWriter::new(file), &mut scrollback, &port_name, range.into(), options)
+            .map_err(|e| e.to_string())?
+    };
+    let id = scrollbacks.next_export.fetch_add(1, Ordering::Relaxed) + 1;
+    std::thread::spawn(move || run_export(app_handle, session, id, export));
+    Ok(id)
+}
+
+/// Lines a search goes through before letting data be appended again
+const SEARCH_STEP: usize = 10_000;
+
+/// The search running on each session's scrollback
+#[derive(Default)]
+struct Searches {
+    active: Mutex<HashMap<SessionId, ActiveSearch>>,
+    next_id: AtomicU64,
+}
+
+struct ActiveSearch {
+    id: u64,
+    search: Arc<Mutex<ScrollbackSearch>>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Sent on "serial-search-matches" as a search goes through a scrollback
+#[derive(Debug, Clone, Serialize)]
+struct TauriSearchMatches {
+    session: SessionId,
+    search: u64,
+    /// Lines found to match since the last event, in order
+    lines: Vec<u64>,
+    /// Lines searched so far
+    searched: u64,
+    /// Complete lines in the scrollback
+    total: u64,
+}
+
+/// Searches the next lines of a session's scrollback, returning None if there were none
+fn search_step(
+    scrollback: &Mutex<Scrollback>,
+    session: SessionId,
+    id: u64,
+    search: &Mutex<ScrollbackSearch>
+) -> std::io::Result<Option<TauriSearchMatches>> {
+    let mut scrollback = scrollback.lock().unwrap();
+    let mut search = search.lock().unwrap();
+    let before = search.searched();
+    let lines = search.scan(&mut scrollback, SEARCH_STEP)?;
+    if search.searched() == before {
+        return Ok(None);
+    }
+    Ok(Some(TauriSearchMatches { session, search: id, lines, searched: search.searched(), total: scrollback.complete_len() }))
+}
+
+/// Searches a session's scrollback in steps until stopped, then keeps
+/// searching the lines appended after it caught up. Stops if the scrollback
+/// is gone.
+fn run_search(app_handle: AppHandle, session: SessionId, id: u64, search: Arc<Mutex<ScrollbackSearch>>, stop: Arc<AtomicBool>) {
+    let scrollbacks = app_handle.state::<Scrollbacks>();
+    while !stop.load(Ordering::Relaxed) {
+        let Some(scrollback) = scrollbacks.get(session) else {
+            log::info!("Stopped searching {}, its log is gone", session);
+            break;
+        };
+        match search_step(&scrollback, session, id, &search) {
+            Ok(Some(matches)) => {
+                if let Err(e) = app_handle.emit("serial-search-matches", &matches) {
+                    log::error!("Failed to emit search matches: {}", e);
+                }
+                if matches.searched < matches.total {
+                    continue;
                 }
             }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Search of {} failed: {}", session, e);
+                break;
+            }
         }
-        
-        println!("🎧 Serial device event forwarder stopped");
+        // Caught up; wait for more lines
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Starts searching a session's log in the background, replacing its previous search.
+/// Returns the id its "serial-search-matches" events carry.
+#[command]
+fn start_search(
+    app_handle: AppHandle,
+    scrollbacks: tauri::State<Scrollbacks>,
+    searches: tauri::State<Searches>,
+    session: SessionId,
+    query: SearchQuery
+) -> Result<u64, String> {
+    if scrollbacks.get(session).is_none() {
+        return Err(format!("nothing was logged on {}", session));
+    }
+    let search = Arc::new(Mutex::new(ScrollbackSearch::new(&query).map_err(|e| e.to_string())?));
+    let id = searches.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let stop = Arc::new(AtomicBool::new(false));
+    let active = ActiveSearch { id, search: search.clone(), stop: stop.clone() };
+    if let Some(previous) = searches.active.lock().unwrap().insert(session, active) {
+        previous.stop.store(true, Ordering::Relaxed);
+    }
+    std::thread::spawn(move || run_search(app_handle, session, id, search, stop));
+    Ok(id)
+}
+
+#[command]
+fn stop_search(searches: tauri::State<Searches>, session: SessionId) {
+    if let Some(active) = searches.active.lock().unwrap().remove(&session) {
+        active.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `f` on the search of a session
+fn with_search<T>(searches: &Searches, session: SessionId, f: impl FnOnce(&ScrollbackSearch) -> T) -> Result<T, String> {
+    let active = searches.active.lock().unwrap();
+    let active = active.get(&session).ok_or_else(|| format!("no search on {}", session))?;
+    let search = active.search.lock().unwrap();
+    Ok(f(&search))
+}
+
+/// Returns the first match after line `from`, wrapping around to the first match
+#[command]
+fn next_match(searches: tauri::State<Searches>, session: SessionId, from: u64) -> Result<Option<u64>, String> {
+    with_search(&searches, session, |search| search.next_match(from))
+}
+
+/// Returns the last match before line `from`, wrapping around to the last match
+#[command]
+fn previous_match(searches: tauri::State<Searches>, session: SessionId, from: u64) -> Result<Option<u64>, String> {
+    with_search(&searches, session, |search| search.previous_match(from))
+}
+
+/// Loads a file from the app config directory. A file that cannot be loaded
+/// is moved aside, so saving does not overwrite it.
+fn load_config<T>(
+    path: PathBuf,
+    open: impl FnOnce(&Path) -> std::io::Result<T>,
+    empty: impl FnOnce(&Path) -> T
+) -> T {
+    match open(&path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            let mut aside = path.clone().into_os_string();
+            aside.push(".bad");
+            log::error!("Failed to load {}, moving it to {:?}: {}", path.display(), aside, e);
+            if let Err(e) = std::fs::rename(&path, &aside) {
+                log::error!("Failed to move {} aside: {}", path.display(), e);
+            }
+            empty(&path)
+        }
+    }
+}
+
+/// Writes `value` as JSON to a temporary file and renames it over `path`, so
+/// a crash never leaves half a file behind.
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_vec_pretty(value).map_err(std::io::Error::from)?;
+    let mut temporary = path.to_path_buf().into_os_string();
+    temporary.push(".tmp");
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Connection profiles, saved in the app config directory
+struct Profiles(Mutex<ProfileStore>);
+
+/// Commands sent with each profile, saved in the app config directory
+struct History(Mutex<CommandHistory>);
+
+/// The profile each open session was opened with, if any
+#[derive(Default)]
+struct SessionProfiles(Mutex<HashMap<SessionId, String>>);
+
+/// Sent on "serial-profile-connected" when a device that arrived was opened with its profile
+#[derive(Debug, Clone, Serialize)]
+struct TauriProfileConnected {
+    profile: String,
+    port_name: String,
+    session: SessionId,
+}
+
+/// Opens a port with a profile's settings, remembering the profile for the session's command history
+fn open_with_profile(
+    manager: &SerialManager,
+    session_profiles: &SessionProfiles,
+    profile: &ConnectionProfile,
+    port_name: &str
+) -> serial_manager::Result<SessionHandle> {
+    let session = manager.open(port_name, profile.config.clone())?;
+    session.set_auto_reconnect(profile.auto_reconnect);
+    session_profiles.0.lock().unwrap().insert(session.id(), profile.name.clone());
+    Ok(session)
+}
+
+/// Opens devices that arrive with their auto-connect profile and tells the
+/// frontend, and forgets the profiles of sessions that closed
+fn start_auto_connect(app_handle: AppHandle, events: EventReceiver) {
+    std::thread::spawn(move || {
+        log::info!("🎧 Profile auto-connect started");
+        loop {
+            let port = match events.recv() {
+                Ok(SerialEvent::DeviceArrived { port, .. }) => port,
+                Ok(SerialEvent::SessionClosed { session, .. }) => {
+                    app_handle.state::<SessionProfiles>().0.lock().unwrap().remove(&session);
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Profile auto-connect fell behind, {} events dropped", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some(profile) = app_handle.state::<Profiles>().0.lock().unwrap().auto_connect_for(&port).cloned() else {
+                continue;
+            };
+
+            let manager = app_handle.state::<Arc<Mutex<SerialManager>>>();
+            let manager = manager.lock().unwrap();
+            // Leave the device to a session that already has it or is waiting to reconnect to it
+            let identity = DeviceIdentity::of(&port);
+            let taken = manager.sessions().unwrap_or_default().iter().any(|session| {
+                session.port_name() == port.port_name || (identity.is_some() && session.identity() == identity)
+            });
+            if taken {
+                continue;
+            }
+            match open_with_profile(&manager, &app_handle.state::<SessionProfiles>(), &profile, &port.port_name) {
+                Ok(session) => {
+                    log::info!("🔌 Profile CONNECTED: {} on {} as {}", profile.name, port.port_name, session.id());
+                    let connected = TauriProfileConnected { profile: profile.name, port_name: port.port_name, session: session.id() };
+                    if let Err(e) = app_handle.emit("serial-profile-connected", &connected) {
+                        log::error!("Failed to emit profile connection: {}", e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to open {} with profile {}: {}", port.port_name, profile.name, e),
+            }
+        }
+        log::info!("🎧 Profile auto-connect stopped");
     });
 }
 
 #[command]
-fn get_serial_ports(manager: tauri::State<Arc<Mutex<SerialManager>>>) -> Vec<String> {
-    manager.lock().unwrap().get_ports()
+fn list_profiles(profiles: tauri::State<Profiles>) -> Vec<ConnectionProfile> {
+    profiles.0.lock().unwrap().profiles().to_vec()
+}
+
+/// Saves a profile, replacing the one with the same name
+#[command]
+fn save_profile(profiles: tauri::State<Profiles>, profile: ConnectionProfile) -> Result<(), String> {
+    profiles.0.lock().unwrap().save(profile).map_err(|e| e.to_string())
 }
 
 #[command]
-fn get_log_chunk(offset: usize, limit: usize) -> Vec<String> {
-    (offset..offset + limit)
-        .map(|i| format!("Serial Port X, Line {}", i + 1))
-        .collect()
+fn delete_profile(profiles: tauri::State<Profiles>, name: String) -> Result<bool, String> {
+    profiles.0.lock().unwrap().remove(&name).map_err(|e| e.to_string())
+}
+
+/// Opens a port with the settings of a profile
+#[command]
+fn connect_profile(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    profiles: tauri::State<Profiles>,
+    session_profiles: tauri::State<SessionProfiles>,
+    name: String,
+    port_name: String
+) -> Result<SessionId, String> {
+    let profile = profiles.0.lock().unwrap()
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("there is no profile named {}", name))?;
+    let session = open_with_profile(&manager.lock().unwrap(), &session_profiles, &profile, &port_name).map_err(|e| e.to_string())?;
+    Ok(session.id())
+}
+
+/// Sends what the user typed, turned into bytes as `mode` says, and adds it to
+/// the command history of the session's profile. What is sent reaches the
+/// session's log as TX like any other write. Fails without sending or
+/// recording anything if the payload encodes to no bytes.
+#[command]
+fn send_data(
+    manager: tauri::State<Arc<Mutex<SerialManager>>>,
+    history: tauri::State<History>,
+    session_profiles: tauri::State<SessionProfiles>,
+    session: SessionId,
+    payload: String,
+    mode: SendMode
+) -> Result<usize, String> {
+    let bytes = mode.encode(&payload).map_err(|e| e.to_string())?;
+    if bytes.is_empty() {
+        return Err("nothing to send".to_string());
+    }
+    find_session(&manager, session)?.write(&bytes).map_err(|e| e.to_string())?;
+
+    let profile = session_profiles.0.lock().unwrap().get(&session).cloned().unwrap_or_default();
+    if let Err(e) = history.0.lock().unwrap().push(&profile, HistoryEntry { payload, mode }) {
+        log::warn!("Failed to save command history: {}", e);
+    }
+    Ok(bytes.len())
+}
+
+/// Returns the commands sent with the session's profile, oldest first
+#[command]
+fn get_command_history(
+    history: tauri::State<History>,
+    session_profiles: tauri::State<SessionProfiles>,
+    session: SessionId
+) -> Vec<HistoryEntry> {
+    let profile = session_profiles.0.lock().unwrap().get(&session).cloned().unwrap_or_default();
+    history.0.lock().unwrap().entries(&profile).to_vec()
 }
 
 pub fn run() {
-    let (sender, receiver) = mpsc::channel();
-    let manager = SerialManager::new(sender);
+    // Discovery runs as a task on Tauri's tokio runtime rather than on its own thread
+    let manager = tauri::async_runtime::block_on(async {
+        SerialManager::spawn_async(PortFilter::allow_all(), DiscoveryConfig::default(), CancellationToken::new())
+    });
+    let events = manager.events().expect("failed to subscribe to serial events");
+    let timeline = manager.timeline(&[]).expect("failed to subscribe to the serial timeline");
+    let scrollback_timeline = manager.timeline(&[]).expect("failed to subscribe to the serial timeline");
+    let profile_events = manager.subscribe().expect("failed to subscribe to serial events");
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default()
         .manage(Arc::new(Mutex::new(manager)))
+        .manage(VirtualPorts::default())
+        .manage(Searches::default())
+        .manage(SessionProfiles::default());
+    #[cfg(unix)]
+    let builder = builder.manage(Replays::default());
+    builder
         .setup(|app| {
             // Start the event forwarder after the app is set up
-            start_event_forwarder(app.handle().clone(), receiver);
+            start_event_forwarder(app.handle().clone(), events);
+            start_timeline_forwarder(app.handle().clone(), timeline);
+
+            let cache_dir = app.path().app_cache_dir().unwrap_or_else(|_| std::env::temp_dir().join("cereal"));
+            app.manage(Scrollbacks::new(cache_dir.join("scrollback")));
+            start_scrollback_writer(app.handle().clone(), scrollback_timeline);
+
+            let config_dir = app.path().app_config_dir().unwrap_or_else(|_| std::env::temp_dir().join("cereal"));
+            let profiles = load_config(config_dir.join("profiles.json"), |path| ProfileStore::open(path), |path| ProfileStore::new(path));
+            app.manage(Profiles(Mutex::new(profiles)));
+            let history = load_config(config_dir.join("history.json"), |path| CommandHistory::open(path), |path| CommandHistory::new(path));
+            app.manage(History(Mutex::new(history)));
+            start_auto_connect(app.handle().clone(), profile_events);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_serial_ports,
-            get_log_chunk
+            get_port_filter,
+            set_port_filter,
+            pause_discovery,
+            resume_discovery,
+            rescan_ports,
+            get_scan_interval,
+            set_scan_interval,
+            open_port,
+            detect_baud,
+            close_port,
+            write_port,
+            set_control_lines,
+            get_control_lines,
+            watch_control_lines,
+            list_reset_sequences,
+            send_break,
+            run_reset_sequence,
+            get_session_stats,
+            create_virtual_port,
+            destroy_virtual_port,
+            transmit,
+            transmit_file,
+            cancel_transmit,
+            start_capture,
+            stop_capture,
+            replay_capture,
+            start_replay,
+            stop_replay,
+            get_log_chunk,
+            get_log_length,
+            start_search,
+            stop_search,
+            next_match,
+            previous_match,
+            export_session,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            connect_profile,
+            send_data,
+            get_command_history
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // Stop discovery before the runtime it runs on goes away
+                let manager = app.state::<Arc<Mutex<SerialManager>>>();
+                if !manager.lock().unwrap().shutdown_and_join(Duration::from_secs(1)) {
+                    log::warn!("Serial discovery did not stop cleanly");
+                }
+            }
+        });
 }
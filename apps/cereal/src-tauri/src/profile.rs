@@ -0,0 +1,149 @@
+//! # Connection Profiles
+//!
+//! The same boards get plugged into the same bench every day. A
+//! ConnectionProfile remembers how to talk to one of them: the PortConfig to
+//! open it with and a PortMatcher that recognises it, by USB IDs, serial
+//! number or /dev/serial/by-id link, whatever port name it gets this time.
+//!
+//! A ProfileStore keeps profiles in a JSON file and writes it on every change.
+//! A profile flagged auto_connect is meant to be opened as soon as a matching
+//! device arrives; auto_connect_for() finds the profile for a port, and the
+//! application opens the session.
+//!
+//! ```rust ignore
+//! let mut profiles = ProfileStore::open(config_dir.join("profiles.json"))?;
+//! profiles.save(ConnectionProfile {
+//!     name: "STM32 debug".to_string(),
+//!     config: PortConfig { baud_rate: 921_600, ..Default::default() },
+//!     matcher: PortMatcher { vid: Some(0x0483), serial_number: Some("0038*".to_string()), ..Default::default() },
+//!     auto_connect: true,
+//!     auto_reconnect: true,
+//! })?;
+//!
+//! if let SerialEvent::DeviceArrived { port, .. } = event {
+//!     if let Some(profile) = profiles.auto_connect_for(&port) {
+//!         let session = manager.open(&port.port_name, profile.config.clone())?;
+//!         session.set_auto_reconnect(profile.auto_reconnect);
+//!     }
+//! }
+//! ```
+
+use std::{
+    fs,
+    io::{self, Result},
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+use serial_manager::{PortConfig, PortInfo, PortMatcher};
+use crate::write_json;
+
+/// A named way of connecting to a device
+///
+/// Fields missing when deserializing take their default values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionProfile {
+    /// Name the profile is saved under
+    pub name: String,
+    /// Settings to open the port with
+    pub config: PortConfig,
+    /// Which ports the profile is for
+    pub matcher: PortMatcher,
+    /// Open a session as soon as a matching device arrives
+    pub auto_connect: bool,
+    /// Keep the session through the device disconnecting
+    pub auto_reconnect: bool,
+}
+
+/// The contents of a profile file
+#[derive(Serialize, Deserialize)]
+struct ProfileFile {
+    profiles: Vec<ConnectionProfile>,
+}
+
+/// Connection profiles, kept in a JSON file
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: Vec<ConnectionProfile>,
+}
+
+impl ProfileStore {
+    /// Starts without profiles, without reading `path`. The file there is
+    /// replaced when a profile is first saved.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        ProfileStore { path: path.as_ref().to_path_buf(), profiles: Vec::new() }
+    }
+
+    /// Loads the profiles saved at `path`, or starts without any if there is
+    /// no file yet. Fails with an InvalidData error if the file is not a
+    /// profile file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let profiles = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice::<ProfileFile>(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid profile file: {}", e)))?
+                .profiles,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(ProfileStore { path, profiles })
+    }
+
+    /// Returns the path of the profile file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns every profile, in the order they were first saved.
+    pub fn profiles(&self) -> &[ConnectionProfile] {
+        &self.profiles
+    }
+
+    /// Returns the profile saved under `name`.
+    pub fn get(&self, name: &str) -> Option<&ConnectionProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Saves a profile, replacing the one with the same name, and writes the file.
+    ///
+    /// Fails with an InvalidInput error if the name is empty, or if the
+    /// profile connects automatically but its matcher has no criteria and so
+    /// would match every port.
+    pub fn save(&mut self, profile: ConnectionProfile) -> Result<()> {
+        if profile.name.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "profile name is empty"));
+        }
+        if profile.auto_connect && profile.matcher == PortMatcher::default() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("profile {} connects automatically but matches every port", profile.name)
+            ));
+        }
+        match self.profiles.iter_mut().find(|saved| saved.name == profile.name) {
+            Some(saved) => *saved = profile,
+            None => self.profiles.push(profile),
+        }
+        self.write()
+    }
+
+    /// Removes the profile saved under `name` and writes the file. Returns
+    /// false if there was no such profile.
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let count = self.profiles.len();
+        self.profiles.retain(|profile| profile.name != name);
+        if self.profiles.len() == count {
+            return Ok(false);
+        }
+        self.write()?;
+        Ok(true)
+    }
+
+    /// Returns the first auto-connect profile that matches the port.
+    pub fn auto_connect_for(&self, port: &PortInfo) -> Option<&ConnectionProfile> {
+        self.profiles.iter().find(|profile| profile.auto_connect && profile.matcher.matches(port))
+    }
+
+    fn write(&self) -> Result<()> {
+        write_json(&self.path, &ProfileFile { profiles: self.profiles.clone() })
+    }
+}
@@ -0,0 +1,302 @@
+//! # Scrollback
+//!
+//! A session that runs for days produces millions of lines, more than a
+//! frontend should hold in memory. A Scrollback keeps them on disk instead,
+//! and hands out any window of them, e.g. the rows a virtually scrolled view
+//! shows, in time independent of how many lines there are.
+//!
+//! ## Lines
+//!
+//! Data is appended as it is read or written and split into lines at "\n",
+//! dropping a "\r" before it. A line in progress is kept in memory and
+//! counted as the last line, so a prompt shows before the device ends it.
+//! A change of direction ends the line in progress, so that what was sent
+//! never ends up on the same line as what was received, and so does reaching
+//! MAX_LINE_LENGTH, which keeps binary data without line endings in check.
+//!
+//! ## Files
+//!
+//! The lines are stored in a data file, each as a direction byte, the time its
+//! first byte was read or written as nanoseconds since the Unix epoch (u64,
+//! little-endian) and its text. Next to it, an index file holds the offset of
+//! every line in the data file as a u64, so finding line n takes one read at
+//! n * 8. Both files are removed when the Scrollback is dropped.
+//!
+//! ```rust ignore
+//! let mut scrollback = Scrollback::create(cache_dir.join("session-1.log"))?;
+//! scrollback.append(Direction::Rx, SystemTime::now(), b"U-Boot 2024.01\r\n=> ")?;
+//! assert_eq!(scrollback.len(), 2);
+//! for line in scrollback.lines(0, 50)? {
+//!     println!("{:?} {}", line.direction, line.text);
+//! }
+//! ```
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Read, Result, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use serde::{Deserialize, Serialize};
+use serial_manager::Direction;
+
+/// Longest line stored; longer ones are split
+pub const MAX_LINE_LENGTH: usize = 4096;
+
+/// Size of a line's direction and timestamp in the data file
+const LINE_HEADER_SIZE: usize = 9;
+
+/// Size of a line's entry in the index file
+const INDEX_ENTRY_SIZE: u64 = 8;
+
+/// A line of a Scrollback
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrollbackLine {
+    /// Whether the line was received or sent
+    pub direction: Direction,
+    /// When the first byte of the line was read or written
+    pub timestamp: SystemTime,
+    /// The line without its ending. Invalid UTF-8 is replaced with U+FFFD.
+    pub text: String,
+}
+
+/// A line as it was received or sent, before decoding
+pub(crate) struct RawLine {
+    pub(crate) direction: Direction,
+    pub(crate) timestamp: SystemTime,
+    pub(crate) bytes: Vec<u8>,
+}
+
+/// The line in progress
+struct PendingLine {
+    direction: Direction,
+    timestamp: SystemTime,
+    bytes: Vec<u8>,
+}
+
+/// Lines of a session, stored on disk
+pub struct Scrollback {
+    data_path: PathBuf,
+    index_path: PathBuf,
+    data: BufWriter<File>,
+    index: BufWriter<File>,
+    data_reader: File,
+    index_reader: File,
+    /// Size of the data file, including what is still buffered
+    data_size: u64,
+    /// Lines stored in the files, not counting the line in progress
+    stored: u64,
+    pending: Option<PendingLine>,
+}
+
+impl Scrollback {
+    /// Creates an empty scrollback stored at `path`, with its index next to it
+    /// at `path` plus ".idx". Existing files are replaced.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let data_path = path.as_ref().to_path_buf();
+        let mut index_path = data_path.clone().into_os_string();
+        index_path.push(".idx");
+        let index_path = PathBuf::from(index_path);
+
+        let open = |path: &Path| OpenOptions::new().create(true).write(true).truncate(true).open(path);
+        let data = open(&data_path)?;
+        let index = open(&index_path)?;
+        Ok(Scrollback {
+            data_reader: File::open(&data_path)?,
+            index_reader: File::open(&index_path)?,
+            data: BufWriter::new(data),
+            index: BufWriter::new(index),
+            data_path,
+            index_path,
+            data_size: 0,
+            stored: 0,
+            pending: None,
+        })
+    }
+
+    /// Returns the path of the data file.
+    pub fn path(&self) -> &Path {
+        &self.data_path
+    }
+
+    /// Returns the number of lines, including the line in progress.
+    pub fn len(&self) -> u64 {
+        self.stored + self.pending.is_some() as u64
+    }
+
+    /// Returns the number of complete lines, not counting the line in progress.
+    pub fn complete_len(&self) -> u64 {
+        self.stored
+    }
+
+    /// Returns whether nothing was appended yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends data read or written at `timestamp`, splitting it into lines.
+    pub fn append(&mut self, direction: Direction, timestamp: SystemTime, bytes: &[u8]) -> Result<()> {
+        if self.pending.as_ref().is_some_and(|pending| pending.direction != direction) {
+            self.end_line()?;
+        }
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let pending = self.pending.get_or_insert_with(|| PendingLine { direction, timestamp, bytes: Vec::new() });
+            let room = MAX_LINE_LENGTH - pending.bytes.len();
+            // A line that is full but for its ending still ends at it
+            let window = &rest[..rest.len().min(room + 1)];
+            match window.iter().position(|&byte| byte == b'\n') {
+                Some(newline) => {
+                    pending.bytes.extend_from_slice(&window[..newline]);
+                    if pending.bytes.last() == Some(&b'\r') {
+                        pending.bytes.pop();
+                    }
+                    rest = &rest[newline + 1..];
+                    self.end_line()?;
+                }
+                None => {
+                    let taken = rest.len().min(room);
+                    pending.bytes.extend_from_slice(&rest[..taken]);
+                    rest = &rest[taken..];
+                    if pending.bytes.len() == MAX_LINE_LENGTH {
+                        self.end_line()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a whole line, e.g. a frame, after ending the line in progress.
+    /// A line longer than MAX_LINE_LENGTH is split.
+    pub fn append_line(&mut self, direction: Direction, timestamp: SystemTime, bytes: &[u8]) -> Result<()> {
+        self.end_line()?;
+        let mut rest = bytes;
+        loop {
+            let (line, more) = rest.split_at(rest.len().min(MAX_LINE_LENGTH));
+            self.pending = Some(PendingLine { direction, timestamp, bytes: line.to_vec() });
+            self.end_line()?;
+            if more.is_empty() {
+                return Ok(());
+            }
+            rest = more;
+        }
+    }
+
+    /// Stores the line in progress, if any.
+    fn end_line(&mut self) -> Result<()> {
+        let Some(line) = self.pending.take() else {
+            return Ok(());
+        };
+        let nanos = line.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        self.index.write_all(&self.data_size.to_le_bytes())?;
+        self.data.write_all(&[direction_byte(line.direction)])?;
+        self.data.write_all(&nanos.to_le_bytes())?;
+        self.data.write_all(&line.bytes)?;
+        self.data_size += (LINE_HEADER_SIZE + line.bytes.len()) as u64;
+        self.stored += 1;
+        Ok(())
+    }
+
+    /// Returns up to `limit` lines starting at line `offset`, counted from 0.
+    /// The line in progress is included as the last line.
+    pub fn lines(&mut self, offset: u64, limit: usize) -> Result<Vec<ScrollbackLine>> {
+        let lines = self.raw_lines(offset, limit)?;
+        Ok(lines
+            .into_iter()
+            .map(|line| ScrollbackLine {
+                direction: line.direction,
+                timestamp: line.timestamp,
+                text: String::from_utf8_lossy(&line.bytes).into_owned(),
+            })
+            .collect())
+    }
+
+    /// Returns the first line whose timestamp is at or after `time`, or the
+    /// number of lines if there is none. Lines are assumed to be in time
+    /// order, which holds unless the wall clock was set back.
+    pub fn position_of_time(&mut self, time: SystemTime) -> Result<u64> {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.raw_lines(middle, 1)?.first() {
+                Some(line) if line.timestamp < time => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+        Ok(low)
+    }
+
+    /// Like lines(), but with the bytes of each line as they were appended.
+    pub(crate) fn raw_lines(&mut self, offset: u64, limit: usize) -> Result<Vec<RawLine>> {
+        let end = self.len().min(offset.saturating_add(limit as u64));
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let stored_end = end.min(self.stored);
+        let mut lines = Vec::with_capacity((end - offset) as usize);
+
+        if offset < stored_end {
+            self.data.flush()?;
+            self.index.flush()?;
+
+            // The offsets of the lines, and where the last of them ends
+            let count = (stored_end - offset) as usize;
+            let mut index = vec![0; count * INDEX_ENTRY_SIZE as usize];
+            self.index_reader.seek(SeekFrom::Start(offset * INDEX_ENTRY_SIZE))?;
+            self.index_reader.read_exact(&mut index)?;
+            let mut starts: Vec<u64> = index
+                .chunks_exact(INDEX_ENTRY_SIZE as usize)
+                .map(|entry| u64::from_le_bytes(entry.try_into().unwrap()))
+                .collect();
+            let data_end = if stored_end < self.stored {
+                let mut entry = [0; INDEX_ENTRY_SIZE as usize];
+                self.index_reader.read_exact(&mut entry)?;
+                u64::from_le_bytes(entry)
+            } else {
+                self.data_size
+            };
+            starts.push(data_end);
+
+            let mut data = vec![0; (data_end - starts[0]) as usize];
+            self.data_reader.seek(SeekFrom::Start(starts[0]))?;
+            self.data_reader.read_exact(&mut data)?;
+            for bounds in starts.windows(2) {
+                let record = &data[(bounds[0] - starts[0]) as usize..(bounds[1] - starts[0]) as usize];
+                lines.push(RawLine {
+                    direction: if record[0] == 0 { Direction::Rx } else { Direction::Tx },
+                    timestamp: UNIX_EPOCH
+                        + Duration::from_nanos(u64::from_le_bytes(record[1..LINE_HEADER_SIZE].try_into().unwrap())),
+                    bytes: record[LINE_HEADER_SIZE..].to_vec(),
+                });
+            }
+        }
+        if end > self.stored {
+            if let Some(pending) = &self.pending {
+                lines.push(RawLine {
+                    direction: pending.direction,
+                    timestamp: pending.timestamp,
+                    bytes: pending.bytes.clone(),
+                });
+            }
+        }
+        Ok(lines)
+    }
+}
+
+impl Drop for Scrollback {
+    fn drop(&mut self) {
+        for path in [&self.data_path, &self.index_path] {
+            if let Err(e) = fs::remove_file(path) {
+                log::warn!("Failed to remove scrollback file {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn direction_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Rx => 0,
+        Direction::Tx => 1,
+    }
+}
@@ -0,0 +1,135 @@
+//! # Scrollback Search
+//!
+//! A ScrollbackSearch finds the lines of a Scrollback that match a query. It
+//! works through the scrollback in steps of scan(), so that a caller can
+//! search in the background, release the scrollback between steps for data
+//! to be appended, and report the matches of each step as it goes. Once it
+//! has caught up, further calls to scan() search the lines appended since,
+//! so a search keeps up with a session that is still running.
+//!
+//! Only complete lines are searched. The line in progress is searched once
+//! it ends, so a match is never reported for a line that later changes.
+//!
+//! ```rust ignore
+//! let query = SearchQuery { pattern: "panic|oops".to_string(), kind: SearchKind::Regex };
+//! let mut search = ScrollbackSearch::new(&query)?;
+//! loop {
+//!     let found = search.scan(&mut scrollback, 10_000)?;
+//!     println!("lines {:?}, {} of {} searched", found, search.searched(), scrollback.len());
+//!     if search.searched() == scrollback.complete_len() {
+//!         break;
+//!     }
+//! }
+//! let after_cursor = search.next_match(cursor);
+//! ```
+
+use std::io::{self, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use crate::scrollback::Scrollback;
+
+/// How a SearchQuery's pattern is matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    /// The pattern appears in the line as it is
+    Literal,
+    /// The pattern appears in the line, ignoring case
+    CaseInsensitive,
+    /// The line matches the pattern as a regular expression
+    Regex,
+}
+
+/// What to search a scrollback for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    /// Text or regular expression to look for in each line
+    pub pattern: String,
+    /// Whether `pattern` is plain text, matched with or without case, or a regex
+    pub kind: SearchKind,
+}
+
+/// A search through a Scrollback, with the matches found so far
+pub struct ScrollbackSearch {
+    query: SearchQuery,
+    matcher: Regex,
+    /// Lines searched, counted from the start of the scrollback
+    searched: u64,
+    /// Offsets of the matching lines, in order
+    matches: Vec<u64>,
+}
+
+impl ScrollbackSearch {
+    /// Starts a search from the first line.
+    ///
+    /// Fails with an InvalidInput error if the pattern is empty or, for a
+    /// regex query, not a valid regular expression.
+    pub fn new(query: &SearchQuery) -> Result<Self> {
+        if query.pattern.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty search pattern"));
+        }
+        let pattern = match query.kind {
+            SearchKind::Literal | SearchKind::CaseInsensitive => regex::escape(&query.pattern),
+            SearchKind::Regex => query.pattern.clone(),
+        };
+        let matcher = RegexBuilder::new(&pattern)
+            .case_insensitive(query.kind == SearchKind::CaseInsensitive)
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid search pattern: {}", e)))?;
+        Ok(ScrollbackSearch { query: query.clone(), matcher, searched: 0, matches: Vec::new() })
+    }
+
+    /// Returns the query being searched for.
+    pub fn query(&self) -> &SearchQuery {
+        &self.query
+    }
+
+    /// Returns whether `text` matches the query.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.matcher.is_match(text)
+    }
+
+    /// Returns the number of lines searched so far, from the first.
+    pub fn searched(&self) -> u64 {
+        self.searched
+    }
+
+    /// Returns the offsets of the matching lines found so far, in order.
+    pub fn matches(&self) -> &[u64] {
+        &self.matches
+    }
+
+    /// Searches up to `max_lines` complete lines after the ones searched
+    /// before and returns the offsets of those that match.
+    pub fn scan(&mut self, scrollback: &mut Scrollback, max_lines: usize) -> Result<Vec<u64>> {
+        let available = scrollback.complete_len().saturating_sub(self.searched);
+        let count = available.min(max_lines as u64) as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let lines = scrollback.lines(self.searched, count)?;
+        let found: Vec<u64> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.matcher.is_match(&line.text))
+            .map(|(i, _)| self.searched + i as u64)
+            .collect();
+        self.searched += lines.len() as u64;
+        self.matches.extend_from_slice(&found);
+        Ok(found)
+    }
+
+    /// Returns the first match after line `from`, wrapping around to the
+    /// first match, or None if nothing matched yet.
+    pub fn next_match(&self, from: u64) -> Option<u64> {
+        let after = self.matches.partition_point(|&line| line <= from);
+        self.matches.get(after).or(self.matches.first()).copied()
+    }
+
+    /// Returns the last match before line `from`, wrapping around to the
+    /// last match, or None if nothing matched yet.
+    pub fn previous_match(&self, from: u64) -> Option<u64> {
+        let before = self.matches.partition_point(|&line| line < from);
+        before.checked_sub(1).and_then(|i| self.matches.get(i)).or(self.matches.last()).copied()
+    }
+}
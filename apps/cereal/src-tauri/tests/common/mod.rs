@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+/// A file in a directory of its own in the temporary directory, removed with
+/// the directory when dropped
+pub struct TempFile(pub PathBuf);
+
+impl TempFile {
+    pub fn new(name: &str, file_name: &str) -> Self {
+        TempFile(std::env::temp_dir().join(format!("cereal-{}-{}", std::process::id(), name)).join(file_name))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.0.parent().unwrap());
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    io::ErrorKind,
+    time::{Duration, SystemTime},
+};
+use cereal_lib::export::{ExportFormat, ExportOptions, ExportRange, ScrollbackExport};
+use cereal_lib::scrollback::Scrollback;
+use serial_manager::Direction;
+
+fn at(millis: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + millis)
+}
+
+/// A short session: a banner, a command and its answer with a comma and binary data
+fn scrollback(name: &str) -> Scrollback {
+    let path = std::env::temp_dir().join(format!("cereal-{}-export-{}.log", std::process::id(), name));
+    let mut scrollback = Scrollback::create(path).unwrap();
+    scrollback.append(Direction::Rx, at(0), b"boot\r\n").unwrap();
+    scrollback.append(Direction::Tx, at(1500), b"version\n").unwrap();
+    scrollback.append(Direction::Rx, at(2250), b"1.2, \"rc\"\x1b\xff\\\n").unwrap();
+    scrollback
+}
+
+fn export(scrollback: &mut Scrollback, range: ExportRange, options: ExportOptions) -> String {
+    let mut export = ScrollbackExport::new(Vec::new(), scrollback, "/dev/ttyUSB0", range, options).unwrap();
+    let total = export.total();
+    let mut steps = Vec::new();
+    while !export.is_finished() {
+        steps.push(export.step(scrollback, 2).unwrap());
+    }
+    assert_eq!(steps.iter().sum::<u64>(), total);
+    assert_eq!(export.exported(), total);
+    String::from_utf8(export.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn text_exports_carry_the_chosen_times() {
+    let mut log = scrollback("text");
+    assert_eq!(
+        export(&mut log, ExportRange::All, ExportOptions::default()),
+        "[2023-11-14T22:13:20.000Z] RX boot\n\
+         [2023-11-14T22:13:21.500Z] TX version\n\
+         [2023-11-14T22:13:22.250Z] RX 1.2, \"rc\"\u{1b}\u{fffd}\\\n"
+    );
+
+    let options = ExportOptions {
+        host_timestamps: false,
+        relative_times: true,
+        escape_non_printable: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        export(&mut log, ExportRange::Lines { start: 1, end: 10 }, options),
+        "+0.000s TX version\n+0.750s RX 1.2, \"rc\"\\x1b\\xff\\x5c\n"
+    );
+}
+
+#[test]
+fn csv_exports_quote_their_fields() {
+    let mut log = scrollback("csv");
+    let options = ExportOptions { format: ExportFormat::Csv, relative_times: true, ..Default::default() };
+    assert_eq!(
+        export(&mut log, ExportRange::All, options),
+        "timestamp,relative,direction,port,text\n\
+         2023-11-14T22:13:20.000Z,0.000,RX,/dev/ttyUSB0,boot\n\
+         2023-11-14T22:13:21.500Z,1.500,TX,/dev/ttyUSB0,version\n\
+         2023-11-14T22:13:22.250Z,2.250,RX,/dev/ttyUSB0,\"1.2, \"\"rc\"\"\u{1b}\u{fffd}\\\"\n"
+    );
+}
+
+#[test]
+fn json_lines_exports_are_one_object_per_line() {
+    let mut log = scrollback("json");
+    let options = ExportOptions { format: ExportFormat::JsonLines, escape_non_printable: true, ..Default::default() };
+    let range = ExportRange::Time { from: at(1), until: at(2250) };
+    let lines: Vec<serde_json::Value> = export(&mut log, range, options)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            serde_json::json!({
+                "line": 1, "timestamp": "2023-11-14T22:13:21.500Z", "direction": "tx",
+                "port": "/dev/ttyUSB0", "text": "version",
+            }),
+            serde_json::json!({
+                "line": 2, "timestamp": "2023-11-14T22:13:22.250Z", "direction": "rx",
+                "port": "/dev/ttyUSB0", "text": "1.2, \"rc\"\\x1b\\xff\\x5c",
+            }),
+        ]
+    );
+}
+
+#[test]
+fn ranges_must_not_end_before_they_start() {
+    let mut log = scrollback("ranges");
+    for range in [ExportRange::Lines { start: 2, end: 1 }, ExportRange::Time { from: at(5), until: at(4) }] {
+        let result = ScrollbackExport::new(Vec::new(), &mut log, "port", range, ExportOptions::default());
+        assert!(matches!(result, Err(e) if e.kind() == ErrorKind::InvalidInput));
+    }
+
+    // Ranges past the end are cut short
+    let past = ScrollbackExport::new(Vec::new(), &mut log, "port", ExportRange::Lines { start: 7, end: 9 }, ExportOptions::default());
+    assert!(past.unwrap().is_finished());
+    assert_eq!(log.position_of_time(at(1500)).unwrap(), 1);
+    assert_eq!(log.position_of_time(at(9999)).unwrap(), 3);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use cereal_lib::history::{CommandHistory, HistoryEntry, DEFAULT_HISTORY_LENGTH};
+use serial_manager::SendMode;
+use common::TempFile;
+
+fn entry(payload: &str) -> HistoryEntry {
+    HistoryEntry { payload: payload.to_string(), mode: SendMode::default() }
+}
+
+#[test]
+fn history_is_kept_per_profile() {
+    let file = TempFile::new("history", "history.json");
+    let mut history = CommandHistory::open(&file.0).unwrap();
+    history.push("board", entry("version")).unwrap();
+    history.push("board", entry("reset")).unwrap();
+    history.push("board", entry("version")).unwrap();
+    history.push("", entry("help")).unwrap();
+
+    let mut history = CommandHistory::open(&file.0).unwrap();
+    assert_eq!(history.entries("board"), &[entry("reset"), entry("version")]);
+    assert_eq!(history.recall("board", 0), Some(&entry("version")));
+    assert_eq!(history.recall("board", 1), Some(&entry("reset")));
+    assert_eq!(history.recall("board", 2), None);
+    assert_eq!(history.entries(""), &[entry("help")]);
+    assert!(history.entries("other").is_empty());
+
+    for i in 0..DEFAULT_HISTORY_LENGTH + 5 {
+        history.push("board", entry(&format!("cmd {}", i))).unwrap();
+    }
+    assert_eq!(history.entries("board").len(), DEFAULT_HISTORY_LENGTH);
+    assert_eq!(history.entries("board")[0], entry("cmd 5"));
+
+    history.clear("board").unwrap();
+    assert!(CommandHistory::open(&file.0).unwrap().entries("board").is_empty());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::io::ErrorKind;
+use cereal_lib::profile::{ConnectionProfile, ProfileStore};
+use serial_manager::{Parity, PortConfig, PortInfo, PortMatcher};
+use common::TempFile;
+
+fn profile(name: &str, serial: &str, auto_connect: bool) -> ConnectionProfile {
+    ConnectionProfile {
+        name: name.to_string(),
+        config: PortConfig { baud_rate: 921_600, parity: Parity::Even, ..Default::default() },
+        matcher: PortMatcher { vid: Some(0x0483), serial_number: Some(serial.to_string()), ..Default::default() },
+        auto_connect,
+        auto_reconnect: true,
+    }
+}
+
+fn board(serial: &str) -> PortInfo {
+    PortInfo { vid: Some(0x0483), serial_number: Some(serial.to_string()), ..PortInfo::new("/dev/ttyACM3") }
+}
+
+#[test]
+fn profiles_are_kept_across_opens() {
+    let file = TempFile::new("profiles-kept", "profiles.json");
+    let mut store = ProfileStore::open(&file.0).unwrap();
+    assert!(store.profiles().is_empty());
+    store.save(profile("debug", "0038*", true)).unwrap();
+    store.save(profile("modem", "1234", false)).unwrap();
+    store.save(ConnectionProfile { auto_reconnect: false, ..profile("debug", "0039*", true) }).unwrap();
+
+    let store = ProfileStore::open(&file.0).unwrap();
+    let names: Vec<&str> = store.profiles().iter().map(|profile| profile.name.as_str()).collect();
+    assert_eq!(names, vec!["debug", "modem"]);
+    assert_eq!(store.get("debug"), Some(&ConnectionProfile { auto_reconnect: false, ..profile("debug", "0039*", true) }));
+
+    let mut store = store;
+    assert!(store.remove("modem").unwrap());
+    assert!(!store.remove("modem").unwrap());
+    assert_eq!(ProfileStore::open(&file.0).unwrap().profiles().len(), 1);
+}
+
+#[test]
+fn arriving_devices_find_their_auto_connect_profile() {
+    let file = TempFile::new("profiles-auto", "profiles.json");
+    let mut store = ProfileStore::open(&file.0).unwrap();
+    store.save(profile("manual", "0038*", false)).unwrap();
+    store.save(profile("auto", "0038*", true)).unwrap();
+
+    assert_eq!(store.auto_connect_for(&board("0038FF")).map(|profile| profile.name.as_str()), Some("auto"));
+    assert_eq!(store.auto_connect_for(&board("0040FF")), None);
+}
+
+#[test]
+fn invalid_profiles_and_files_are_rejected() {
+    let file = TempFile::new("profiles-invalid", "profiles.json");
+    let mut store = ProfileStore::open(&file.0).unwrap();
+    let everything = ConnectionProfile { name: "any".to_string(), auto_connect: true, ..Default::default() };
+    for profile in [profile("", "0038*", false), everything] {
+        assert!(matches!(store.save(profile), Err(e) if e.kind() == ErrorKind::InvalidInput));
+    }
+    assert!(store.profiles().is_empty());
+
+    std::fs::create_dir_all(file.0.parent().unwrap()).unwrap();
+    std::fs::write(&file.0, "not json").unwrap();
+    assert!(matches!(ProfileStore::open(&file.0), Err(e) if e.kind() == ErrorKind::InvalidData));
+}
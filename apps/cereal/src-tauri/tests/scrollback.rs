@@ -0,0 +1,124 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use cereal_lib::scrollback::{Scrollback, MAX_LINE_LENGTH};
+use serial_manager::Direction;
+
+/// A scrollback in the temporary directory
+fn create(name: &str) -> Scrollback {
+    let path = std::env::temp_dir().join(format!("cereal-{}-{}.log", std::process::id(), name));
+    Scrollback::create(path).unwrap()
+}
+
+fn at(millis: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+/// The direction and text of the lines in a window
+fn texts(scrollback: &mut Scrollback, offset: u64, limit: usize) -> Vec<(Direction, String)> {
+    scrollback
+        .lines(offset, limit)
+        .unwrap()
+        .into_iter()
+        .map(|line| (line.direction, line.text))
+        .collect()
+}
+
+#[test]
+fn data_is_split_into_lines() {
+    let mut scrollback = create("lines");
+    assert!(scrollback.is_empty());
+    scrollback.append(Direction::Rx, at(1), b"U-Boot 2024.01\r\n\r\nDRA").unwrap();
+    scrollback.append(Direction::Rx, at(2), b"M: 512 MiB\n=> ").unwrap();
+    assert_eq!(scrollback.len(), 4);
+    assert_eq!(
+        texts(&mut scrollback, 0, 10),
+        vec![
+            (Direction::Rx, "U-Boot 2024.01".to_string()),
+            (Direction::Rx, String::new()),
+            (Direction::Rx, "DRAM: 512 MiB".to_string()),
+            (Direction::Rx, "=> ".to_string()),
+        ]
+    );
+
+    // A line keeps the time of its first byte
+    let lines = scrollback.lines(2, 2).unwrap();
+    assert_eq!(lines[0].timestamp, at(1));
+    assert_eq!(lines[1].timestamp, at(2));
+
+    // The prompt is completed in place rather than added again
+    scrollback.append(Direction::Rx, at(3), b"\xffok\r\n").unwrap();
+    assert_eq!(scrollback.len(), 4);
+    assert_eq!(texts(&mut scrollback, 3, 10), vec![(Direction::Rx, "=> \u{fffd}ok".to_string())]);
+    assert!(scrollback.lines(4, 10).unwrap().is_empty());
+}
+
+#[test]
+fn directions_do_not_share_lines() {
+    let mut scrollback = create("directions");
+    scrollback.append(Direction::Rx, at(1), b"=> ").unwrap();
+    scrollback.append(Direction::Tx, at(2), b"version\r").unwrap();
+    scrollback.append(Direction::Rx, at(3), b"version\r\n1.2.3\r\n").unwrap();
+    scrollback.append_line(Direction::Rx, at(4), b"$GPGGA,123519").unwrap();
+    assert_eq!(
+        texts(&mut scrollback, 0, 10),
+        vec![
+            (Direction::Rx, "=> ".to_string()),
+            (Direction::Tx, "version\r".to_string()),
+            (Direction::Rx, "version".to_string()),
+            (Direction::Rx, "1.2.3".to_string()),
+            (Direction::Rx, "$GPGGA,123519".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn long_lines_are_split() {
+    let mut scrollback = create("long");
+    let binary = vec![b'x'; MAX_LINE_LENGTH * 2 + 10];
+    scrollback.append(Direction::Rx, at(1), &binary).unwrap();
+    scrollback.append(Direction::Rx, at(2), b"\n").unwrap();
+    scrollback.append_line(Direction::Tx, at(3), &binary[..MAX_LINE_LENGTH + 1]).unwrap();
+
+    let lengths: Vec<usize> = scrollback.lines(0, 10).unwrap().iter().map(|line| line.text.len()).collect();
+    assert_eq!(lengths, vec![MAX_LINE_LENGTH, MAX_LINE_LENGTH, 10, MAX_LINE_LENGTH, 1]);
+}
+
+#[test]
+fn windows_deep_into_large_scrollbacks_are_exact() {
+    let mut scrollback = create("large");
+    // 200 appends of 1000 lines, each a millisecond after the one before
+    for chunk in 0..200 {
+        let mut data = Vec::new();
+        for line in chunk * 1000..(chunk + 1) * 1000 {
+            data.extend_from_slice(format!("line {}\r\n", line).as_bytes());
+        }
+        scrollback.append(Direction::Rx, at(chunk), &data).unwrap();
+    }
+    assert_eq!(scrollback.len(), 200_000);
+
+    for (offset, count) in [(0, 100), (123_456, 100), (199_950, 50)] {
+        let expected: Vec<_> = (offset..offset + count)
+            .map(|line| (Direction::Rx, format!("line {}", line)))
+            .collect();
+        assert_eq!(texts(&mut scrollback, offset, 100), expected);
+    }
+    assert_eq!(texts(&mut scrollback, 199_999, 100), vec![(Direction::Rx, "line 199999".to_string())]);
+    assert!(texts(&mut scrollback, 200_000, 100).is_empty());
+
+    let line = scrollback.lines(123_456, 1).unwrap().remove(0);
+    assert_eq!(line.timestamp, at(123));
+    assert_eq!(scrollback.position_of_time(at(123)).unwrap(), 123_000);
+    assert_eq!(scrollback.position_of_time(at(200)).unwrap(), 200_000);
+}
+
+#[test]
+fn files_are_removed_when_dropped() {
+    let scrollback = create("removed");
+    let path: PathBuf = scrollback.path().to_path_buf();
+    let index = PathBuf::from(format!("{}.idx", path.display()));
+    assert!(path.exists() && index.exists());
+    drop(scrollback);
+    assert!(!path.exists() && !index.exists());
+}
@@ -0,0 +1,72 @@
+use std::{
+    io::ErrorKind,
+    time::SystemTime,
+};
+use cereal_lib::scrollback::Scrollback;
+use cereal_lib::search::{ScrollbackSearch, SearchKind, SearchQuery};
+use serial_manager::Direction;
+
+/// A scrollback in the temporary directory holding `data`
+fn scrollback(name: &str, data: &[u8]) -> Scrollback {
+    let path = std::env::temp_dir().join(format!("cereal-{}-search-{}.log", std::process::id(), name));
+    let mut scrollback = Scrollback::create(path).unwrap();
+    scrollback.append(Direction::Rx, SystemTime::now(), data).unwrap();
+    scrollback
+}
+
+fn search(pattern: &str, kind: SearchKind) -> ScrollbackSearch {
+    ScrollbackSearch::new(&SearchQuery { pattern: pattern.to_string(), kind }).unwrap()
+}
+
+const BOOT_LOG: &[u8] = b"U-Boot 2024.01\r\nDRAM: 512 MiB\r\nerror: no MMC\r\nStarting kernel (a.out)\r\nERROR: panic\r\n";
+
+#[test]
+fn queries_match_as_their_kind_says() {
+    let mut log = scrollback("kinds", BOOT_LOG);
+    let mut found = |pattern: &str, kind| search(pattern, kind).scan(&mut log, 100).unwrap();
+
+    assert_eq!(found("error", SearchKind::Literal), vec![2]);
+    assert_eq!(found("error", SearchKind::CaseInsensitive), vec![2, 4]);
+    assert_eq!(found("(a.out)", SearchKind::Literal), vec![3]);
+    assert_eq!(found(r"^\w+: \d+", SearchKind::Regex), vec![1]);
+    assert_eq!(found("MiB|kernel", SearchKind::Regex), vec![1, 3]);
+
+    for (pattern, kind) in [("", SearchKind::Literal), ("(", SearchKind::Regex)] {
+        let query = SearchQuery { pattern: pattern.to_string(), kind };
+        assert!(matches!(ScrollbackSearch::new(&query), Err(e) if e.kind() == ErrorKind::InvalidInput));
+    }
+}
+
+#[test]
+fn searches_proceed_in_steps_and_follow_new_lines() {
+    let mut log = scrollback("steps", BOOT_LOG);
+    let mut search = search("r", SearchKind::CaseInsensitive);
+    assert_eq!(search.scan(&mut log, 2).unwrap(), vec![1]);
+    assert_eq!(search.scan(&mut log, 2).unwrap(), vec![2, 3]);
+    assert_eq!(search.scan(&mut log, 2).unwrap(), vec![4]);
+    assert_eq!(search.searched(), 5);
+    assert!(search.scan(&mut log, 2).unwrap().is_empty());
+
+    // The line in progress is only searched once it ends
+    log.append(Direction::Rx, SystemTime::now(), b"Error").unwrap();
+    assert!(search.scan(&mut log, 2).unwrap().is_empty());
+    log.append(Direction::Rx, SystemTime::now(), b"\r\n").unwrap();
+    assert_eq!(search.scan(&mut log, 2).unwrap(), vec![5]);
+    assert_eq!(search.matches(), &[1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn matches_can_be_stepped_through() {
+    let mut log = scrollback("navigate", b"a\nb\na\nb\na\n");
+    let mut search = search("a", SearchKind::Literal);
+    assert_eq!(search.next_match(0), None);
+    assert_eq!(search.previous_match(0), None);
+    search.scan(&mut log, 100).unwrap();
+
+    assert_eq!(search.next_match(0), Some(2));
+    assert_eq!(search.next_match(1), Some(2));
+    assert_eq!(search.next_match(4), Some(0));
+    assert_eq!(search.previous_match(4), Some(2));
+    assert_eq!(search.previous_match(3), Some(2));
+    assert_eq!(search.previous_match(0), Some(4));
+}
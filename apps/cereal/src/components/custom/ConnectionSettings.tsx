@@ -1,3 +1,5 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { Button } from "../ui/button";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "../ui/card";
 import { Label } from "../ui/label";
@@ -10,19 +12,94 @@ import {
   SelectValue,
 } from "../ui/select";
 import { Separator } from "../ui/separator";
-import { Settings2, Zap, TestTube, Cable } from "lucide-react";
+import { Settings2, Zap, TestTube, Cable, Unplug, Search } from "lucide-react";
+
+// Mirrors FramingKind in serial_manager
+export type FramingKind =
+  | { type: "lines"; ending: "lf" | "cr" | "crlf" | "any" }
+  | { type: "fixed"; length: number }
+  | { type: "length_prefixed"; prefix: "u8" | "u16_be" | "u16_le" | "u32_be" | "u32_le" }
+  | { type: "slip" }
+  | { type: "cobs" }
+  | { type: "hdlc" };
+
+// Mirrors PortConfig in serial_manager; timeouts and frame limits are left at their defaults
+export interface PortConfig {
+  baud_rate: number;
+  data_bits: "5" | "6" | "7" | "8";
+  parity: "none" | "odd" | "even";
+  stop_bits: "1" | "2";
+  flow_control: "none" | "software" | "hardware";
+  framing: { kind: FramingKind } | null;
+}
+
+// Mirrors BaudCandidate in serial_manager; the score details are not shown
+interface BaudCandidate {
+  baud_rate: number;
+  confidence: number;
+}
+
+const BAUD_RATES = [9600, 19200, 38400, 57600, 115200];
+
+// How long detection listens at each rate
+const DETECT_DURATION_MS = 500;
+
+// Framers offered in the settings, by select value
+const FRAMINGS: Record<string, FramingKind | null> = {
+  raw: null,
+  lines: { type: "lines", ending: "any" },
+  slip: { type: "slip" },
+  cobs: { type: "cobs" },
+  hdlc: { type: "hdlc" },
+};
 
 interface ConnectionSettingsProps {
   readonly selectedPort?: string;
-  readonly onConnect?: () => void;
+  readonly connected?: boolean;
+  readonly onConnect?: (config: PortConfig, autoReconnect: boolean) => void;
+  readonly onDisconnect?: () => void;
   readonly onTest?: () => void;
 }
 
 export default function ConnectionSettings({ 
   selectedPort, 
+  connected,
   onConnect, 
+  onDisconnect,
   onTest 
 }: ConnectionSettingsProps) {
+  const [config, setConfig] = useState<PortConfig>({
+    baud_rate: 9600,
+    data_bits: "8",
+    parity: "none",
+    stop_bits: "1",
+    flow_control: "none",
+    framing: null,
+  });
+  const [autoReconnect, setAutoReconnect] = useState(false);
+  const [detecting, setDetecting] = useState(false);
+  const [detected, setDetected] = useState<BaudCandidate | undefined>();
+
+  const detectBaud = async () => {
+    if (!selectedPort) return;
+    setDetecting(true);
+    setDetected(undefined);
+    try {
+      const ranked = await invoke<BaudCandidate[]>("detect_baud", { portName: selectedPort, durationMs: DETECT_DURATION_MS });
+      if (ranked.length > 0) {
+        setDetected(ranked[0]);
+        setConfig(config => ({ ...config, baud_rate: ranked[0].baud_rate }));
+      }
+    } catch (err) {
+      console.error(`Failed to detect the baud rate of ${selectedPort}:`, err);
+    } finally {
+      setDetecting(false);
+    }
+  };
+
+  // Offer a detected rate even if it is not one of the usual ones
+  const baudRates = BAUD_RATES.includes(config.baud_rate) ? BAUD_RATES : [...BAUD_RATES, config.baud_rate].sort((a, b) => a - b);
+
   return (
     <Card className="h-full">
       <CardHeader className="pb-3">
@@ -42,11 +119,11 @@ export default function ConnectionSettings({
               <Cable className="h-4 w-4 text-green-600" />
               <div>
                 <p className="text-sm font-medium">{selectedPort}</p>
-                <p className="text-xs text-muted-foreground">Ready to connect</p>
+                <p className="text-xs text-muted-foreground">{connected ? "Connected" : "Ready to connect"}</p>
               </div>
             </div>
             <Badge variant="secondary" className="bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200">
-              Available
+              {connected ? "Open" : "Available"}
             </Badge>
           </div>
         )}
@@ -64,19 +141,36 @@ export default function ConnectionSettings({
                 <Label htmlFor="baud-rate" className="text-sm font-normal text-muted-foreground">Baud Rate</Label>
               </div>
               <div className="flex-1 max-w-xs">
-                <Select defaultValue="9600">
+                <Select
+                  value={String(config.baud_rate)}
+                  onValueChange={value => setConfig({ ...config, baud_rate: Number(value) })}
+                  disabled={connected}
+                >
                   <SelectTrigger className="h-11">
                     <SelectValue placeholder="Select baud rate" />
                   </SelectTrigger>
                   <SelectContent>
-                    <SelectItem value="9600">9,600 bps</SelectItem>
-                    <SelectItem value="19200">19,200 bps</SelectItem>
-                    <SelectItem value="38400">38,400 bps</SelectItem>
-                    <SelectItem value="57600">57,600 bps</SelectItem>
-                    <SelectItem value="115200">115,200 bps</SelectItem>
+                    {baudRates.map(rate => (
+                      <SelectItem key={rate} value={String(rate)}>{rate.toLocaleString("en-US")} bps</SelectItem>
+                    ))}
                   </SelectContent>
                 </Select>
+                {detected && (
+                  <p className="text-xs text-muted-foreground mt-1">
+                    Detected {detected.baud_rate.toLocaleString("en-US")} bps ({Math.round(detected.confidence * 100)}% confidence)
+                  </p>
+                )}
               </div>
+              <Button
+                variant="outline"
+                size="icon"
+                className="h-11 w-11"
+                onClick={detectBaud}
+                disabled={connected || detecting || !selectedPort}
+                title="Detect baud rate"
+              >
+                <Search className={detecting ? "h-4 w-4 animate-pulse" : "h-4 w-4"} />
+              </Button>
             </div>
             
             <div className="flex items-center justify-between gap-6">
@@ -84,7 +178,11 @@ export default function ConnectionSettings({
                 <Label htmlFor="data-bits" className="text-sm font-normal text-muted-foreground">Data Bits</Label>
               </div>
               <div className="flex-1 max-w-xs">
-                <Select defaultValue="8">
+                <Select
+                  value={config.data_bits}
+                  onValueChange={value => setConfig({ ...config, data_bits: value as PortConfig["data_bits"] })}
+                  disabled={connected}
+                >
                   <SelectTrigger className="h-11">
                     <SelectValue placeholder="Select data bits" />
                   </SelectTrigger>
@@ -113,7 +211,11 @@ export default function ConnectionSettings({
                 <Label htmlFor="parity" className="text-sm font-normal text-muted-foreground">Parity</Label>
               </div>
               <div className="flex-1 max-w-xs">
-                <Select defaultValue="none">
+                <Select
+                  value={config.parity}
+                  onValueChange={value => setConfig({ ...config, parity: value as PortConfig["parity"] })}
+                  disabled={connected}
+                >
                   <SelectTrigger className="h-11">
                     <SelectValue placeholder="Select parity" />
                   </SelectTrigger>
@@ -121,8 +223,6 @@ export default function ConnectionSettings({
                     <SelectItem value="none">None</SelectItem>
                     <SelectItem value="even">Even</SelectItem>
                     <SelectItem value="odd">Odd</SelectItem>
-                    <SelectItem value="mark">Mark</SelectItem>
-                    <SelectItem value="space">Space</SelectItem>
                   </SelectContent>
                 </Select>
               </div>
@@ -133,13 +233,16 @@ export default function ConnectionSettings({
                 <Label htmlFor="stop-bits" className="text-sm font-normal text-muted-foreground">Stop Bits</Label>
               </div>
               <div className="flex-1 max-w-xs">
-                <Select defaultValue="1">
+                <Select
+                  value={config.stop_bits}
+                  onValueChange={value => setConfig({ ...config, stop_bits: value as PortConfig["stop_bits"] })}
+                  disabled={connected}
+                >
                   <SelectTrigger className="h-11">
                     <SelectValue placeholder="Select stop bits" />
                   </SelectTrigger>
                   <SelectContent>
                     <SelectItem value="1">1 bit</SelectItem>
-                    <SelectItem value="1.5">1.5 bits</SelectItem>
                     <SelectItem value="2">2 bits</SelectItem>
                   </SelectContent>
                 </Select>
@@ -151,7 +254,11 @@ export default function ConnectionSettings({
                 <Label htmlFor="flow-control" className="text-sm font-normal text-muted-foreground">Flow Control</Label>
               </div>
               <div className="flex-1 max-w-xs">
-                <Select defaultValue="none">
+                <Select
+                  value={config.flow_control}
+                  onValueChange={value => setConfig({ ...config, flow_control: value as PortConfig["flow_control"] })}
+                  disabled={connected}
+                >
                   <SelectTrigger className="h-11">
                     <SelectValue placeholder="Select flow control" />
                   </SelectTrigger>
@@ -163,6 +270,54 @@ export default function ConnectionSettings({
                 </Select>
               </div>
             </div>
+
+            <div className="flex items-center justify-between gap-6">
+              <div className="flex-shrink-0 w-20">
+                <Label htmlFor="framing" className="text-sm font-normal text-muted-foreground">Framing</Label>
+              </div>
+              <div className="flex-1 max-w-xs">
+                <Select
+                  value={config.framing?.kind.type ?? "raw"}
+                  onValueChange={value => {
+                    const kind = FRAMINGS[value];
+                    setConfig({ ...config, framing: kind ? { kind } : null });
+                  }}
+                  disabled={connected}
+                >
+                  <SelectTrigger className="h-11">
+                    <SelectValue placeholder="Select framing" />
+                  </SelectTrigger>
+                  <SelectContent>
+                    <SelectItem value="raw">Raw chunks</SelectItem>
+                    <SelectItem value="lines">Lines (CR/LF/CRLF)</SelectItem>
+                    <SelectItem value="slip">SLIP</SelectItem>
+                    <SelectItem value="cobs">COBS</SelectItem>
+                    <SelectItem value="hdlc">HDLC (FCS-16)</SelectItem>
+                  </SelectContent>
+                </Select>
+              </div>
+            </div>
+
+            <div className="flex items-center justify-between gap-6">
+              <div className="flex-shrink-0 w-20">
+                <Label htmlFor="auto-reconnect" className="text-sm font-normal text-muted-foreground">Reconnect</Label>
+              </div>
+              <div className="flex-1 max-w-xs">
+                <Select
+                  value={autoReconnect ? "on" : "off"}
+                  onValueChange={value => setAutoReconnect(value === "on")}
+                  disabled={connected}
+                >
+                  <SelectTrigger className="h-11">
+                    <SelectValue placeholder="Select reconnect behaviour" />
+                  </SelectTrigger>
+                  <SelectContent>
+                    <SelectItem value="off">Off</SelectItem>
+                    <SelectItem value="on">When the device returns</SelectItem>
+                  </SelectContent>
+                </Select>
+              </div>
+            </div>
           </div>
         </div>
         
@@ -170,15 +325,27 @@ export default function ConnectionSettings({
         
         {/* Connection Actions */}
         <div className="flex flex-col sm:flex-row gap-3 pt-2">
-          <Button 
-            disabled={!selectedPort}
-            onClick={onConnect}
-            className="flex-1 h-11 font-medium"
-            size="lg"
-          >
-            <Zap className="mr-2 h-4 w-4" />
-            Connect to Port
-          </Button>
+          {connected ? (
+            <Button 
+              variant="destructive"
+              onClick={onDisconnect}
+              className="flex-1 h-11 font-medium"
+              size="lg"
+            >
+              <Unplug className="mr-2 h-4 w-4" />
+              Disconnect
+            </Button>
+          ) : (
+            <Button 
+              disabled={!selectedPort}
+              onClick={() => onConnect?.(config, autoReconnect)}
+              className="flex-1 h-11 font-medium"
+              size="lg"
+            >
+              <Zap className="mr-2 h-4 w-4" />
+              Connect to Port
+            </Button>
+          )}
           <Button 
             variant="outline"
             disabled={!selectedPort}
@@ -0,0 +1,119 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Button } from "../ui/button";
+import { Badge } from "../ui/badge";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "../ui/select";
+import { RotateCcw } from "lucide-react";
+
+interface ControlLinesProps {
+  readonly session: number;
+}
+
+// Mirrors ControlLines in serial_manager
+interface Lines {
+  cts: boolean;
+  dsr: boolean;
+  ri: boolean;
+  cd: boolean;
+}
+
+// Mirrors ResetSequence in serial_manager; only the names are needed here
+interface ResetSequence {
+  name: string;
+  description: string;
+}
+
+const BREAK_DURATION_MS = 250;
+
+export default function ControlLines({ session }: ControlLinesProps) {
+  const [dtr, setDtr] = useState(true);
+  const [rts, setRts] = useState(true);
+  const [lines, setLines] = useState<Lines | undefined>();
+  const [sequences, setSequences] = useState<ResetSequence[]>([]);
+  const [sequence, setSequence] = useState<string | undefined>();
+
+  useEffect(() => {
+    invoke<ResetSequence[]>("list_reset_sequences").then(setSequences);
+  }, []);
+
+  // Watch the device's lines for as long as this session is shown
+  useEffect(() => {
+    setLines(undefined);
+    const unlisten = listen<{ type: string; data: { session?: number; lines?: Lines } }>('serial-device-event', (event) => {
+      const { type, data } = event.payload;
+      if (type === 'ControlLinesChanged' && data.session === session) {
+        setLines(data.lines);
+      }
+    });
+    invoke("watch_control_lines", { session, enabled: true })
+      .catch(err => console.error("Failed to watch control lines:", err));
+    return () => {
+      unlisten.then(f => f());
+      invoke("watch_control_lines", { session, enabled: false }).catch(() => {});
+    };
+  }, [session]);
+
+  const setLine = async (line: "dtr" | "rts", asserted: boolean) => {
+    try {
+      await invoke("set_control_lines", { session, [line]: asserted });
+      (line === "dtr" ? setDtr : setRts)(asserted);
+    } catch (err) {
+      console.error(`Failed to set ${line.toUpperCase()}:`, err);
+    }
+  };
+
+  const run = async (action: string, args: Record<string, unknown>) => {
+    try {
+      await invoke(action, { session, ...args });
+    } catch (err) {
+      console.error(`${action} failed:`, err);
+    }
+  };
+
+  return (
+    <div className="space-y-2">
+      <div className="flex gap-1">
+        <Button size="sm" variant={dtr ? "default" : "outline"} onClick={() => setLine("dtr", !dtr)}>DTR</Button>
+        <Button size="sm" variant={rts ? "default" : "outline"} onClick={() => setLine("rts", !rts)}>RTS</Button>
+        <Button size="sm" variant="outline" onClick={() => run("send_break", { durationMs: BREAK_DURATION_MS })}>Break</Button>
+      </div>
+
+      {lines && (
+        <div className="flex gap-1">
+          {(["cts", "dsr", "ri", "cd"] as const).map(line => (
+            <Badge key={line} variant={lines[line] ? "default" : "outline"}>{line.toUpperCase()}</Badge>
+          ))}
+        </div>
+      )}
+
+      <div className="flex gap-1">
+        <Select value={sequence} onValueChange={setSequence}>
+          <SelectTrigger className="h-8 text-xs">
+            <SelectValue placeholder="Reset sequence" />
+          </SelectTrigger>
+          <SelectContent>
+            {sequences.map(s => (
+              <SelectItem key={s.name} value={s.name} title={s.description}>{s.name}</SelectItem>
+            ))}
+          </SelectContent>
+        </Select>
+        <Button
+          size="sm"
+          variant="outline"
+          disabled={!sequence}
+          onClick={() => run("run_reset_sequence", { name: sequence })}
+          title="Run reset sequence"
+        >
+          <RotateCcw className="h-4 w-4" />
+        </Button>
+      </div>
+    </div>
+  );
+}
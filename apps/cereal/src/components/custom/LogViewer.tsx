@@ -1,84 +1,279 @@
 import { useRef, useState, useEffect } from "react";
 import { useVirtualizer } from "@tanstack/react-virtual";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Button } from "../ui/button";
+import { Input } from "../ui/input";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "../ui/select";
 
-const TOTAL_LINES = 1_000_000;
-const CHUNK_SIZE = 1000;
+// A line of a session's scrollback, as get_log_chunk returns it
+interface LogLine {
+  direction: "rx" | "tx";
+  timestamp: number;
+  text: string;
+}
+
+// Payload of the "serial-log-appended" event
+interface LogAppended {
+  session: number;
+  length: number;
+}
+
+// Payload of the "serial-log-incomplete" event, sent when data is missing from the log
+interface LogIncomplete {
+  session: number;
+  missed: number;
+}
+
+// Payload of the "serial-search-matches" event, sent as a search goes through the log
+interface SearchMatches {
+  session: number;
+  search: number;
+  lines: number[];
+  searched: number;
+  total: number;
+}
+
+type SearchKind = "literal" | "case_insensitive" | "regex";
+
+interface SearchProgress {
+  matches: number;
+  searched: number;
+  total: number;
+}
 
+// Lines kept in memory; the rest are fetched again when scrolled back into view
+const MAX_CACHED_LINES = 10_000;
 
-// Real backend fetch function via Tauri
-async function fetchLogChunk(offset: number, limit: number): Promise<string[]> {
-  return await invoke("get_log_chunk", { offset, limit });
+function formatTime(timestamp: number): string {
+  const time = new Date(timestamp);
+  return `${time.toLocaleTimeString([], { hour12: false })}.${String(time.getMilliseconds()).padStart(3, "0")}`;
 }
 
-export default function VirtualLogViewer() {
+export default function LogViewer({ session }: { session?: number }) {
   const parentRef = useRef<HTMLDivElement>(null);
-  const [logData, setLogData] = useState<Map<number, string>>(new Map());
+  const lines = useRef(new Map<number, LogLine>());
+  const loading = useRef(new Set<number>());
+  const [length, setLength] = useState(0);
+  const [, setLoaded] = useState(0);
+  const [follow, setFollow] = useState(true);
+  const [pattern, setPattern] = useState("");
+  const [kind, setKind] = useState<SearchKind>("case_insensitive");
+  const [searchId, setSearchId] = useState<number | undefined>();
+  // Progress by search id; events can arrive before start_search returns the id
+  const progress = useRef(new Map<number, SearchProgress>());
+  const [searchError, setSearchError] = useState<string | undefined>();
+  const [currentMatch, setCurrentMatch] = useState<number | undefined>();
+  const [missed, setMissed] = useState(0);
+
+  useEffect(() => {
+    setSearchId(undefined);
+    setCurrentMatch(undefined);
+    progress.current.clear();
+    lines.current.clear();
+    loading.current.clear();
+    setLength(0);
+    setMissed(0);
+    if (session === undefined) return;
+
+    invoke<number>("get_log_length", { session })
+      .then(setLength)
+      .catch(err => console.error(`Failed to get the log length of session ${session}:`, err));
+    const unlisten = listen<LogAppended>("serial-log-appended", (event) => {
+      if (event.payload.session !== session) return;
+      setLength(prev => {
+        // The last line may have been in progress, so fetch it again
+        lines.current.delete(prev - 1);
+        return event.payload.length;
+      });
+    });
+    const unlistenIncomplete = listen<LogIncomplete>("serial-log-incomplete", (event) => {
+      if (event.payload.session !== session) return;
+      setMissed(prev => prev + event.payload.missed);
+    });
+    const unlistenSearch = listen<SearchMatches>("serial-search-matches", (event) => {
+      const { search: id, lines: found, searched, total } = event.payload;
+      if (event.payload.session !== session) return;
+      const matches = (progress.current.get(id)?.matches ?? 0) + found.length;
+      progress.current.set(id, { matches, searched, total });
+      setLoaded(prev => prev + 1);
+    });
+    return () => {
+      unlisten.then(f => f());
+      unlistenIncomplete.then(f => f());
+      unlistenSearch.then(f => f());
+      invoke("stop_search", { session }).catch(() => {});
+    };
+  }, [session]);
 
   const rowVirtualizer = useVirtualizer({
-    count: TOTAL_LINES,
+    count: length,
     getScrollElement: () => parentRef.current,
     estimateSize: () => 24,
     overscan: 20,
   });
+  const items = rowVirtualizer.getVirtualItems();
+  const first = items.length > 0 ? items[0].index : 0;
+  const last = items.length > 0 ? items[items.length - 1].index : -1;
 
-  // Fetch logs when visible rows change
+  // Fetch the lines in view that are not in memory yet
   useEffect(() => {
-    const virtualItems = rowVirtualizer.getVirtualItems();
-    if (!virtualItems.length) return;
+    if (session === undefined || last < first) return;
+    let offset = first;
+    while (offset <= last && (lines.current.has(offset) || loading.current.has(offset))) offset++;
+    if (offset > last) return;
 
-    const start = Math.max(0, virtualItems[0].index - CHUNK_SIZE);
-    const end = Math.min(TOTAL_LINES, virtualItems.at(-1)!.index + CHUNK_SIZE);
+    const limit = last - offset + 1;
+    for (let index = offset; index <= last; index++) loading.current.add(index);
+    invoke<LogLine[]>("get_log_chunk", { session, offset, limit })
+      .then((chunk) => {
+        if (lines.current.size + chunk.length > MAX_CACHED_LINES) lines.current.clear();
+        chunk.forEach((line, i) => lines.current.set(offset + i, line));
+        setLoaded(prev => prev + 1);
+      })
+      .catch(err => console.error(`Failed to read the log of session ${session}:`, err))
+      .finally(() => {
+        for (let index = offset; index < offset + limit; index++) loading.current.delete(index);
+      });
+  }, [session, first, last, length]);
 
-    // Fetch missing lines
-    const missing = [] as number[];
-    for (let i = start; i < end; i++) {
-      if (!logData.has(i)) missing.push(i);
+  // Keep the newest lines in view unless the user scrolled up
+  useEffect(() => {
+    if (follow && length > 0) {
+      rowVirtualizer.scrollToIndex(length - 1, { align: "end" });
     }
+  }, [length, follow]);
 
-    if (missing.length) {
-      const offset = missing[0];
-      const limit = missing.length;
-
-      fetchLogChunk(offset, limit).then((lines) => {
-        setLogData((prev) => {
-          const next = new Map(prev);
-          lines.forEach((line, i) => {
-            next.set(offset + i, line);
-          });
-          return next;
-        });
-      });
+  const onScroll = () => {
+    const element = parentRef.current;
+    if (!element) return;
+    setFollow(element.scrollHeight - element.scrollTop - element.clientHeight < 48);
+  };
+
+  const startSearch = async () => {
+    if (session === undefined) return;
+    setCurrentMatch(undefined);
+    setSearchError(undefined);
+    setSearchId(undefined);
+    progress.current.clear();
+    if (pattern === "") {
+      await invoke("stop_search", { session });
+      return;
     }
-  }, [rowVirtualizer.getVirtualItems()]);
+    try {
+      setSearchId(await invoke<number>("start_search", { session, query: { pattern, kind } }));
+    } catch (err) {
+      setSearchError(String(err));
+    }
+  };
+
+  const goToMatch = async (command: "next_match" | "previous_match") => {
+    if (session === undefined || searchId === undefined) return;
+    try {
+      const from = currentMatch ?? (command === "next_match" ? Math.max(first - 1, 0) : last + 1);
+      const line = await invoke<number | null>(command, { session, from });
+      if (line === null) return;
+      setCurrentMatch(line);
+      setFollow(false);
+      rowVirtualizer.scrollToIndex(line, { align: "center" });
+    } catch (err) {
+      console.error("Failed to go to the match:", err);
+    }
+  };
+
+  const search = searchId !== undefined ? progress.current.get(searchId) : undefined;
+
+  if (session === undefined) {
+    return <div className="p-4 text-sm text-muted-foreground">Connect the selected port to view its log</div>;
+  }
 
   return (
-    <div ref={parentRef} style={{ height: "100vh", overflow: "auto" }}>
-      <div
-        style={{
-          height: `${rowVirtualizer.getTotalSize()}px`,
-          position: "relative",
+    <div className="flex h-full flex-col">
+      <form
+        className="flex items-center gap-2 border-b px-2 py-2"
+        onSubmit={(event) => {
+          event.preventDefault();
+          startSearch();
         }}
       >
-        {rowVirtualizer.getVirtualItems().map((virtualRow) => (
-          <div
-            key={virtualRow.key}
-            style={{
-              position: "absolute",
-              top: 0,
-              left: 0,
-              width: "100%",
-              transform: `translateY(${virtualRow.start}px)`,
-              height: `${virtualRow.size}px`,
-//              fontFamily: "monospace",
-              whiteSpace: "pre",
-              padding: "2px 8px",
-              boxSizing: "border-box",
-            }}
-          >
-            {logData.get(virtualRow.index) ?? "Loading..."}
-          </div>
-        ))}
+        <Input
+          className="h-8 max-w-sm font-mono text-xs"
+          placeholder="Search the log"
+          value={pattern}
+          onChange={(event) => setPattern(event.target.value)}
+        />
+        <Select value={kind} onValueChange={(value) => setKind(value as SearchKind)}>
+          <SelectTrigger className="h-8 w-40 text-xs">
+            <SelectValue />
+          </SelectTrigger>
+          <SelectContent>
+            <SelectItem value="literal">Match case</SelectItem>
+            <SelectItem value="case_insensitive">Ignore case</SelectItem>
+            <SelectItem value="regex">Regex</SelectItem>
+          </SelectContent>
+        </Select>
+        <Button type="submit" size="sm" variant="outline">Search</Button>
+        <Button type="button" size="sm" variant="ghost" disabled={!search?.matches} onClick={() => goToMatch("previous_match")}>↑</Button>
+        <Button type="button" size="sm" variant="ghost" disabled={!search?.matches} onClick={() => goToMatch("next_match")}>↓</Button>
+        <span className="text-xs text-muted-foreground">
+          {searchError
+            ?? (search
+              ? `${search.matches.toLocaleString()} matches${search.searched < search.total ? `, searched ${search.searched.toLocaleString()} of ${search.total.toLocaleString()} lines` : ""}`
+              : searchId !== undefined && "Searching…")}
+        </span>
+        {missed > 0 && (
+          <span className="ml-auto text-xs text-destructive">
+            Log incomplete, {missed.toLocaleString()} events dropped
+          </span>
+        )}
+      </form>
+      <div ref={parentRef} onScroll={onScroll} className="min-h-0 flex-1" style={{ overflow: "auto" }}>
+        <div
+          style={{
+            height: `${rowVirtualizer.getTotalSize()}px`,
+            position: "relative",
+          }}
+        >
+          {items.map((virtualRow) => {
+            const line = lines.current.get(virtualRow.index);
+            return (
+              <div
+                key={virtualRow.key}
+                className={`flex gap-3 font-mono text-xs ${virtualRow.index === currentMatch ? "bg-yellow-100 dark:bg-yellow-900" : ""}`}
+                style={{
+                  position: "absolute",
+                  top: 0,
+                  left: 0,
+                  width: "100%",
+                  transform: `translateY(${virtualRow.start}px)`,
+                  height: `${virtualRow.size}px`,
+                  whiteSpace: "pre",
+                  padding: "2px 8px",
+                  boxSizing: "border-box",
+                }}
+              >
+                <span className="w-16 shrink-0 text-right text-muted-foreground">{virtualRow.index + 1}</span>
+                {line ? (
+                  <>
+                    <span className="w-24 shrink-0 text-muted-foreground">{formatTime(line.timestamp)}</span>
+                    <span className={`w-6 shrink-0 ${line.direction === "tx" ? "text-blue-500" : "text-green-600"}`}>
+                      {line.direction === "tx" ? "TX" : "RX"}
+                    </span>
+                    <span className="overflow-hidden text-ellipsis">{line.text}</span>
+                  </>
+                ) : (
+                  <span className="text-muted-foreground">…</span>
+                )}
+              </div>
+            );
+          })}
+        </div>
       </div>
     </div>
   );
@@ -1,8 +1,12 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import SerialPortList from "./SerialPortList";
 import LogViewer from "./LogViewer";
-import ConnectionSettings from "./ConnectionSettings";
+import TimelineLog from "./TimelineLog";
+import ConnectionSettings, { PortConfig } from "./ConnectionSettings";
 import SerialTerminal from "./SerialTerminal";
+import ControlLines from "./ControlLines";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "../ui/tabs";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "../ui/card";
 import {
@@ -22,6 +26,70 @@ import { Settings, Zap, Terminal } from "lucide-react";
 
 export default function SerialDashboard() {
   const [selectedPort, setSelectedPort] = useState<string | undefined>();
+  // Open sessions by port; several ports can be open at once and share the log
+  const [sessions, setSessions] = useState<Record<string, number>>({});
+  const [reconnecting, setReconnecting] = useState<number[]>([]);
+  const session = selectedPort !== undefined ? sessions[selectedPort] : undefined;
+
+  const forgetSession = (id: number) => {
+    setSessions(prev => Object.fromEntries(Object.entries(prev).filter(([, open]) => open !== id)));
+    setReconnecting(prev => prev.filter(waiting => waiting !== id));
+  };
+
+  // Track sessions through disconnects and forget them once the backend reports them closed
+  useEffect(() => {
+    const unlisten = listen<{ type: string; data: { session?: number; port_name?: string; reason?: string } }>('serial-device-event', (event) => {
+      const { type, data } = event.payload;
+      const id = data.session;
+      if (id === undefined) return;
+      if (type === 'SessionClosed') {
+        console.log(`🔌 Session ${id} closed: ${data.reason}`);
+        forgetSession(id);
+      } else if (type === 'SessionDisconnected') {
+        console.log(`🔌 Session ${id} waiting for device: ${data.reason}`);
+        setReconnecting(prev => prev.includes(id) ? prev : [...prev, id]);
+      } else if (type === 'SessionReconnected') {
+        // The device may have come back under a different name
+        setSessions(prev => ({
+          ...Object.fromEntries(Object.entries(prev).filter(([, open]) => open !== id)),
+          [data.port_name!]: id,
+        }));
+        setReconnecting(prev => prev.filter(waiting => waiting !== id));
+      }
+    });
+    // Devices with an auto-connect profile are opened by the backend as they arrive
+    const unlistenProfiles = listen<{ profile: string; port_name: string; session: number }>('serial-profile-connected', (event) => {
+      const { profile, port_name, session: id } = event.payload;
+      console.log(`🔌 ${port_name} connected with profile ${profile}`);
+      setSessions(prev => ({ ...prev, [port_name]: id }));
+    });
+    return () => {
+      unlisten.then(f => f());
+      unlistenProfiles.then(f => f());
+    };
+  }, []);
+
+  const connect = async (config: PortConfig, autoReconnect: boolean) => {
+    if (!selectedPort) return;
+    try {
+      const id = await invoke<number>("open_port", { portName: selectedPort, config, autoReconnect });
+      setSessions(prev => ({ ...prev, [selectedPort]: id }));
+    } catch (err) {
+      console.error(`Failed to open ${selectedPort}:`, err);
+    }
+  };
+
+  const disconnect = async () => {
+    if (session === undefined) return;
+    try {
+      await invoke("close_port", { session });
+    } catch (err) {
+      console.error("Failed to close session:", err);
+    }
+    forgetSession(session);
+  };
+
+  const openPorts = Object.keys(sessions).sort();
 
   return (
     <SidebarProvider defaultOpen={true}>
@@ -56,8 +124,13 @@ export default function SerialDashboard() {
                       <CardContent className="p-3">
                         <div className="text-sm font-mono">{selectedPort}</div>
                         <div className="text-xs text-muted-foreground mt-1">
-                          Ready to connect
+                          {session === undefined ? "Ready to connect" : reconnecting.includes(session) ? "Waiting for device..." : "Connected"}
                         </div>
+                        {session !== undefined && !reconnecting.includes(session) && (
+                          <div className="mt-3">
+                            <ControlLines session={session} />
+                          </div>
+                        )}
                       </CardContent>
                     </Card>
                   </div>
@@ -102,8 +175,9 @@ export default function SerialDashboard() {
           
           <div className="flex-1 p-4">
             <Tabs defaultValue="logs" className="h-full flex flex-col">
-              <TabsList className="grid w-full grid-cols-3 max-w-lg">
+              <TabsList className="grid w-full grid-cols-4 max-w-xl">
                 <TabsTrigger value="logs">Log Viewer</TabsTrigger>
+                <TabsTrigger value="timeline">Timeline</TabsTrigger>
                 <TabsTrigger value="connection">Connection</TabsTrigger>
                 <TabsTrigger value="terminal">Serial Terminal</TabsTrigger>
               </TabsList>
@@ -111,16 +185,33 @@ export default function SerialDashboard() {
               <TabsContent value="logs" className="flex-1 mt-4">
                 <Card className="h-full">
                   <CardHeader>
-                    <CardTitle>Serial Port Logs</CardTitle>
+                    <CardTitle>Serial Port Log</CardTitle>
+                    <CardDescription>
+                      {session !== undefined
+                        ? `Everything ${selectedPort} received and sent since it was opened`
+                        : "Connect a serial port to view its log"
+                      }
+                    </CardDescription>
+                  </CardHeader>
+                  <CardContent className="p-0 h-[calc(100%-80px)]">
+                    <LogViewer session={session} />
+                  </CardContent>
+                </Card>
+              </TabsContent>
+
+              <TabsContent value="timeline" className="flex-1 mt-4">
+                <Card className="h-full">
+                  <CardHeader>
+                    <CardTitle>Timeline</CardTitle>
                     <CardDescription>
-                      {selectedPort 
-                        ? `Viewing logs from ${selectedPort}` 
-                        : "Select a serial port to view logs"
+                      {openPorts.length > 0
+                        ? `Combined log of ${openPorts.join(", ")}, in the order the data was sent and received`
+                        : "Connect one or more serial ports to view their logs"
                       }
                     </CardDescription>
                   </CardHeader>
                   <CardContent className="p-0 h-[calc(100%-80px)]">
-                    <LogViewer />
+                    <TimelineLog />
                   </CardContent>
                 </Card>
               </TabsContent>
@@ -128,13 +219,15 @@ export default function SerialDashboard() {
               <TabsContent value="connection" className="flex-1 mt-4">
                 <ConnectionSettings 
                   selectedPort={selectedPort}
-                  onConnect={() => console.log('Connect clicked')}
+                  connected={session !== undefined}
+                  onConnect={connect}
+                  onDisconnect={disconnect}
                   onTest={() => console.log('Test clicked')}
                 />
               </TabsContent>
               
               <TabsContent value="terminal" className="flex-1 mt-4">
-                <SerialTerminal selectedPort={selectedPort} />
+                <SerialTerminal selectedPort={selectedPort} session={session} />
               </TabsContent>
             </Tabs>
           </div>
@@ -8,23 +8,111 @@ interface SerialPortListProps {
     readonly selectedPort?: string;
 }
 
+// Port metadata reported by the Rust backend
+export interface PortInfo {
+    port_name: string;
+    port_type: "Usb" | "Pci" | "Bluetooth" | "Virtual" | "Unknown";
+    vid: number | null;
+    pid: number | null;
+    serial_number: string | null;
+    manufacturer: string | null;
+    product: string | null;
+    by_id: string | null;
+    location: string | null;
+}
+
 // Type definition for the serial events from Rust
-interface TauriSerialEvent {
-    type: "DeviceArrived" | "DeviceDeparted";
-    data: {
-        port_name: string;
-        timestamp: number;
+type TauriSerialEvent =
+    | {
+        type: "DeviceArrived" | "DeviceDeparted";
+        data: {
+            port: PortInfo;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "DeviceReenumerated";
+        data: {
+            port: PortInfo;
+            identity: unknown;
+            stability: { reenumerations: number; unstable: boolean };
+            timestamp: number;
+        };
+    }
+    | {
+        type: "DeviceMoved";
+        data: {
+            from: PortInfo;
+            to: PortInfo;
+            identity: unknown;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "DiscoveryError";
+        data: {
+            message: string;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "SessionClosed" | "SessionDisconnected";
+        data: {
+            session: number;
+            port_name: string;
+            reason: string;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "ControlLinesChanged";
+        data: {
+            session: number;
+            lines: { cts: boolean; dsr: boolean; ri: boolean; cd: boolean };
+            timestamp: number;
+        };
+    }
+    | {
+        type: "SessionReconnected";
+        data: {
+            session: number;
+            port_name: string;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "TransmitProgress";
+        data: {
+            session: number;
+            transmit: number;
+            sent: number;
+            total: number;
+            timestamp: number;
+        };
+    }
+    | {
+        type: "TransmitFinished";
+        data: {
+            session: number;
+            transmit: number;
+            completed: boolean;
+            outcome: string;
+            timestamp: number;
+        };
     };
-}
+
+// Human readable device name, mirroring PortInfo::display_name in Rust
+const displayName = (port: PortInfo) => port.product ?? port.manufacturer ?? port.port_name;
 
 export default function SerialPortList({ onPortSelect, selectedPort }: SerialPortListProps) {
-    const [ports, setPorts] = useState<string[]>([]);
+    const [ports, setPorts] = useState<PortInfo[]>([]);
     const [recentEvents, setRecentEvents] = useState<TauriSerialEvent[]>([]);
+    const [unstablePorts, setUnstablePorts] = useState<Set<string>>(new Set());
 
     // Fetch serial ports
     const fetchPorts = async () => {
         try {
-            const result = await invoke<string[]>("get_serial_ports");
+            const result = await invoke<PortInfo[]>("get_serial_ports");
             setPorts(result);
         } catch (err) {
             console.error("Failed to fetch serial ports:", err);
@@ -46,27 +134,67 @@ export default function SerialPortList({ onPortSelect, selectedPort }: SerialPor
                     setRecentEvents(prev => [...prev.slice(-9), serialEvent]);
 
                     // Update port list in real-time
+                    if (serialEvent.type === 'DeviceMoved') {
+                        const { from, to } = serialEvent.data;
+                        setPorts(prev => [...prev.filter(port => port.port_name !== from.port_name && port.port_name !== to.port_name), to]
+                            .sort((a, b) => a.port_name.localeCompare(b.port_name)));
+
+                        // Follow the device to its new port
+                        if (selectedPort === from.port_name) {
+                            onPortSelect?.(to.port_name);
+                        }
+
+                        console.log(`🔌 Device moved: ${from.port_name} -> ${to.port_name}`);
+                        return;
+                    }
+
+                    if (serialEvent.type === 'DiscoveryError') {
+                        console.warn(`🔌 Discovery error: ${serialEvent.data.message}`);
+                        return;
+                    }
+
+                    // Sessions are tracked by the dashboard
+                    if (serialEvent.type === 'SessionClosed' || serialEvent.type === 'SessionDisconnected' || serialEvent.type === 'SessionReconnected' || serialEvent.type === 'ControlLinesChanged'
+                        || serialEvent.type === 'TransmitProgress' || serialEvent.type === 'TransmitFinished') {
+                        return;
+                    }
+
+                    if (serialEvent.type === 'DeviceReenumerated') {
+                        const { port, stability } = serialEvent.data;
+                        setPorts(prev => prev.map(p => p.port_name === port.port_name ? port : p));
+                        setUnstablePorts(prev => {
+                            const next = new Set(prev);
+                            if (stability.unstable) next.add(port.port_name); else next.delete(port.port_name);
+                            return next;
+                        });
+
+                        console.log(`🔌 Device re-enumerated: ${port.port_name} (${stability.reenumerations} times)`);
+                        return;
+                    }
+
+                    const portName = serialEvent.data.port.port_name;
                     if (serialEvent.type === 'DeviceArrived') {
                         setPorts(prev => {
                             // Only add if not already present
-                            if (!prev.includes(serialEvent.data.port_name)) {
-                                return [...prev, serialEvent.data.port_name].sort();
+                            if (!prev.some(port => port.port_name === portName)) {
+                                return [...prev, serialEvent.data.port]
+                                    .sort((a, b) => a.port_name.localeCompare(b.port_name));
                             }
                             return prev;
                         });
 
                         // Show a brief visual indication
-                        console.log(`🔌 Device connected: ${serialEvent.data.port_name}`);
+                        console.log(`🔌 Device connected: ${portName}`);
 
                     } else if (serialEvent.type === 'DeviceDeparted') {
-                        setPorts(prev => prev.filter(port => port !== serialEvent.data.port_name));
+                        setPorts(prev => prev.filter(port => port.port_name !== portName));
 
                         // Clear selection if the selected port was disconnected
-                        if (selectedPort === serialEvent.data.port_name) {
+                        if (selectedPort === portName) {
                             onPortSelect?.('');
                         }
 
-                        console.log(`🔌 Device disconnected: ${serialEvent.data.port_name}`);
+                        console.log(`🔌 Device disconnected: ${portName}`);
                     }
                 });
 
@@ -87,19 +215,30 @@ export default function SerialPortList({ onPortSelect, selectedPort }: SerialPor
         };
     }, [selectedPort, onPortSelect]);
 
-    // Get port type/platform indicator
-    const getPortType = (port: string) => {
-        if (port.startsWith("COM")) return "Windows";
-        if (port.startsWith("/dev/tty")) return "Linux/Unix";
-        if (port.startsWith("/dev/cu.")) return "macOS";
-        return "Unknown";
+    // Ask the backend to rescan now; changes arrive as device events
+    const refreshPorts = async () => {
+        try {
+            await invoke("rescan_ports");
+        } catch (err) {
+            console.error("Failed to rescan serial ports:", err);
+        }
+    };
+
+    // Get a short description of the device behind the port
+    const getPortDetails = (port: PortInfo) => {
+        if (port.vid !== null && port.pid !== null) {
+            const id = `${port.vid.toString(16).padStart(4, "0")}:${port.pid.toString(16).padStart(4, "0")}`;
+            return `${port.port_name} · USB ${id}`;
+        }
+        return port.port_name;
     };
 
     // Get port icon based on type
-    const getPortIcon = (port: string) => {
-        if (port.includes("usb") || port.includes("USB")) return "🔌";
-        if (port.includes("bluetooth") || port.includes("Bluetooth")) return "📶";
-        if (port.startsWith("COM")) return "💻";
+    const getPortIcon = (port: PortInfo) => {
+        if (port.port_type === "Usb") return "🔌";
+        if (port.port_type === "Bluetooth") return "📶";
+        if (port.port_type === "Virtual") return "🧪";
+        if (port.port_name.startsWith("COM")) return "💻";
         return "⚡";
     };
 
@@ -107,14 +246,19 @@ export default function SerialPortList({ onPortSelect, selectedPort }: SerialPor
     const isRecentlyConnected = (port: string) => {
         const now = Date.now();
         return recentEvents.some(event =>
-            event.type === 'DeviceArrived' &&
-            event.data.port_name === port &&
+            ((event.type === 'DeviceArrived' && event.data.port.port_name === port) ||
+                (event.type === 'DeviceMoved' && event.data.to.port_name === port)) &&
             now - event.data.timestamp < 3000
         );
     };
 
     return (
         <div className="w-full">
+            <div className="flex justify-end mb-1">
+                <Button variant="ghost" size="sm" className="h-6 px-2 text-xs" onClick={refreshPorts}>
+                    Refresh
+                </Button>
+            </div>
             {ports.length === 0 ? (
                 <div className="text-center py-4 text-gray-500">
                     <p className="text-xs">No ports found</p>
@@ -123,32 +267,35 @@ export default function SerialPortList({ onPortSelect, selectedPort }: SerialPor
                 <div className="space-y-1">
                     {ports.map((port) => (
                         <Button
-                            key={port}
-                            variant={selectedPort === port ? "default" : "outline"}
+                            key={port.port_name}
+                            variant={selectedPort === port.port_name ? "default" : "outline"}
                             size="sm"
-                            className={`w-full justify-start h-auto min-h-[2.5rem] p-3 text-left whitespace-normal ${selectedPort === port
+                            className={`w-full justify-start h-auto min-h-[2.5rem] p-3 text-left whitespace-normal ${selectedPort === port.port_name
                                     ? "bg-primary text-primary-foreground"
                                     : "bg-background hover:bg-accent"
-                                } ${isRecentlyConnected(port)
+                                } ${isRecentlyConnected(port.port_name)
                                     ? "ring-2 ring-green-400 bg-green-50 animate-pulse"
                                     : ""
                                 }`}
-                            onClick={() => onPortSelect?.(port)}
+                            onClick={() => onPortSelect?.(port.port_name)}
                         >
                             <div className="flex items-start space-x-2 w-full">
                                 <span className="text-sm flex-shrink-0 mt-0.5">
                                     {getPortIcon(port)}
-                                    {isRecentlyConnected(port) && (
+                                    {isRecentlyConnected(port.port_name) && (
                                         <span className="ml-1 text-green-500">●</span>
                                     )}
                                 </span>
                                 <div className="min-w-0 flex-1 text-left">
                                     <div className="font-medium text-xs break-all leading-relaxed">
-                                        {port}
+                                        {displayName(port)}
                                     </div>
                                     <div className="text-muted-foreground text-xs mt-0.5">
-                                        {getPortType(port)}
-                                        {isRecentlyConnected(port) && (
+                                        {getPortDetails(port)}
+                                        {unstablePorts.has(port.port_name) && (
+                                            <span className="text-amber-600 ml-1">(unstable)</span>
+                                        )}
+                                        {isRecentlyConnected(port.port_name) && (
                                             <span className="text-green-600 ml-1">(just connected)</span>
                                         )}
                                     </div>
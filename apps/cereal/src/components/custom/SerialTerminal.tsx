@@ -1,10 +1,164 @@
+import { KeyboardEvent, useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "../ui/card";
 
 interface SerialTerminalProps {
   readonly selectedPort?: string;
+  readonly session?: number;
 }
 
-export default function SerialTerminal({ selectedPort }: SerialTerminalProps) {
+// Payload of the "serial-data" event
+type SerialData =
+  | {
+    type: "DataReceived";
+    data: {
+      session: number;
+      bytes: number[];
+      host_timestamp: number;
+    };
+  }
+  | {
+    type: "DataSent";
+    data: {
+      session: number;
+      bytes: number[];
+      host_timestamp: number;
+    };
+  }
+  | {
+    type: "FrameReceived";
+    data: {
+      session: number;
+      bytes: number[];
+      status: "complete" | "flushed" | "truncated" | "malformed";
+      crc_ok: boolean | null;
+      host_timestamp: number;
+    };
+  }
+  | {
+    type: "DataGap";
+    data: {
+      session: number;
+      since: number;
+      until: number;
+    };
+  };
+
+type LineEnding = "none" | "lf" | "cr" | "crlf";
+
+// How send_data turns the typed payload into bytes
+type SendMode =
+  | { type: "text"; line_ending: LineEnding }
+  | { type: "escaped"; line_ending: LineEnding }
+  | { type: "hex" };
+
+interface HistoryEntry {
+  payload: string;
+  mode: SendMode;
+}
+
+// Keep the terminal responsive by only holding on to the tail of the output
+const MAX_OUTPUT_LENGTH = 64 * 1024;
+
+// One frame per line, with its status when it is not a clean frame
+function formatFrame(frame: { bytes: number[]; status: string; crc_ok: boolean | null }): string {
+  const notes = [
+    frame.status !== "complete" ? frame.status : undefined,
+    frame.crc_ok === false ? "bad CRC" : undefined,
+  ].filter(Boolean);
+  const text = new TextDecoder().decode(new Uint8Array(frame.bytes));
+  return notes.length > 0 ? `${text} [${notes.join(", ")}]\n` : `${text}\n`;
+}
+
+export default function SerialTerminal({ selectedPort, session }: SerialTerminalProps) {
+  const [output, setOutput] = useState("");
+  const [input, setInput] = useState("");
+  const [modeType, setModeType] = useState<SendMode["type"]>("text");
+  const [lineEnding, setLineEnding] = useState<LineEnding>("crlf");
+  const [history, setHistory] = useState<HistoryEntry[]>([]);
+  // Position while recalling with the arrow keys, counted back from the latest command
+  const [recalled, setRecalled] = useState<number | undefined>();
+  const [sendError, setSendError] = useState<string | undefined>();
+
+  const loadHistory = () => {
+    if (session === undefined) return;
+    invoke<HistoryEntry[]>("get_command_history", { session })
+      .then(setHistory)
+      .catch(err => console.error("Failed to load the command history:", err));
+  };
+
+  useEffect(() => {
+    setOutput("");
+    setHistory([]);
+    setRecalled(undefined);
+    if (session === undefined) return;
+    loadHistory();
+
+    const decoder = new TextDecoder();
+    const unlisten = listen<SerialData>("serial-data", (event) => {
+      const payload = event.payload;
+      if (payload.data.session !== session) return;
+      let text: string;
+      switch (payload.type) {
+        case "DataSent":
+          // The terminal shows what the device sends; both directions are in the log
+          return;
+        case "DataGap":
+          text = `\n--- device disconnected for ${((payload.data.until - payload.data.since) / 1000).toFixed(1)}s ---\n`;
+          break;
+        case "FrameReceived":
+          text = formatFrame(payload.data);
+          break;
+        default:
+          text = decoder.decode(new Uint8Array(payload.data.bytes), { stream: true });
+      }
+      setOutput(prev => (prev + text).slice(-MAX_OUTPUT_LENGTH));
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, [session]);
+
+  const send = async () => {
+    if (session === undefined) return;
+    const mode: SendMode = modeType === "hex" ? { type: "hex" } : { type: modeType, line_ending: lineEnding };
+    try {
+      await invoke<number>("send_data", { session, payload: input, mode });
+      setInput("");
+      setRecalled(undefined);
+      setSendError(undefined);
+      loadHistory();
+    } catch (err) {
+      setSendError(String(err));
+    }
+  };
+
+  const recall = (back: number | undefined) => {
+    setRecalled(back);
+    if (back === undefined) {
+      setInput("");
+      return;
+    }
+    const entry = history[history.length - 1 - back];
+    setInput(entry.payload);
+    setModeType(entry.mode.type);
+    if (entry.mode.type !== "hex") setLineEnding(entry.mode.line_ending);
+  };
+
+  const onKeyDown = (event: KeyboardEvent<HTMLInputElement>) => {
+    if (event.key === "Enter") {
+      event.preventDefault();
+      send();
+    } else if (event.key === "ArrowUp" && history.length > 0) {
+      event.preventDefault();
+      recall(Math.min((recalled ?? -1) + 1, history.length - 1));
+    } else if (event.key === "ArrowDown" && recalled !== undefined) {
+      event.preventDefault();
+      recall(recalled > 0 ? recalled - 1 : undefined);
+    }
+  };
+
   return (
     <Card className="h-full">
       <CardHeader>
@@ -17,18 +171,48 @@ export default function SerialTerminal({ selectedPort }: SerialTerminalProps) {
         </CardDescription>
       </CardHeader>
       <CardContent className="h-[calc(100%-80px)]">
-        <div className="h-full bg-black text-green-400 font-mono p-4 rounded-md">
+        <div className="h-full bg-black text-green-400 font-mono p-4 rounded-md overflow-auto">
           {selectedPort ? (
             <div>
-              <div>Connected to {selectedPort}</div>
-              <div>Baud rate: 9600</div>
-              <div className="mt-4 text-gray-400">
-                # Terminal functionality coming soon...
-              </div>
-              <div className="mt-2">
-                <span className="text-green-400">$ </span>
-                <span className="animate-pulse">_</span>
-              </div>
+              <div>{session !== undefined ? `Connected to ${selectedPort}` : `Not connected to ${selectedPort}`}</div>
+              <pre className="mt-4 whitespace-pre-wrap break-all">{output}</pre>
+              {session !== undefined && (
+                <div className="mt-2 flex items-center gap-2">
+                  <span className="text-green-400">$</span>
+                  <input
+                    className="flex-1 bg-transparent text-green-400 outline-none"
+                    value={input}
+                    placeholder={modeType === "hex" ? "DE AD BE EF" : modeType === "escaped" ? "AT\\x1a" : ""}
+                    onChange={(event) => {
+                      setInput(event.target.value);
+                      setRecalled(undefined);
+                    }}
+                    onKeyDown={onKeyDown}
+                    autoFocus
+                  />
+                  <select
+                    className="bg-black text-xs text-gray-400"
+                    value={modeType}
+                    onChange={(event) => setModeType(event.target.value as SendMode["type"])}
+                  >
+                    <option value="text">Text</option>
+                    <option value="escaped">Escapes</option>
+                    <option value="hex">Hex</option>
+                  </select>
+                  <select
+                    className="bg-black text-xs text-gray-400"
+                    value={lineEnding}
+                    disabled={modeType === "hex"}
+                    onChange={(event) => setLineEnding(event.target.value as LineEnding)}
+                  >
+                    <option value="none">None</option>
+                    <option value="lf">LF</option>
+                    <option value="cr">CR</option>
+                    <option value="crlf">CRLF</option>
+                  </select>
+                </div>
+              )}
+              {sendError && <div className="mt-1 text-xs text-red-400">{sendError}</div>}
             </div>
           ) : (
             <div className="text-gray-400 text-center mt-20">
@@ -0,0 +1,123 @@
+import { useRef, useState, useEffect } from "react";
+import { useVirtualizer } from "@tanstack/react-virtual";
+import { listen } from "@tauri-apps/api/event";
+
+// Payload of the "serial-timeline" event: data of all open ports, in the order it was sent and received
+interface TimelineEntry {
+  session: number;
+  port_name: string;
+  direction: "rx" | "tx";
+  bytes: number[];
+  status: "complete" | "flushed" | "truncated" | "malformed" | null;
+  crc_ok: boolean | null;
+  elapsed_ms: number;
+  timestamp: number;
+}
+
+interface LogRow {
+  time: string;
+  port: string;
+  direction: "rx" | "tx";
+  text: string;
+}
+
+// Only the most recent rows are kept in memory
+const MAX_ROWS = 100_000;
+
+const decoder = new TextDecoder();
+
+// Control characters are shown escaped so each entry stays on one row
+function toRow(entry: TimelineEntry): LogRow {
+  const notes = [
+    entry.status && entry.status !== "complete" ? entry.status : undefined,
+    entry.crc_ok === false ? "bad CRC" : undefined,
+  ].filter(Boolean);
+  const text = decoder.decode(new Uint8Array(entry.bytes))
+    .replace(/\r/g, "\\r")
+    .replace(/\n/g, "\\n")
+    .replace(/\t/g, "\\t");
+  return {
+    time: `${(entry.elapsed_ms / 1000).toFixed(3)}s`,
+    port: entry.port_name,
+    direction: entry.direction,
+    text: notes.length > 0 ? `${text} [${notes.join(", ")}]` : text,
+  };
+}
+
+export default function TimelineLog() {
+  const parentRef = useRef<HTMLDivElement>(null);
+  const [rows, setRows] = useState<LogRow[]>([]);
+  const [follow, setFollow] = useState(true);
+
+  useEffect(() => {
+    const unlisten = listen<TimelineEntry[]>("serial-timeline", (event) => {
+      const added = event.payload.map(toRow);
+      setRows((prev) => {
+        const next = prev.concat(added);
+        return next.length > MAX_ROWS ? next.slice(next.length - MAX_ROWS) : next;
+      });
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
+
+  const rowVirtualizer = useVirtualizer({
+    count: rows.length,
+    getScrollElement: () => parentRef.current,
+    estimateSize: () => 24,
+    overscan: 20,
+  });
+
+  // Keep the newest rows in view unless the user scrolled up
+  useEffect(() => {
+    if (follow && rows.length > 0) {
+      rowVirtualizer.scrollToIndex(rows.length - 1, { align: "end" });
+    }
+  }, [rows.length, follow]);
+
+  const onScroll = () => {
+    const element = parentRef.current;
+    if (!element) return;
+    setFollow(element.scrollHeight - element.scrollTop - element.clientHeight < 48);
+  };
+
+  return (
+    <div ref={parentRef} onScroll={onScroll} style={{ height: "100vh", overflow: "auto" }}>
+      <div
+        style={{
+          height: `${rowVirtualizer.getTotalSize()}px`,
+          position: "relative",
+        }}
+      >
+        {rowVirtualizer.getVirtualItems().map((virtualRow) => {
+          const row = rows[virtualRow.index];
+          return (
+            <div
+              key={virtualRow.key}
+              className="flex gap-3 font-mono text-xs"
+              style={{
+                position: "absolute",
+                top: 0,
+                left: 0,
+                width: "100%",
+                transform: `translateY(${virtualRow.start}px)`,
+                height: `${virtualRow.size}px`,
+                whiteSpace: "pre",
+                padding: "2px 8px",
+                boxSizing: "border-box",
+              }}
+            >
+              <span className="w-20 shrink-0 text-right text-muted-foreground">{row.time}</span>
+              <span className="w-32 shrink-0 truncate">{row.port}</span>
+              <span className={`w-6 shrink-0 ${row.direction === "tx" ? "text-blue-500" : "text-green-600"}`}>
+                {row.direction === "tx" ? "TX" : "RX"}
+              </span>
+              <span className="overflow-hidden text-ellipsis">{row.text}</span>
+            </div>
+          );
+        })}
+      </div>
+    </div>
+  );
+}